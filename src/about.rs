@@ -0,0 +1,36 @@
+use eframe::egui;
+
+const EGUI_VERSION: &str = "0.32"; // Keep in step with Cargo.toml
+const ISSUES_URL: &str = concat!(env!("CARGO_PKG_REPOSITORY"), "/issues");
+
+/// Version and build details to quote in bug reports.
+fn build_info() -> String {
+    format!(
+        "{} {}\nBuilt for {}-{} ({})\negui {}",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::ARCH,
+        std::env::consts::OS,
+        if cfg!(debug_assertions) { "debug" } else { "release" },
+        EGUI_VERSION
+    )
+}
+
+pub fn show_about_window(ctx: &egui::Context, open: &mut bool) {
+    egui::Window::new("About").open(open).resizable(false).collapsible(false).show(ctx, |ui| {
+        ui.heading(crate::APP_TITLE);
+        let info = build_info();
+        ui.monospace(&info);
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            if ui.button("🐛 Report an issue").on_hover_text(ISSUES_URL).clicked()
+                && let Err(e) = open::that(ISSUES_URL)
+            {
+                log::warn!("Could not open {}: {}", ISSUES_URL, e);
+            }
+            if ui.button("📋 Copy version info").on_hover_text("Paste it into the issue").clicked() {
+                ui.ctx().copy_text(info);
+            }
+        });
+    });
+}
@@ -0,0 +1,59 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use eframe::egui;
+
+const HISTOGRAM_BINS: usize = 40;
+const HISTOGRAM_HEIGHT: f32 = 80.0;
+
+/// The images scoring below `threshold`, in the order given. Images without a score are left out.
+pub fn below_threshold(images: &[PathBuf], scores: &HashMap<PathBuf, f64>, threshold: f64) -> Vec<PathBuf> {
+    images
+        .iter()
+        .filter(|image| scores.get(*image).is_some_and(|score| *score < threshold))
+        .cloned()
+        .collect()
+}
+
+/// Counts of `scores` in equally wide bins on a log scale, as sharpness spreads over orders
+/// of magnitude, with the range they cover.
+fn histogram(scores: &[f64]) -> Option<(Vec<usize>, f64, f64)> {
+    let logs: Vec<f64> = scores.iter().map(|score| score.max(f64::MIN_POSITIVE).log10()).collect();
+    let low = logs.iter().copied().reduce(f64::min)?;
+    let high = logs.iter().copied().reduce(f64::max)?.max(low + 1e-6);
+    let mut bins = vec![0; HISTOGRAM_BINS];
+    for log in logs {
+        let bin = ((log - low) / (high - low) * HISTOGRAM_BINS as f64) as usize;
+        bins[bin.min(HISTOGRAM_BINS - 1)] += 1;
+    }
+    Some((bins, low, high))
+}
+
+/// Draws the distribution of `scores` with the part below `threshold` in red. Clicking or
+/// dragging on it moves the threshold; returns true when it did.
+pub fn show_histogram(ui: &mut egui::Ui, scores: &[f64], threshold: &mut f64) -> bool {
+    let Some((bins, low, high)) = histogram(scores) else {
+        return false;
+    };
+    let (response, painter) = ui.allocate_painter(egui::vec2(ui.available_width().max(240.0), HISTOGRAM_HEIGHT), egui::Sense::click_and_drag());
+    let rect = response.rect;
+    let x_of = |log: f64| rect.left() + ((log - low) / (high - low)) as f32 * rect.width();
+    let tallest = bins.iter().copied().max().unwrap_or(1).max(1) as f32;
+    let bin_width = rect.width() / HISTOGRAM_BINS as f32;
+    let threshold_x = x_of(threshold.max(f64::MIN_POSITIVE).log10());
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    for (index, count) in bins.iter().enumerate() {
+        let left = rect.left() + index as f32 * bin_width;
+        let height = *count as f32 / tallest * rect.height();
+        let bar = egui::Rect::from_min_max(egui::pos2(left + 1.0, rect.bottom() - height), egui::pos2(left + bin_width - 1.0, rect.bottom()));
+        let color = if left + bin_width / 2.0 < threshold_x { egui::Color32::from_rgb(200, 80, 70) } else { ui.visuals().text_color() };
+        painter.rect_filled(bar, 0.0, color);
+    }
+    painter.vline(threshold_x, rect.y_range(), egui::Stroke::new(2.0, ui.visuals().warn_fg_color));
+
+    if let Some(pointer) = response.interact_pointer_pos() {
+        let log = low + f64::from((pointer.x - rect.left()) / rect.width()).clamp(0.0, 1.0) * (high - low);
+        *threshold = 10f64.powf(log);
+        return true;
+    }
+    false
+}
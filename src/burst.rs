@@ -0,0 +1,98 @@
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+
+pub const MAX_BURST_FRAMES: usize = 200; // Stops a timelapse from turning into one endless "burst"
+
+/// Consecutive frames shot within a moment of each other, offered as one decision.
+pub struct BurstGroup {
+    pub frames: Vec<PathBuf>,
+    pub keep: Vec<bool>, // Proposed decision per frame; the user can flip any of them
+    pub preselected: bool, // Set once all scores are in and the sharpest frame was picked
+}
+
+pub enum BurstChoice {
+    Apply,
+    KeepSharpest, // Apply with only the sharpest frame kept, whatever was flipped
+    ReviewIndividually,
+}
+
+impl BurstGroup {
+    pub fn new(frames: Vec<PathBuf>) -> Self {
+        Self {
+            keep: vec![false; frames.len()],
+            frames,
+            preselected: false,
+        }
+    }
+
+    /// Proposes keeping the sharpest frame and discarding the rest.
+    pub fn preselect_sharpest(&mut self, scores: &[f64]) {
+        let sharpest = scores
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(index, _)| index);
+        for (index, keep) in self.keep.iter_mut().enumerate() {
+            *keep = Some(index) == sharpest;
+        }
+        self.preselected = true;
+    }
+}
+
+/// The burst starting at the front of `queue`: following images from the same folder whose
+/// capture time is at most `max_gap` seconds after the previous frame's.
+pub fn find_burst(
+    queue: &[PathBuf],
+    max_gap: f64,
+    mut capture_time: impl FnMut(&Path) -> Option<f64>,
+) -> Vec<PathBuf> {
+    let Some(first) = queue.first() else {
+        return Vec::new();
+    };
+    let Some(mut previous_time) = capture_time(first) else {
+        return vec![first.clone()];
+    };
+    let mut frames = vec![first.clone()];
+    for path in queue.iter().skip(1).take(MAX_BURST_FRAMES - 1) {
+        if path.parent() != first.parent() {
+            break;
+        }
+        match capture_time(path) {
+            Some(time) if (time - previous_time).abs() <= max_gap => {
+                frames.push(path.clone());
+                previous_time = time;
+            },
+            _ => break,
+        }
+    }
+    frames
+}
+
+/// Variance of the Laplacian over the grayscale image: crisp edges give large second
+/// derivatives, so blurry frames score low.
+pub fn sharpness(image: &egui::ColorImage) -> f64 {
+    let [width, height] = image.size;
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+    let gray: Vec<f32> = image
+        .pixels
+        .iter()
+        .map(|pixel| 0.299 * pixel.r() as f32 + 0.587 * pixel.g() as f32 + 0.114 * pixel.b() as f32)
+        .collect();
+
+    let mut sum = 0.0f64;
+    let mut sum_of_squares = 0.0f64;
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let i = y * width + x;
+            let laplacian = (gray[i - 1] + gray[i + 1] + gray[i - width] + gray[i + width] - 4.0 * gray[i]) as f64;
+            sum += laplacian;
+            sum_of_squares += laplacian * laplacian;
+        }
+    }
+    let count = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / count;
+    sum_of_squares / count - mean * mean
+}
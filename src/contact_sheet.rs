@@ -0,0 +1,213 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+};
+
+use ab_glyph::{FontRef, PxScale};
+use eframe::egui;
+use image::{Rgb, RgbImage};
+use serde::{Serialize, Deserialize};
+
+use crate::decode::{self, DecodeOptions, JpegBackend};
+
+const BACKGROUND: Rgb<u8> = Rgb([255, 255, 255]);
+const MISSING_TILE: Rgb<u8> = Rgb([225, 225, 225]); // Where an image could not be decoded
+const CAPTION_COLOR: Rgb<u8> = Rgb([40, 40, 40]);
+const JPEG_QUALITY: u8 = 90;
+
+/// One printable overview image with every kept image tiled on it, captioned by file name.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ContactSheet {
+    pub columns: u32,
+    pub tile_size: u32, // Pixels of the longer side of every image on the sheet
+    pub file_name: String, // Written to the kept images' output folder; .png or .jpg
+}
+
+impl Default for ContactSheet {
+    fn default() -> Self {
+        Self {
+            columns: 6,
+            tile_size: 320,
+            file_name: "contact_sheet.jpg".to_string(),
+        }
+    }
+}
+
+impl ContactSheet {
+    /// Returns true when anything was edited.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label("Columns:");
+            changed |= ui.add(egui::DragValue::new(&mut self.columns).range(1..=20)).changed();
+            ui.label("Image size:");
+            changed |= ui.add(egui::DragValue::new(&mut self.tile_size).range(64..=1024).suffix(" px")).changed();
+            ui.label("File:");
+            changed |= ui
+                .add(egui::TextEdit::singleline(&mut self.file_name).desired_width(140.0))
+                .on_hover_text("Ending in .png or .jpg")
+                .changed();
+        });
+        changed
+    }
+
+    fn gap(&self) -> u32 {
+        (self.tile_size / 16).max(4)
+    }
+
+    fn caption_height(&self) -> f32 {
+        (self.tile_size as f32 / 14.0).max(12.0)
+    }
+
+    /// Tiles `images`, in order, row by row. Images that cannot be decoded leave a gray tile.
+    pub fn render(&self, images: &[PathBuf], jpeg_backend: JpegBackend, mut on_tile: impl FnMut()) -> RgbImage {
+        let font = FontRef::try_from_slice(epaint_default_fonts::UBUNTU_LIGHT).expect("the bundled font is valid");
+        let (tile, gap) = (self.tile_size.max(1), self.gap());
+        let columns = self.columns.clamp(1, images.len().max(1) as u32);
+        let rows = (images.len() as u32).div_ceil(columns).max(1);
+        let caption_scale = PxScale::from(self.caption_height());
+        let cell_height = tile + gap + self.caption_height().ceil() as u32;
+        let mut sheet = RgbImage::from_pixel(columns * (tile + gap) + gap, rows * (cell_height + gap) + gap, BACKGROUND);
+
+        // Decoded a little larger than needed, as the decoder's own downscale is the fast, rough kind
+        let options = DecodeOptions { max_dimension: Some(tile * 2), jpeg_backend };
+        for (index, path) in images.iter().enumerate() {
+            let (column, row) = (index as u32 % columns, index as u32 / columns);
+            let (x, y) = (gap + column * (tile + gap), gap + row * (cell_height + gap));
+            match decode::decode_image(path, &options) {
+                Ok(decoded) => {
+                    let [width, height] = decoded.size.map(|side| side as u32);
+                    let rgb = RgbImage::from_fn(width, height, |px, py| {
+                        let color = decoded.pixels[(py * width + px) as usize];
+                        Rgb([color.r(), color.g(), color.b()])
+                    });
+                    let scale = tile as f32 / width.max(height) as f32;
+                    let fitted = image::imageops::thumbnail(
+                        &rgb,
+                        ((width as f32 * scale).round() as u32).max(1),
+                        ((height as f32 * scale).round() as u32).max(1),
+                    );
+                    let (offset_x, offset_y) = ((tile - fitted.width()) / 2, (tile - fitted.height()) / 2);
+                    image::imageops::replace(&mut sheet, &fitted, i64::from(x + offset_x), i64::from(y + offset_y));
+                },
+                Err(e) => {
+                    log::warn!("Leaving {} off the contact sheet: {}", path.display(), e);
+                    imageproc::drawing::draw_filled_rect_mut(&mut sheet, imageproc::rect::Rect::at(x as i32, y as i32).of_size(tile, tile), MISSING_TILE);
+                },
+            }
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let caption = fit_caption(&name, tile, caption_scale, &font);
+            let (caption_width, _) = imageproc::drawing::text_size(caption_scale, &font, &caption);
+            let caption_x = x + tile.saturating_sub(caption_width) / 2;
+            imageproc::drawing::draw_text_mut(&mut sheet, CAPTION_COLOR, caption_x as i32, (y + tile + gap / 2) as i32, caption_scale, &font, &caption);
+            on_tile();
+        }
+        sheet
+    }
+
+    /// Renders the sheet and writes it into `output_folder`. Returns the file written.
+    pub fn write(&self, images: &[PathBuf], output_folder: &Path, jpeg_backend: JpegBackend, on_tile: impl FnMut()) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let sheet = self.render(images, jpeg_backend, on_tile);
+        let file_name = match self.file_name.trim() {
+            "" => ContactSheet::default().file_name,
+            name => name.to_string(),
+        };
+        std::fs::create_dir_all(output_folder)?;
+        let path = output_folder.join(file_name);
+        let is_png = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("png"));
+        if is_png {
+            sheet.save_with_format(&path, image::ImageFormat::Png)?;
+        } else {
+            let file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+            image::codecs::jpeg::JpegEncoder::new_with_quality(file, JPEG_QUALITY).encode_image(&sheet)?;
+        }
+        Ok(path)
+    }
+}
+
+/// `name`, shortened with an ellipsis in the middle until it is no wider than `width`, so
+/// the number at the end of camera file names stays readable.
+fn fit_caption(name: &str, width: u32, scale: PxScale, font: &FontRef) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut keep = chars.len();
+    let mut caption = name.to_string();
+    while keep > 2 && imageproc::drawing::text_size(scale, font, &caption).0 > width {
+        keep -= 1;
+        let tail = keep / 2;
+        let head = keep - tail;
+        caption = chars[..head].iter().chain(['…'].iter()).chain(&chars[chars.len() - tail..]).collect();
+    }
+    caption
+}
+
+/// A contact sheet being rendered on its own thread.
+pub struct ContactSheetJob {
+    receiver: Receiver<Option<Result<PathBuf, String>>>, // None for every tile done, then the outcome
+    pub total: usize,
+    pub done: usize,
+    pub result: Option<Result<PathBuf, String>>,
+}
+
+impl ContactSheetJob {
+    pub fn start(sheet: ContactSheet, images: Vec<PathBuf>, output_folder: PathBuf, jpeg_backend: JpegBackend, ctx: egui::Context) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let total = images.len();
+        std::thread::Builder::new()
+            .name("contact sheet".to_string())
+            .spawn(move || {
+                let progress = sender.clone();
+                let repaint = ctx.clone();
+                let result = sheet.write(&images, &output_folder, jpeg_backend, || {
+                    let _ = progress.send(None);
+                    repaint.request_repaint();
+                });
+                let _ = sender.send(Some(result.map_err(|e| e.to_string())));
+                ctx.request_repaint();
+            })
+            .expect("failed to spawn contact sheet thread");
+        Self { receiver, total, done: 0, result: None }
+    }
+
+    /// Collects what the thread has done since the last call.
+    pub fn poll(&mut self) {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(None) => self.done += 1,
+                Ok(Some(result)) => self.result = Some(result),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.result.get_or_insert_with(|| Err("the contact sheet thread stopped unexpectedly".to_string()));
+                    break;
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn contact_sheets_tile_every_image_with_room_for_captions() {
+        let root = TempDir::new("contact_sheet");
+        let images: Vec<std::path::PathBuf> = (0..5).map(|i| root.join(format!("IMG_{:04}.jpg", i))).collect();
+        for (i, image) in images.iter().enumerate() {
+            // Alternating landscape and portrait frames
+            let size = if i % 2 == 0 { (60, 40) } else { (40, 60) };
+            image::RgbImage::from_pixel(size.0, size.1, image::Rgb([200, 30, 30])).save(image).unwrap();
+        }
+        let sheet = ContactSheet { columns: 3, tile_size: 100, file_name: "sheet.png".to_string() };
+        let mut tiles = 0;
+        let written = sheet.write(&images, &root.join("kept"), crate::decode::JpegBackend::default(), || tiles += 1);
+        let rendered = written.as_ref().ok().and_then(|path| image::open(path).ok()).map(|image| image.to_rgb8());
+        let rendered = rendered.expect("the sheet was written");
+        assert_eq!(tiles, 5);
+        assert_eq!(rendered.width(), 3 * 106 + 6, "three tiles and their gaps across");
+        assert!(rendered.height() > 2 * 106, "two rows, each with a caption under the tiles");
+        assert!(rendered.get_pixel(6 + 50, 6 + 50)[0] > 150, "the first image sits in the first tile");
+        assert_eq!(*rendered.get_pixel(rendered.width() - 10, rendered.height() - 10), image::Rgb([255, 255, 255]), "the sixth tile is empty");
+    }
+}
@@ -0,0 +1,309 @@
+use std::path::Path;
+
+use eframe::egui;
+use serde::{Serialize, Deserialize};
+
+const MAX_RAW_PREVIEW_CANDIDATES: usize = 32; // Start markers tried; RAW data can contain them by chance
+
+/// Whether this build can decode JPEGs with libjpeg-turbo (the `turbojpeg` feature).
+pub const TURBOJPEG_AVAILABLE: bool = cfg!(feature = "turbojpeg");
+
+/// Which library decodes JPEGs.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum JpegBackend {
+    #[default]
+    Builtin, // jpeg_decoder, pure Rust
+    TurboJpeg, // libjpeg-turbo with SIMD; falls back to Builtin in builds without it
+}
+
+/// The library that actually decoded an image, which for JPEGs is not always the backend
+/// chosen.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum DecodedBy {
+    JpegDecoder,
+    TurboJpeg,
+    ImageCrate,
+    Tiff,
+}
+
+/// What decodes JPEGs for `backend`: libjpeg-turbo only in builds that have it.
+fn jpeg_decoder_for(backend: JpegBackend) -> DecodedBy {
+    match backend {
+        JpegBackend::TurboJpeg if TURBOJPEG_AVAILABLE => DecodedBy::TurboJpeg,
+        _ => DecodedBy::JpegDecoder,
+    }
+}
+
+/// How an image should come out of the decoder.
+#[derive(Clone, Copy, Default)]
+pub struct DecodeOptions {
+    pub max_dimension: Option<u32>, // Downscale so neither side exceeds this; None keeps full size
+    pub jpeg_backend: JpegBackend,
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    Io(std::io::Error),
+    Jpeg(jpeg_decoder::Error),
+    #[cfg(feature = "turbojpeg")]
+    TurboJpeg(turbojpeg::Error),
+    Image(image::ImageError),
+    UnsupportedFormat(String),
+    TiffPage(String), // A page of a multi-page TIFF
+    TimedOut(std::time::Duration), // Given up on by the decode pool
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not read the file: {}", e),
+            Self::Jpeg(e) => write!(f, "broken JPEG: {}", e),
+            #[cfg(feature = "turbojpeg")]
+            Self::TurboJpeg(e) => write!(f, "broken JPEG: {}", e),
+            Self::Image(e) => write!(f, "could not decode: {}", e),
+            Self::UnsupportedFormat(format) => write!(f, "unsupported format: {}", format),
+            Self::TiffPage(e) => write!(f, "could not decode the page: {}", e),
+            Self::TimedOut(timeout) => write!(f, "decode timed out after {} s", timeout.as_secs()),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Decodes an image file into pixels ready for a texture. JPEGs go through
+/// `jpeg_decoder` (or libjpeg-turbo, if chosen and built in), which is faster for them;
+/// everything else through the `image` crate, and pages of multi-page TIFFs through `tiff`.
+pub fn decode_image(path: &Path, opts: &DecodeOptions) -> Result<egui::ColorImage, DecodeError> {
+    let started = std::time::Instant::now();
+    let (image, decoder) = match crate::pages::split_page_path(path) {
+        Some((file, page)) => {
+            let page = crate::pages::read_page(&file, page).map_err(DecodeError::TiffPage)?.to_rgba8();
+            let image = egui::ColorImage::from_rgba_unmultiplied([page.width() as usize, page.height() as usize], page.as_raw());
+            (image, DecodedBy::Tiff)
+        },
+        None => decode_file(path, opts)?,
+    };
+    // RUST_LOG=bildsak::decode=debug shows these, to compare the backends on real files
+    log::debug!(
+        "Decoded {} ({}x{}) with {:?} in {:.1} ms",
+        path.display(),
+        image.size[0],
+        image.size[1],
+        decoder,
+        started.elapsed().as_secs_f64() * 1000.0
+    );
+    Ok(match opts.max_dimension {
+        Some(max_dimension) => downscale(image, max_dimension),
+        None => image,
+    })
+}
+
+fn decode_file(path: &Path, opts: &DecodeOptions) -> Result<(egui::ColorImage, DecodedBy), DecodeError> {
+    let bytes = std::fs::read(path).map_err(DecodeError::Io)?;
+    let extension = path.extension().and_then(|e| e.to_str()).map(|s| s.to_lowercase());
+    let is_jpeg = match extension.as_deref() {
+        Some(extension) => matches!(extension, "jpg" | "jpeg"),
+        None => match image::guess_format(&bytes) {
+            Ok(format) => format == image::ImageFormat::Jpeg,
+            Err(_) => return Err(DecodeError::UnsupportedFormat("no file extension and no known signature".to_string())),
+        },
+    };
+    if crate::scan::is_raw(path) {
+        Ok((decode_raw_preview(&bytes)?, DecodedBy::JpegDecoder))
+    } else if is_jpeg {
+        match jpeg_decoder_for(opts.jpeg_backend) {
+            #[cfg(feature = "turbojpeg")]
+            DecodedBy::TurboJpeg => Ok((decode_jpeg_turbo(&bytes)?, DecodedBy::TurboJpeg)),
+            _ => Ok((decode_jpeg(&bytes)?, DecodedBy::JpegDecoder)),
+        }
+    } else {
+        Ok((decode_with_image_crate(&bytes)?, DecodedBy::ImageCrate))
+    }
+}
+
+/// Whether the file starts like a JPEG, for files whose name does not say what they are.
+/// Costs an open and a small read.
+pub fn has_jpeg_signature(path: &Path) -> bool {
+    use std::io::Read;
+
+    let mut start = [0; 16];
+    let Ok(mut file) = std::fs::File::open(crate::pages::file_of(path)) else {
+        return false;
+    };
+    let Ok(length) = file.read(&mut start) else {
+        return false;
+    };
+    image::guess_format(&start[..length]).is_ok_and(|format| format == image::ImageFormat::Jpeg)
+}
+
+/// The largest JPEG preview embedded in a RAW file. Cameras put one the size of the sensor (or
+/// close) next to the RAW data, so there is no need to develop the RAW itself. The previews are
+/// found by their start marker rather than by each format's own structure.
+fn decode_raw_preview(bytes: &[u8]) -> Result<egui::ColorImage, DecodeError> {
+    let (_, start) = bytes
+        .windows(3)
+        .enumerate()
+        .filter(|(_, window)| *window == [0xFF, 0xD8, 0xFF])
+        .take(MAX_RAW_PREVIEW_CANDIDATES)
+        .filter_map(|(start, _)| {
+            let mut decoder = jpeg_decoder::Decoder::new(std::io::Cursor::new(&bytes[start..]));
+            decoder.read_info().ok()?;
+            let info = decoder.info()?;
+            // Lossless JPEG is how some formats store the RAW data itself, not a picture
+            (info.coding_process != jpeg_decoder::CodingProcess::Lossless)
+                .then_some((u32::from(info.width) * u32::from(info.height), start))
+        })
+        .max()
+        .ok_or_else(|| DecodeError::UnsupportedFormat("RAW file without an embedded preview".to_string()))?;
+    decode_jpeg(&bytes[start..])
+}
+
+fn decode_jpeg(bytes: &[u8]) -> Result<egui::ColorImage, DecodeError> {
+    let mut decoder = jpeg_decoder::Decoder::new(std::io::Cursor::new(bytes));
+    let decoded = decoder.decode().map_err(DecodeError::Jpeg)?;
+    let info = decoder
+        .info()
+        .ok_or_else(|| DecodeError::UnsupportedFormat("JPEG without a frame header".to_string()))?;
+    let pixels: Vec<egui::Color32> = match info.pixel_format {
+        jpeg_decoder::PixelFormat::RGB24 => decoded
+            .chunks_exact(3)
+            .map(|chunk| egui::Color32::from_rgb(chunk[0], chunk[1], chunk[2]))
+            .collect(),
+        jpeg_decoder::PixelFormat::L8 => decoded.iter().map(|&gray| egui::Color32::from_gray(gray)).collect(),
+        jpeg_decoder::PixelFormat::L16 => decoded.chunks_exact(2).map(|chunk| egui::Color32::from_gray(chunk[0])).collect(), // Big-endian, keep the high byte
+        jpeg_decoder::PixelFormat::CMYK32 => decoded
+            .chunks_exact(4)
+            .map(|chunk| {
+                // Ink amounts, so white paper is 0; a naive conversion is good enough to judge by
+                let [c, m, y, k] = [chunk[0], chunk[1], chunk[2], chunk[3]].map(|ink| 255 - u32::from(ink));
+                egui::Color32::from_rgb((c * k / 255) as u8, (m * k / 255) as u8, (y * k / 255) as u8)
+            })
+            .collect(),
+    };
+    let size = [info.width as usize, info.height as usize];
+    if pixels.len() != size[0] * size[1] {
+        return Err(DecodeError::UnsupportedFormat("JPEG pixel data does not match its size".to_string()));
+    }
+    Ok(egui::ColorImage {
+        size,
+        source_size: egui::Vec2::new(size[0] as f32, size[1] as f32),
+        pixels,
+    })
+}
+
+#[cfg(feature = "turbojpeg")]
+fn decode_jpeg_turbo(bytes: &[u8]) -> Result<egui::ColorImage, DecodeError> {
+    let image = turbojpeg::decompress(bytes, turbojpeg::PixelFormat::RGBA).map_err(DecodeError::TurboJpeg)?;
+    let size = [image.width, image.height];
+    let row_bytes = image.width * 4;
+    let pixels: Vec<u8> = image.pixels.chunks(image.pitch).flat_map(|row| &row[..row_bytes]).copied().collect();
+    Ok(egui::ColorImage::from_rgba_unmultiplied(size, &pixels))
+}
+
+fn decode_with_image_crate(bytes: &[u8]) -> Result<egui::ColorImage, DecodeError> {
+    let img = image::load_from_memory(bytes).map_err(DecodeError::Image)?;
+    let rgba = img.to_rgba8();
+    let size = [rgba.width() as usize, rgba.height() as usize];
+    let pixels = rgba.into_raw();
+    Ok(egui::ColorImage::from_rgba_unmultiplied(size, &pixels))
+}
+
+/// Nearest-neighbor downscale; only used where speed matters more than looks.
+fn downscale(image: egui::ColorImage, max_dimension: u32) -> egui::ColorImage {
+    let [width, height] = image.size;
+    let largest = width.max(height);
+    let max_dimension = max_dimension.max(1) as usize;
+    if largest <= max_dimension {
+        return image;
+    }
+    let new_width = (width * max_dimension / largest).max(1);
+    let new_height = (height * max_dimension / largest).max(1);
+    let mut pixels = Vec::with_capacity(new_width * new_height);
+    for y in 0..new_height {
+        let source_row = y * height / new_height * width;
+        for x in 0..new_width {
+            pixels.push(image.pixels[source_row + x * width / new_width]);
+        }
+    }
+    egui::ColorImage {
+        size: [new_width, new_height],
+        source_size: image.source_size,
+        pixels,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::FIXTURE;
+
+    #[test]
+    fn choosing_libjpeg_turbo_without_it_decodes_with_jpeg_decoder() {
+        let expected = if TURBOJPEG_AVAILABLE { DecodedBy::TurboJpeg } else { DecodedBy::JpegDecoder };
+        assert_eq!(jpeg_decoder_for(JpegBackend::TurboJpeg), expected);
+        assert_eq!(jpeg_decoder_for(JpegBackend::Builtin), DecodedBy::JpegDecoder);
+        let (_, decoder) = decode_file(Path::new(FIXTURE), &DecodeOptions { max_dimension: None, jpeg_backend: JpegBackend::TurboJpeg }).unwrap();
+        assert_eq!(decoder, expected);
+    }
+
+    #[cfg(feature = "turbojpeg")]
+    #[test]
+    fn both_jpeg_backends_decode_to_the_same_picture() {
+        let decode = |jpeg_backend| decode_image(Path::new(FIXTURE), &DecodeOptions { max_dimension: None, jpeg_backend }).unwrap();
+        let (builtin, turbo) = (decode(JpegBackend::Builtin), decode(JpegBackend::TurboJpeg));
+        assert_eq!(builtin.size, turbo.size);
+        // The two round and upsample chroma a little differently
+        let difference: u64 = builtin
+            .pixels
+            .iter()
+            .zip(&turbo.pixels)
+            .map(|(a, b)| u64::from(a.r().abs_diff(b.r())) + u64::from(a.g().abs_diff(b.g())) + u64::from(a.b().abs_diff(b.b())))
+            .sum();
+        assert!(difference < 3 * 2 * builtin.pixels.len() as u64);
+    }
+
+    /// Run with `cargo test --release --features turbojpeg -- --ignored --nocapture` to see
+    /// what each backend costs on a 24 megapixel photo.
+    #[test]
+    #[ignore = "benchmark"]
+    fn jpeg_backend_speed() {
+        let folder = crate::testing::TempDir::new("jpeg_speed");
+        let path = folder.join("large.jpg");
+        let (width, height) = (6000, 4000);
+        // Noise keeps the encoder from compressing it into a file that decodes unusually fast
+        let pixels = image::RgbImage::from_fn(width, height, |x, y| {
+            let noise = (x.wrapping_mul(7919) ^ y.wrapping_mul(104_729)) % 64;
+            image::Rgb([(x * 255 / width + noise) as u8, (y * 255 / height) as u8, (128 + noise) as u8])
+        });
+        pixels.save(&path).unwrap();
+        for jpeg_backend in [JpegBackend::Builtin, JpegBackend::TurboJpeg] {
+            let opts = DecodeOptions { max_dimension: None, jpeg_backend };
+            decode_file(&path, &opts).unwrap(); // Warm the file cache
+            let started = std::time::Instant::now();
+            let runs = 5;
+            for _ in 0..runs {
+                let (image, _) = decode_file(&path, &opts).unwrap();
+                assert_eq!(image.size, [width as usize, height as usize]);
+            }
+            println!("{:?}: {:.0} ms per decode", jpeg_decoder_for(jpeg_backend), started.elapsed().as_secs_f64() * 1000.0 / f64::from(runs));
+        }
+    }
+
+    #[test]
+    fn decodes_the_fixture_jpeg() {
+        let image = decode_image(Path::new(FIXTURE), &DecodeOptions::default()).unwrap();
+        assert_eq!(image.size, [204, 272]);
+        assert_eq!(image.pixels.len(), 204 * 272);
+
+        let small = decode_image(Path::new(FIXTURE), &DecodeOptions { max_dimension: Some(68), ..Default::default() }).unwrap();
+        assert_eq!(small.size, [51, 68]);
+    }
+
+    #[test]
+    fn undecodable_files_are_errors() {
+        let missing = decode_image(Path::new("assets/missing.jpg"), &DecodeOptions::default());
+        assert!(matches!(missing, Err(DecodeError::Io(_))));
+        let not_a_jpeg = decode_image(Path::new("Cargo.toml"), &DecodeOptions::default());
+        assert!(not_a_jpeg.is_err());
+    }
+}
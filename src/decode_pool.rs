@@ -0,0 +1,271 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use eframe::egui;
+
+use crate::{
+    decode::{self, DecodeError, DecodeOptions, JpegBackend},
+    thumbnail_cache::ThumbnailCache,
+};
+
+// Bursts are compared against each other, which does not need every pixel
+const SHARPNESS_MAX_DIMENSION: u32 = 1600;
+const THUMBNAIL_MAX_DIMENSION: u32 = 128;
+
+#[derive(Clone)]
+pub enum DecodeJob {
+    Display(PathBuf), // Full image for the viewer
+    Sharpness(PathBuf), // Only the sharpness score of the decoded image
+    Thumbnail(PathBuf),
+}
+
+pub enum DecodeResult {
+    Display { path: PathBuf, image: Result<egui::ColorImage, DecodeError> },
+    Sharpness { path: PathBuf, score: f64 },
+    Thumbnail { path: PathBuf, image: Result<egui::ColorImage, DecodeError> },
+}
+
+/// A fixed set of background threads decoding images off the UI thread.
+/// Jobs go in through a shared channel; finished images come back through another
+/// and the UI is asked to repaint so it can pick them up.
+pub struct DecodePool {
+    job_sender: Option<Sender<DecodeJob>>,
+    job_receiver: Arc<Mutex<Receiver<DecodeJob>>>,
+    result_sender: Sender<DecodeResult>, // For replacement workers and timed-out jobs
+    result_receiver: Receiver<DecodeResult>,
+    workers: Vec<Worker>,
+    spawned: usize, // Workers started so far, replacements included, for their thread names
+    shutdown: Arc<AtomicBool>,
+    jpeg_backend: JpegBackend,
+    timeout: Option<Duration>, // After which a decode is given up on
+    thumbnail_cache: Option<ThumbnailCache>,
+    ctx: egui::Context,
+}
+
+struct Worker {
+    handle: JoinHandle<()>,
+    current: Arc<Mutex<Option<(DecodeJob, Instant)>>>, // Job being decoded and since when
+    abandoned: Arc<AtomicBool>, // Its job timed out; whatever it comes up with is thrown away
+}
+
+impl DecodePool {
+    pub fn new(
+        thread_count: usize,
+        jpeg_backend: JpegBackend,
+        timeout: Option<Duration>,
+        thumbnail_cache: Option<ThumbnailCache>,
+        ctx: &egui::Context,
+    ) -> Self {
+        let (job_sender, job_receiver) = mpsc::channel::<DecodeJob>();
+        let (result_sender, result_receiver) = mpsc::channel();
+        let mut pool = Self {
+            job_sender: Some(job_sender),
+            job_receiver: Arc::new(Mutex::new(job_receiver)),
+            result_sender,
+            result_receiver,
+            workers: Vec::new(),
+            spawned: 0,
+            shutdown: Arc::new(AtomicBool::new(false)),
+            jpeg_backend,
+            timeout,
+            thumbnail_cache,
+            ctx: ctx.clone(),
+        };
+        for _ in 0..thread_count.max(1) {
+            pool.spawn_worker();
+        }
+        pool
+    }
+
+    fn spawn_worker(&mut self) {
+        let job_receiver = Arc::clone(&self.job_receiver);
+        let result_sender = self.result_sender.clone();
+        let shutdown = Arc::clone(&self.shutdown);
+        let current = Arc::new(Mutex::new(None));
+        let abandoned = Arc::new(AtomicBool::new(false));
+        let ctx = self.ctx.clone();
+        let timeout = self.timeout;
+        let thumbnail_cache = self.thumbnail_cache.clone();
+        let jpeg_backend = self.jpeg_backend;
+        let display_options = DecodeOptions { max_dimension: None, jpeg_backend };
+        let sharpness_options = DecodeOptions { max_dimension: Some(SHARPNESS_MAX_DIMENSION), jpeg_backend };
+        let thumbnail_options = DecodeOptions { max_dimension: Some(THUMBNAIL_MAX_DIMENSION), jpeg_backend };
+        let worker_current = Arc::clone(&current);
+        let worker_abandoned = Arc::clone(&abandoned);
+        let handle = std::thread::Builder::new()
+            .name(format!("decode-{}", self.spawned))
+            .spawn(move || loop {
+                // Hold the lock only while waiting for the next job, not while decoding
+                let job = job_receiver.lock().map(|receiver| receiver.recv());
+                let Ok(Ok(job)) = job else {
+                    break; // Channel closed or lock poisoned: the pool is going away
+                };
+                if shutdown.load(Ordering::Relaxed) {
+                    break;
+                }
+                if let Ok(mut current) = worker_current.lock() {
+                    *current = Some((job.clone(), Instant::now()));
+                }
+                if let Some(timeout) = timeout {
+                    ctx.request_repaint_after(timeout); // So the UI gets to notice if this hangs
+                }
+                let result = match job {
+                    DecodeJob::Display(path) => {
+                        let image = decode::decode_image(&path, &display_options);
+                        DecodeResult::Display { path, image }
+                    },
+                    DecodeJob::Sharpness(path) => {
+                        // An undecodable frame scores lowest rather than holding up the burst
+                        let score = match decode::decode_image(&path, &sharpness_options) {
+                            Ok(image) => crate::burst::sharpness(&image),
+                            Err(_) => 0.0,
+                        };
+                        DecodeResult::Sharpness { path, score }
+                    },
+                    DecodeJob::Thumbnail(path) => {
+                        let image = match thumbnail_cache.as_ref().and_then(|cache| cache.get(&path)) {
+                            Some(cached) => Ok(cached),
+                            None => decode::decode_image(&path, &thumbnail_options).inspect(|image| {
+                                if let Some(cache) = &thumbnail_cache {
+                                    cache.put(&path, image);
+                                }
+                            }),
+                        };
+                        DecodeResult::Thumbnail { path, image }
+                    },
+                };
+                // Cleared under the lock the UI thread abandons workers under, so either the
+                // result goes out or the timeout does, never both
+                if let Ok(mut current) = worker_current.lock() {
+                    *current = None;
+                }
+                if worker_abandoned.load(Ordering::Relaxed) {
+                    break; // A replacement has taken over
+                }
+                if shutdown.load(Ordering::Relaxed) || result_sender.send(result).is_err() {
+                    break;
+                }
+                ctx.request_repaint();
+            })
+            .expect("failed to spawn decode worker");
+        self.spawned += 1;
+        self.workers.push(Worker { handle, current, abandoned });
+    }
+
+    /// Gives up on decodes that have been running longer than the timeout: their images are
+    /// reported as timed out and their workers are left to finish on their own while new
+    /// ones take their place.
+    pub fn abandon_stuck_workers(&mut self) {
+        let Some(timeout) = self.timeout else {
+            return;
+        };
+        let mut stuck = Vec::new();
+        for (index, worker) in self.workers.iter().enumerate() {
+            let Ok(mut current) = worker.current.lock() else {
+                continue;
+            };
+            if current.as_ref().is_some_and(|(_, started)| started.elapsed() > timeout)
+                && let Some((job, _)) = current.take()
+            {
+                worker.abandoned.store(true, Ordering::Relaxed);
+                stuck.push((index, job));
+            }
+        }
+        for (index, job) in stuck.into_iter().rev() {
+            self.workers.remove(index); // Detached rather than joined: it may never come back
+            let result = match job {
+                DecodeJob::Display(path) => {
+                    log::warn!("Gave up decoding {} after {:?}", path.display(), timeout);
+                    DecodeResult::Display { path, image: Err(DecodeError::TimedOut(timeout)) }
+                },
+                DecodeJob::Sharpness(path) => DecodeResult::Sharpness { path, score: 0.0 },
+                DecodeJob::Thumbnail(path) => DecodeResult::Thumbnail { path, image: Err(DecodeError::TimedOut(timeout)) },
+            };
+            let _ = self.result_sender.send(result);
+            self.spawn_worker();
+        }
+    }
+
+    pub fn thread_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    pub fn submit(&self, job: DecodeJob) {
+        if let Some(sender) = &self.job_sender {
+            let _ = sender.send(job);
+        }
+    }
+
+    /// Returns every job finished since the last call without blocking.
+    pub fn finished(&self) -> Vec<DecodeResult> {
+        self.result_receiver.try_iter().collect()
+    }
+
+    /// Stops accepting work and drops queued jobs. Workers are not waited for: idle ones
+    /// stop right away, busy ones once their decode is done, which may be never if it hangs.
+    pub fn shutdown(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        self.job_sender = None; // Closing the channel wakes up idle workers
+        for worker in self.workers.drain(..) {
+            worker.abandoned.store(true, Ordering::Relaxed);
+            drop(worker.handle); // Detached, like the workers given up on in abandon_stuck_workers
+        }
+    }
+}
+
+impl Drop for DecodePool {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[cfg(unix)]
+    #[test]
+    fn hanging_decodes_time_out_and_their_worker_is_replaced() {
+        // Reading a FIFO nobody writes to blocks like a decoder stuck on a bad file
+        let root = TempDir::new("timeout");
+        let fifo = root.join("hanging.jpg");
+        assert!(std::process::Command::new("mkfifo").arg(&fifo).status().unwrap().success());
+        let timeout = std::time::Duration::from_millis(50);
+        let mut pool = DecodePool::new(1, JpegBackend::Builtin, Some(timeout), None, &egui::Context::default());
+        pool.submit(DecodeJob::Display(fifo.clone()));
+        std::thread::sleep(timeout * 4);
+        pool.abandon_stuck_workers();
+        let results = pool.finished();
+        let _ = std::fs::OpenOptions::new().write(true).open(&fifo); // Lets the abandoned worker go
+        assert!(matches!(results.as_slice(), [DecodeResult::Display { image: Err(crate::decode::DecodeError::TimedOut(_)), .. }]));
+        assert_eq!(pool.thread_count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shutting_down_does_not_wait_for_a_hanging_decode() {
+        let root = TempDir::new("shutdown");
+        let fifo = root.join("hanging.jpg");
+        assert!(std::process::Command::new("mkfifo").arg(&fifo).status().unwrap().success());
+        let mut pool = DecodePool::new(2, JpegBackend::Builtin, None, None, &egui::Context::default());
+        pool.submit(DecodeJob::Display(fifo.clone()));
+        std::thread::sleep(std::time::Duration::from_millis(50)); // Until a worker is stuck on it
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            pool.shutdown();
+            let _ = sender.send(());
+        });
+        let shut_down = receiver.recv_timeout(std::time::Duration::from_secs(2));
+        let _ = std::fs::OpenOptions::new().write(true).open(&fifo); // Lets the stuck worker go
+        assert!(shut_down.is_ok());
+    }
+}
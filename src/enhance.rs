@@ -0,0 +1,81 @@
+use eframe::egui;
+
+const CLIP_FRACTION: f64 = 0.005; // Share of the darkest and brightest pixels per channel allowed to clip
+
+/// A quick auto-levels preview: every channel is stretched on its own so its darkest and
+/// brightest values span the full range. Stretching the channels separately also
+/// neutralizes a color cast, which doubles as a simple auto white balance.
+pub fn auto_enhance(image: &egui::ColorImage) -> egui::ColorImage {
+    let mut histograms = [[0usize; 256]; 3];
+    for pixel in &image.pixels {
+        histograms[0][pixel.r() as usize] += 1;
+        histograms[1][pixel.g() as usize] += 1;
+        histograms[2][pixel.b() as usize] += 1;
+    }
+    let lookup = histograms.map(|histogram| stretch_table(&histogram, image.pixels.len()));
+
+    let pixels = image
+        .pixels
+        .iter()
+        .map(|pixel| {
+            egui::Color32::from_rgba_unmultiplied(
+                lookup[0][pixel.r() as usize],
+                lookup[1][pixel.g() as usize],
+                lookup[2][pixel.b() as usize],
+                pixel.a(),
+            )
+        })
+        .collect();
+    egui::ColorImage {
+        size: image.size,
+        source_size: image.source_size,
+        pixels,
+    }
+}
+
+/// Maps the clipped low..=high range of one channel onto 0..=255.
+fn stretch_table(histogram: &[usize; 256], pixel_count: usize) -> [u8; 256] {
+    let clip = (pixel_count as f64 * CLIP_FRACTION) as usize;
+    let low = percentile(histogram.iter().enumerate(), clip).unwrap_or(0);
+    let high = percentile(histogram.iter().enumerate().rev(), clip).unwrap_or(255);
+
+    let mut table = [0u8; 256];
+    for (value, mapped) in table.iter_mut().enumerate() {
+        *mapped = if high <= low {
+            value as u8 // Flat channel, nothing to stretch
+        } else {
+            ((value as f32 - low as f32) * 255.0 / (high - low) as f32).clamp(0.0, 255.0).round() as u8
+        };
+    }
+    table
+}
+
+/// First value, walking the histogram in the given direction, past `clip` pixels.
+fn percentile<'a>(mut bins: impl Iterator<Item = (usize, &'a usize)>, clip: usize) -> Option<usize> {
+    let mut seen = 0;
+    bins.find_map(|(value, count)| {
+        seen += count;
+        (seen > clip).then_some(value)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_enhance_stretches_each_channel_to_the_full_range() {
+        let pixels = (0..100u8)
+            .map(|i| egui::Color32::from_rgb(100 + i / 2, 60 + i / 4, 120))
+            .collect();
+        let flat = egui::ColorImage {
+            size: [10, 10],
+            source_size: egui::Vec2::new(10.0, 10.0),
+            pixels,
+        };
+        let enhanced = auto_enhance(&flat);
+        let red: Vec<u8> = enhanced.pixels.iter().map(|pixel| pixel.r()).collect();
+        assert_eq!((red.iter().min(), red.iter().max()), (Some(&0), Some(&255)));
+        assert!(enhanced.pixels.iter().all(|pixel| pixel.b() == 120)); // A flat channel is left alone
+    }
+}
@@ -0,0 +1,181 @@
+use std::path::Path;
+
+use exif::{In, Tag, Value};
+
+/// The EXIF fields the app uses, read once per image.
+#[derive(Clone, Default)]
+pub struct ExifInfo {
+    pub gps: Option<GpsPosition>,
+    pub captured_at: Option<f64>, // Seconds since 1970 on the camera's clock, including sub-seconds
+    pub capture_settings: CaptureSettings,
+}
+
+/// How the camera was set up for the shot.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct CaptureSettings {
+    pub focal_length: Option<f64>, // Millimetres
+    pub f_number: Option<f64>,
+    pub exposure_time: Option<f64>, // Seconds
+    pub iso: Option<u32>,
+}
+
+impl CaptureSettings {
+    /// The settings in one line, e.g. "50mm · f/1.8 · 1/200 · ISO 400"; `None` without any.
+    pub fn summary(&self) -> Option<String> {
+        let parts: Vec<String> = [
+            self.focal_length.map(|focal_length| format!("{}mm", trim_decimals(focal_length))),
+            self.f_number.map(|f_number| format!("f/{}", trim_decimals(f_number))),
+            self.exposure_time.map(format_exposure_time),
+            self.iso.map(|iso| format!("ISO {}", iso)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        (!parts.is_empty()).then(|| parts.join(" · "))
+    }
+}
+
+/// Shutter speeds under a second the way cameras show them, as a fraction.
+fn format_exposure_time(seconds: f64) -> String {
+    if seconds > 0.0 && seconds < 1.0 {
+        format!("1/{}", (1.0 / seconds).round())
+    } else {
+        format!("{}s", trim_decimals(seconds))
+    }
+}
+
+/// One decimal at most, and none when it is zero: 50, 1.8, 2.5.
+fn trim_decimals(value: f64) -> String {
+    let rounded = (value * 10.0).round() / 10.0;
+    if rounded.fract() == 0.0 {
+        format!("{}", rounded as i64)
+    } else {
+        format!("{:.1}", rounded)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GpsPosition {
+    pub latitude: f64,  // Positive north of the equator
+    pub longitude: f64, // Positive east of Greenwich
+}
+
+impl GpsPosition {
+    pub fn map_url(&self) -> String {
+        format!(
+            "https://www.openstreetmap.org/?mlat={lat:.6}&mlon={lon:.6}#map=16/{lat:.6}/{lon:.6}",
+            lat = self.latitude,
+            lon = self.longitude
+        )
+    }
+}
+
+impl std::fmt::Display for GpsPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let north_south = if self.latitude < 0.0 { 'S' } else { 'N' };
+        let east_west = if self.longitude < 0.0 { 'W' } else { 'E' };
+        write!(f, "{:.5}° {}, {:.5}° {}", self.latitude.abs(), north_south, self.longitude.abs(), east_west)
+    }
+}
+
+impl ExifInfo {
+    /// Returns `None` when the file has no readable EXIF block at all.
+    pub fn read(path: &Path) -> Option<Self> {
+        let file = std::fs::File::open(crate::pages::file_of(path)).ok()?; // Pages carry their file's tags
+        let exif = exif::Reader::new()
+            .read_from_container(&mut std::io::BufReader::new(file))
+            .ok()?;
+        Some(Self {
+            gps: read_gps(&exif),
+            captured_at: read_capture_time(&exif),
+            capture_settings: read_capture_settings(&exif),
+        })
+    }
+}
+
+fn read_capture_time(exif: &exif::Exif) -> Option<f64> {
+    let Value::Ascii(values) = &exif.get_field(Tag::DateTimeOriginal, In::PRIMARY)?.value else {
+        return None;
+    };
+    let mut date_time = exif::DateTime::from_ascii(values.first()?).ok()?;
+    // Bursts shoot several frames per second, so the whole seconds alone cannot order them
+    if let Some(field) = exif.get_field(Tag::SubSecTimeOriginal, In::PRIMARY)
+        && let Value::Ascii(subsec) = &field.value
+        && let Some(subsec) = subsec.first()
+    {
+        let _ = date_time.parse_subsec(subsec);
+    }
+    let days = days_from_civil(date_time.year as i64, date_time.month as i64, date_time.day as i64);
+    let seconds = days * 86_400 + date_time.hour as i64 * 3600 + date_time.minute as i64 * 60 + date_time.second as i64;
+    Some(seconds as f64 + date_time.nanosecond.unwrap_or(0) as f64 / 1e9)
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date (Howard Hinnant's algorithm).
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn read_capture_settings(exif: &exif::Exif) -> CaptureSettings {
+    let rational = |tag| match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Rational(values) => values.first().map(|value| value.to_f64()).filter(|value| value.is_finite() && *value > 0.0),
+        _ => None,
+    };
+    CaptureSettings {
+        focal_length: rational(Tag::FocalLength),
+        f_number: rational(Tag::FNumber),
+        exposure_time: rational(Tag::ExposureTime),
+        iso: exif.get_field(Tag::PhotographicSensitivity, In::PRIMARY).and_then(|field| field.value.get_uint(0)),
+    }
+}
+
+fn read_gps(exif: &exif::Exif) -> Option<GpsPosition> {
+    let latitude = coordinate(exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, b'S')?;
+    let longitude = coordinate(exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, b'W')?;
+    Some(GpsPosition { latitude, longitude })
+}
+
+/// GPS coordinates are stored as unsigned degrees/minutes/seconds rationals plus a
+/// separate N/S or E/W reference; `negative_ref` is the reference that flips the sign.
+fn coordinate(exif: &exif::Exif, value_tag: Tag, ref_tag: Tag, negative_ref: u8) -> Option<f64> {
+    let Value::Rational(dms) = &exif.get_field(value_tag, In::PRIMARY)?.value else {
+        return None;
+    };
+    let degrees = dms_to_degrees(&dms.iter().map(|r| r.to_f64()).collect::<Vec<_>>())?;
+    let is_negative = match &exif.get_field(ref_tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => values
+            .first()
+            .and_then(|value| value.first())
+            .is_some_and(|reference| reference.eq_ignore_ascii_case(&negative_ref)),
+        _ => false,
+    };
+    Some(if is_negative { -degrees } else { degrees })
+}
+
+fn dms_to_degrees(dms: &[f64]) -> Option<f64> {
+    let degrees = dms.first()? + dms.get(1).unwrap_or(&0.0) / 60.0 + dms.get(2).unwrap_or(&0.0) / 3600.0;
+    degrees.is_finite().then_some(degrees)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capture_settings_read_like_on_the_camera() {
+        let settings = CaptureSettings {
+            focal_length: Some(50.0),
+            f_number: Some(1.8),
+            exposure_time: Some(0.005),
+            iso: Some(400),
+        };
+        assert_eq!(settings.summary().as_deref(), Some("50mm · f/1.8 · 1/200 · ISO 400"));
+        let long_exposure = CaptureSettings { exposure_time: Some(2.5), ..Default::default() };
+        assert_eq!(long_exposure.summary().as_deref(), Some("2.5s"));
+        assert_eq!(CaptureSettings::default().summary(), None);
+    }
+}
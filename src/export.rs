@@ -0,0 +1,595 @@
+use std::{
+    collections::HashMap,
+    io::{ErrorKind, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    time::Duration,
+};
+
+use eframe::egui;
+
+use crate::{decode, metadata::{self, XmpFields}, pages, settings::VerifyCopies, watermark::Watermark};
+
+const COPY_ATTEMPTS: u32 = 4;
+const VERIFIED_COPY_ATTEMPTS: u32 = 3; // Copies made before a copy that keeps coming out different counts as failed
+const FIRST_RETRY_DELAY: Duration = Duration::from_millis(250); // Doubled after every failed attempt
+const COMPARED_BYTES: u64 = 1 << 16; // From each end of a file, when telling a copy from another file of the same name
+
+/// Outcome of copying a batch of kept images.
+#[derive(Default)]
+pub struct CopyReport {
+    pub output_folder: PathBuf,
+    pub copied: usize,
+    pub failed: Vec<(PathBuf, String)>, // Kept image and why it (or one of its sidecars) failed
+    pub quarantined: usize, // Images that could not be decoded, copied aside instead of into their bucket
+    pub kept_copies: Vec<PathBuf>, // Where the kept images were copied to
+    pub verified: bool, // Every copy was checked against its original
+    pub recopied: usize, // Files whose first copy did not match the original, copied again
+    pub unwatermarked: usize, // Kept images copied without the watermark, as only JPEGs get one
+}
+
+pub const DECODE_ERROR_LOG: &str = "decode_errors.log";
+
+/// Lists the images copied into the quarantine folder and why they could not be decoded,
+/// one `path: error` line each.
+pub fn write_decode_error_log(folder: &Path, errors: &[(PathBuf, String)]) -> std::io::Result<()> {
+    let log: String = errors.iter().map(|(path, error)| format!("{}: {}\n", path.display(), error)).collect();
+    std::fs::write(folder.join(DECODE_ERROR_LOG), log)
+}
+
+/// One image to copy, with everything about it worked out beforehand, so the copying itself
+/// can run on a thread of its own.
+pub struct CopyTask {
+    pub image: PathBuf,
+    pub destination: Result<PathBuf, String>,
+    pub watermark: bool, // Re-encoded with the watermark, if it turns out to be a JPEG
+    pub xmp: XmpFields,
+    pub kept: bool,
+}
+
+/// Images going into one output folder.
+pub struct CopyBatch {
+    pub output_folder: PathBuf,
+    pub tasks: Vec<CopyTask>,
+    pub decode_errors: Option<Vec<(PathBuf, String)>>, // For the quarantine: logged next to the copies
+}
+
+/// A whole copy, batch by batch.
+pub struct CopyPlan {
+    pub batches: Vec<CopyBatch>,
+    pub working_path: PathBuf, // Reported as the output folder when there is not exactly one
+    pub verify: VerifyCopies,
+    pub preserve_timestamps: bool,
+    pub watermark: Watermark,
+}
+
+impl CopyPlan {
+    /// Copies every image, calling `on_image` after each. A file that keeps failing is
+    /// recorded in the report and the rest of the batch carries on.
+    pub fn run(&self, mut on_image: impl FnMut()) -> Result<CopyReport, Box<dyn std::error::Error>> {
+        let mut report = CopyReport::default();
+        let mut output_folders = Vec::new();
+        for batch in &self.batches {
+            std::fs::create_dir_all(&batch.output_folder)?;
+            let mut copied = 0;
+            for task in &batch.tasks {
+                match self.copy_image(task, &mut report) {
+                    Ok(destination) => {
+                        copied += 1;
+                        if task.kept {
+                            report.kept_copies.push(destination);
+                        }
+                    },
+                    Err(e) => {
+                        log::warn!("Giving up on copying {}: {}", task.image.display(), e);
+                        report.failed.push((task.image.clone(), e.to_string()));
+                    },
+                }
+                on_image();
+            }
+            match &batch.decode_errors {
+                Some(errors) => {
+                    write_decode_error_log(&batch.output_folder, errors)?;
+                    report.quarantined += copied;
+                },
+                None => {
+                    report.copied += copied;
+                    output_folders.push(batch.output_folder.clone());
+                },
+            }
+        }
+        report.output_folder = match output_folders.len() {
+            1 => output_folders.remove(0),
+            _ => self.working_path.clone(),
+        };
+        report.verified = self.verify != VerifyCopies::Off;
+        Ok(report)
+    }
+
+    pub fn image_count(&self) -> usize {
+        self.batches.iter().map(|batch| batch.tasks.len()).sum()
+    }
+
+    /// Returns where the image was copied to.
+    /// Copies that had to be made again to match their original are counted in `report`.
+    fn copy_image(&self, task: &CopyTask, report: &mut CopyReport) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let image = task.image.as_path();
+        let destination_path = task.destination.clone()?;
+
+        // Create parent directories if they don't exist
+        if let Some(parent) = destination_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Copy the JPEG file, or re-encode it with the proof watermark; RAW files below are never touched
+        let source = pages::file_of(image); // Pages take the timestamps of their file
+        let watermarked = task.watermark && !pages::is_page(image) && decode::has_jpeg_signature(image);
+        if task.watermark && !watermarked {
+            report.unwatermarked += 1;
+        }
+        if pages::is_page(image) {
+            pages::write_page(image, &destination_path).map_err(|e| format!("page: {}", e))?;
+        } else if watermarked {
+            self.watermark.write_copy(image, &destination_path)
+                .map_err(|e| format!("watermark: {}", e))?;
+        } else {
+            let recopies = copy_verified(image, &destination_path, self.verify)?;
+            report.recopied += recopies as usize;
+        }
+        if self.preserve_timestamps {
+            copy_timestamps(&source, &destination_path).map_err(|e| format!("timestamps: {}", e))?;
+        }
+
+        // Hand ratings and labels over to Lightroom/Bridge through an XMP sidecar
+        if !task.xmp.is_empty() {
+            metadata::write_xmp_sidecar(&destination_path, &task.xmp)
+                .map_err(|e| format!("XMP sidecar: {}", e))?;
+            if self.preserve_timestamps {
+                // The sidecar is new, so it takes on the timestamps of the image it describes
+                copy_timestamps(&source, &metadata::sidecar_path(&destination_path))
+                    .map_err(|e| format!("XMP sidecar timestamps: {}", e))?;
+            }
+        }
+
+        // Check for corresponding CR3 (Canon RAW) file and copy it too
+        if let Some(cr3_path) = raw_sidecar(image) {
+            let cr3_destination = destination_path.with_extension(cr3_path.extension().unwrap_or_default());
+            let recopies = copy_verified(&cr3_path, &cr3_destination, self.verify)
+                .map_err(|e| format!("{}: {}", cr3_path.display(), e))?;
+            report.recopied += recopies as usize;
+            if self.preserve_timestamps {
+                copy_timestamps(&cr3_path, &cr3_destination)
+                    .map_err(|e| format!("{} timestamps: {}", cr3_path.display(), e))?;
+            }
+        }
+        Ok(destination_path)
+    }
+}
+
+/// The CR3 (Canon RAW) file next to an image, trying both uppercase and lowercase extensions.
+/// None for RAW files reviewed by their preview, which are their own RAW.
+pub fn raw_sidecar(image: &Path) -> Option<PathBuf> {
+    if crate::scan::is_raw(image) || crate::pages::is_page(image) {
+        return None;
+    }
+    let stem = image.file_stem()?.to_string_lossy();
+    ["CR3", "cr3"]
+        .into_iter()
+        .map(|extension| image.with_file_name(format!("{}.{}", stem, extension)))
+        .find(|raw| raw.exists())
+}
+
+/// A copy running on its own thread, so slow or flaky drives do not hold up the window.
+pub struct CopyJob {
+    receiver: Receiver<Option<Result<CopyReport, String>>>, // None for every image done, then the outcome
+    pub total: usize,
+    pub done: usize,
+    pub result: Option<Result<CopyReport, String>>,
+}
+
+impl CopyJob {
+    pub fn start(plan: CopyPlan, ctx: egui::Context) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let total = plan.image_count();
+        std::thread::Builder::new()
+            .name("copy".to_string())
+            .spawn(move || {
+                let progress = sender.clone();
+                let repaint = ctx.clone();
+                let result = plan.run(|| {
+                    let _ = progress.send(None);
+                    repaint.request_repaint();
+                });
+                let _ = sender.send(Some(result.map_err(|e| e.to_string())));
+                ctx.request_repaint();
+            })
+            .expect("failed to spawn copy thread");
+        Self { receiver, total, done: 0, result: None }
+    }
+
+    /// Collects what the thread has done since the last call.
+    pub fn poll(&mut self) {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(None) => self.done += 1,
+                Ok(Some(result)) => self.result = Some(result),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.result.get_or_insert_with(|| Err("the copy thread stopped unexpectedly".to_string()));
+                    break;
+                },
+            }
+        }
+    }
+
+    /// Blocks until the thread is done.
+    pub fn wait(&mut self) {
+        while self.result.is_none() {
+            match self.receiver.recv() {
+                Ok(None) => self.done += 1,
+                Ok(Some(result)) => self.result = Some(result),
+                Err(_) => self.result = Some(Err("the copy thread stopped unexpectedly".to_string())),
+            }
+        }
+    }
+}
+
+/// Copies a file, retrying with an increasing delay when the error looks like a hiccup
+/// (network shares and flaky USB drives) rather than something retrying cannot fix.
+pub fn copy_with_retry(source: &Path, destination: &Path) -> std::io::Result<u64> {
+    let mut delay = FIRST_RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        match std::fs::copy(source, destination) {
+            Ok(bytes) => return Ok(bytes),
+            Err(e) if attempt < COPY_ATTEMPTS && is_transient(&e) => {
+                log::warn!("Copying {} failed (attempt {}), retrying: {}", source.display(), attempt, e);
+                std::thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            },
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Copies a file like `copy_with_retry`, then checks the copy against the original and copies
+/// again when they differ, as flaky drives can cut a copy short without reporting an error.
+/// Returns how many extra copies it took.
+pub fn copy_verified(source: &Path, destination: &Path, verify: VerifyCopies) -> std::io::Result<u32> {
+    let mut attempt = 1;
+    loop {
+        copy_with_retry(source, destination)?;
+        let mismatch = match verify {
+            VerifyCopies::Off => None,
+            VerifyCopies::Size => size_mismatch(source, destination)?,
+            VerifyCopies::Contents => match size_mismatch(source, destination)? {
+                None if !same_contents(source, destination)? => Some("the contents differ".to_string()),
+                mismatch => mismatch,
+            },
+        };
+        let Some(mismatch) = mismatch else {
+            return Ok(attempt - 1);
+        };
+        if attempt >= VERIFIED_COPY_ATTEMPTS {
+            return Err(std::io::Error::other(format!("the copy does not match the original: {}", mismatch)));
+        }
+        log::warn!("The copy of {} does not match the original ({}), copying again", source.display(), mismatch);
+        attempt += 1;
+    }
+}
+
+fn size_mismatch(source: &Path, destination: &Path) -> std::io::Result<Option<String>> {
+    let (expected, actual) = (std::fs::metadata(source)?.len(), std::fs::metadata(destination)?.len());
+    Ok((expected != actual).then(|| format!("{} of {} bytes", actual, expected)))
+}
+
+/// Reads both files through and compares them; they are assumed to be the same size.
+fn same_contents(a: &Path, b: &Path) -> std::io::Result<bool> {
+    let (mut a, mut b) = (std::fs::File::open(a)?, std::fs::File::open(b)?);
+    let (mut buffer_a, mut buffer_b) = (vec![0; 1 << 16], vec![0; 1 << 16]);
+    loop {
+        let read = a.read(&mut buffer_a)?;
+        if read == 0 {
+            return Ok(true);
+        }
+        b.read_exact(&mut buffer_b[..read])?;
+        if buffer_a[..read] != buffer_b[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+/// Files already in an output folder that an export would not write itself, e.g. left there
+/// by a session on other images.
+pub struct OutputConflict {
+    pub folder: PathBuf,
+    pub files: Vec<PathBuf>,
+    pub bytes: u64,
+}
+
+/// What a file an export writes is made from, which a file already at its path has to
+/// match to count as an earlier export of the same thing.
+pub enum Origin {
+    Copy(PathBuf), // Copied as it is, so of the same size and contents
+    Derived(PathBuf), // Made from it, e.g. watermarked, so only its timestamps can tell
+    SidecarOf(PathBuf), // Written next to this file of the export, so as much its own as that is
+    Own, // Written from the session's own data, or moved out by it
+}
+
+/// Whether `existing` is what an export from `origin` would have put there.
+fn matches_origin(existing: &Path, origin: &Origin, expected: &HashMap<&Path, &Origin>) -> bool {
+    match origin {
+        Origin::Copy(source) => looks_like_copy(source, existing).unwrap_or(false),
+        Origin::Derived(source) => {
+            let modified = |path: &Path| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+            modified(source).is_some_and(|source_modified| modified(existing) == Some(source_modified))
+        },
+        Origin::SidecarOf(file) => expected
+            .get(file.as_path())
+            .is_some_and(|origin| !matches!(origin, Origin::SidecarOf(_)) && matches_origin(file, origin, expected)),
+        Origin::Own => true,
+    }
+}
+
+/// Whether two files are of the same size and start and end the same, which tells camera
+/// files of the same name from different cards apart without reading them whole.
+fn looks_like_copy(a: &Path, b: &Path) -> std::io::Result<bool> {
+    let length = std::fs::metadata(a)?.len();
+    if std::fs::metadata(b)?.len() != length {
+        return Ok(false);
+    }
+    let (mut a, mut b) = (std::fs::File::open(a)?, std::fs::File::open(b)?);
+    let compared = COMPARED_BYTES.min(length) as usize;
+    let (mut buffer_a, mut buffer_b) = (vec![0; compared], vec![0; compared]);
+    for offset in [0, length - compared as u64] {
+        a.seek(SeekFrom::Start(offset))?;
+        b.seek(SeekFrom::Start(offset))?;
+        a.read_exact(&mut buffer_a)?;
+        b.read_exact(&mut buffer_b)?;
+        if buffer_a != buffer_b {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// What in `folder` is not one of the `expected` files, by exact path, or is at the path of
+/// one but does not match its origin. None when there is nothing else.
+pub fn find_conflict(folder: &Path, expected: &[(PathBuf, Origin)]) -> Option<OutputConflict> {
+    let expected: HashMap<&Path, &Origin> = expected.iter().map(|(path, origin)| (path.as_path(), origin)).collect();
+    let mut conflict = OutputConflict { folder: folder.to_path_buf(), files: Vec::new(), bytes: 0 };
+    let mut folders = vec![folder.to_path_buf()];
+    while let Some(current) = folders.pop() {
+        for entry in std::fs::read_dir(&current).into_iter().flatten().flatten() {
+            let path = entry.path();
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                folders.push(path);
+            } else if !expected.get(path.as_path()).is_some_and(|origin| matches_origin(&path, origin, &expected)) {
+                conflict.bytes += metadata.len();
+                conflict.files.push(path);
+            }
+        }
+    }
+    conflict.files.sort();
+    (!conflict.files.is_empty()).then_some(conflict)
+}
+
+/// A folder next to `folder` that does not exist yet, named after it with `suffix` and,
+/// if need be, a number.
+pub fn free_sibling_folder(folder: &Path, suffix: &str) -> PathBuf {
+    let name = folder.file_name().unwrap_or_default().to_string_lossy();
+    (1..)
+        .map(|number| match number {
+            1 => folder.with_file_name(format!("{}{}", name, suffix)),
+            number => folder.with_file_name(format!("{}{}_{}", name, suffix, number)),
+        })
+        .find(|candidate| !candidate.exists())
+        .expect("some numbered folder is free")
+}
+
+/// Moves the files of `conflict` out of the way into `aside`, in the same subfolders, and
+/// removes the subfolders this empties.
+pub fn move_conflict_aside(conflict: &OutputConflict, aside: &Path, verify: VerifyCopies) -> std::io::Result<()> {
+    for file in &conflict.files {
+        let relative = file.strip_prefix(&conflict.folder).unwrap_or(file);
+        let destination = aside.join(relative);
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        move_file(file, &destination, verify)?;
+        let mut emptied = file.parent();
+        while let Some(folder) = emptied.filter(|folder| *folder != conflict.folder && std::fs::remove_dir(folder).is_ok()) {
+            emptied = folder.parent();
+        }
+    }
+    Ok(())
+}
+
+/// Moves a file, falling back to copying and removing the original when `destination` is on
+/// another drive. The original is only removed once the copy checks out by `verify`, and at
+/// least by its size. An existing file at `destination` is never replaced.
+pub fn move_file(source: &Path, destination: &Path, verify: VerifyCopies) -> std::io::Result<()> {
+    if destination.exists() {
+        return Err(std::io::Error::new(ErrorKind::AlreadyExists, format!("{} already exists", destination.display())));
+    }
+    match std::fs::rename(source, destination) {
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => {
+            if let Err(e) = copy_verified(source, destination, verify.max(VerifyCopies::Size)) {
+                let _ = std::fs::remove_file(destination); // A copy that cannot be trusted is no move
+                return Err(e);
+            }
+            copy_timestamps(source, destination)?;
+            std::fs::remove_file(source)
+        },
+        result => result,
+    }
+}
+
+/// The files of one image to move: the image and its sidecars, from and to.
+pub type FileMoves = Vec<(PathBuf, PathBuf)>;
+
+/// How moving the files of one image went.
+pub struct MoveOutcome {
+    pub image: PathBuf,
+    pub back: bool, // Moved back to where it was found, rather than out
+    pub moved: FileMoves, // The files that arrived, in order
+    pub error: Option<(PathBuf, String)>, // The file it stopped at and why
+}
+
+struct MoveJob {
+    image: PathBuf,
+    files: FileMoves,
+    back: bool,
+    verify: VerifyCopies,
+}
+
+/// Moves the files of decided images on a thread of its own, one image after the other, so
+/// moving to another drive, which copies, does not hold up the review.
+pub struct MoveQueue {
+    sender: Sender<MoveJob>,
+    receiver: Receiver<MoveOutcome>,
+    in_flight: Vec<PathBuf>, // Once per move, as an image can be on its way out and back at once
+}
+
+impl MoveQueue {
+    pub fn start() -> Self {
+        let (sender, jobs) = mpsc::channel::<MoveJob>();
+        let (outcomes, receiver) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("moves".to_string())
+            .spawn(move || {
+                for MoveJob { image, files, back, verify } in jobs {
+                    let mut moved = Vec::new();
+                    let mut error = None;
+                    for (from, to) in files {
+                        // Files whose move out failed never left, so they are back already
+                        if back && !from.exists() && to.exists() {
+                            moved.push((from, to));
+                            continue;
+                        }
+                        match to.parent().map_or(Ok(()), std::fs::create_dir_all).and_then(|()| move_file(&from, &to, verify)) {
+                            Ok(()) => moved.push((from, to)),
+                            Err(e) => {
+                                error = Some((from, e.to_string()));
+                                break;
+                            },
+                        }
+                    }
+                    if outcomes.send(MoveOutcome { image, back, moved, error }).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("failed to spawn move thread");
+        Self { sender, receiver, in_flight: Vec::new() }
+    }
+
+    /// Queues moving `files`, out to an output folder or, with `back`, back from one.
+    pub fn submit(&mut self, image: PathBuf, files: FileMoves, back: bool, verify: VerifyCopies) {
+        self.in_flight.push(image.clone());
+        let _ = self.sender.send(MoveJob { image, files, back, verify });
+    }
+
+    fn landed(&mut self, image: &Path) {
+        if let Some(index) = self.in_flight.iter().position(|in_flight| in_flight == image) {
+            self.in_flight.remove(index);
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.in_flight.is_empty()
+    }
+
+    /// Returns the moves finished since the last call without blocking.
+    pub fn finished(&mut self) -> Vec<MoveOutcome> {
+        let outcomes: Vec<MoveOutcome> = self.receiver.try_iter().collect();
+        for outcome in &outcomes {
+            self.landed(&outcome.image);
+        }
+        outcomes
+    }
+
+    /// Waits until the files of `image`, or of every image with None, have been moved, and
+    /// returns the moves finished meanwhile.
+    pub fn wait_for(&mut self, image: Option<&Path>) -> Vec<MoveOutcome> {
+        let mut outcomes = self.finished();
+        while image.map_or(!self.in_flight.is_empty(), |image| self.in_flight.iter().any(|in_flight| in_flight == image)) {
+            let Ok(outcome) = self.receiver.recv() else {
+                self.in_flight.clear(); // The thread is gone; nothing more is coming
+                break;
+            };
+            self.landed(&outcome.image);
+            outcomes.push(outcome);
+        }
+        outcomes
+    }
+}
+
+/// Gives `destination` the modified and accessed times of `source`, plus the creation
+/// time on platforms that let it be set.
+pub fn copy_timestamps(source: &Path, destination: &Path) -> std::io::Result<()> {
+    let metadata = std::fs::metadata(source)?;
+    filetime::set_file_times(
+        destination,
+        filetime::FileTime::from_last_access_time(&metadata),
+        filetime::FileTime::from_last_modification_time(&metadata),
+    )?;
+    copy_creation_time(&metadata, destination)
+}
+
+#[cfg(any(windows, target_os = "macos"))]
+fn copy_creation_time(metadata: &std::fs::Metadata, destination: &Path) -> std::io::Result<()> {
+    #[cfg(target_os = "macos")]
+    use std::os::macos::fs::FileTimesExt;
+    #[cfg(windows)]
+    use std::os::windows::fs::FileTimesExt;
+
+    let Ok(created) = metadata.created() else {
+        return Ok(()); // The source's file system does not record it
+    };
+    let file = std::fs::File::options().write(true).open(destination)?;
+    file.set_times(std::fs::FileTimes::new().set_created(created))
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn copy_creation_time(_metadata: &std::fs::Metadata, _destination: &Path) -> std::io::Result<()> {
+    Ok(()) // Linux has no way to set the birth time
+}
+
+fn is_transient(error: &std::io::Error) -> bool {
+    !matches!(
+        error.kind(),
+        ErrorKind::NotFound
+            | ErrorKind::PermissionDenied
+            | ErrorKind::AlreadyExists
+            | ErrorKind::InvalidInput
+            | ErrorKind::InvalidFilename
+            | ErrorKind::ReadOnlyFilesystem
+            | ErrorKind::StorageFull
+            | ErrorKind::Unsupported
+            | ErrorKind::IsADirectory
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn verified_copies_match_their_originals() {
+        let root = TempDir::new("verify");
+        let source = root.join("IMG_0001.jpg");
+        let contents: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&source, &contents).unwrap();
+        let destination = root.join("copy.jpg");
+        let recopies = copy_verified(&source, &destination, crate::settings::VerifyCopies::Contents);
+        let copied = std::fs::read(&destination);
+        assert_eq!(recopies.unwrap(), 0, "a good copy is not made again");
+        assert_eq!(copied.unwrap(), contents);
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+};
+
+use eframe::egui;
+
+use crate::decode::{DecodeOptions, JpegBackend};
+
+/// Whether this build can recognize faces with dlib (the `faces` feature).
+pub const FACES_AVAILABLE: bool = cfg!(feature = "faces");
+
+// Faces stay recognizable well below full resolution, and dlib's detector slows down a lot above it
+const FACE_MAX_DIMENSION: u32 = 1600;
+
+#[cfg_attr(not(feature = "faces"), allow(dead_code))] // Only the failure is sent without the feature
+pub enum FaceMessage {
+    Checked { path: PathBuf, matched: bool },
+    Unreadable, // Could not be decoded; left for the user to decide
+    Failed(String), // The search cannot go on at all, e.g. no face in the reference
+}
+
+/// A search for the person in a reference photo through a list of images, running on
+/// its own thread since every image needs full pixels and a neural network pass.
+pub struct FaceSearch {
+    receiver: Receiver<FaceMessage>,
+    pub total: usize,
+    pub matches: Vec<PathBuf>,
+    pub misses: Vec<PathBuf>,
+    pub unreadable: usize,
+    pub error: Option<String>,
+    pub finished: bool,
+}
+
+impl FaceSearch {
+    /// Dropping the search stops the thread after the image it is working on.
+    pub fn start(reference: PathBuf, images: Vec<PathBuf>, tolerance: f64, jpeg_backend: JpegBackend, ctx: egui::Context) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let total = images.len();
+        let options = DecodeOptions { max_dimension: Some(FACE_MAX_DIMENSION), jpeg_backend };
+        std::thread::Builder::new()
+            .name("faces".to_string())
+            .spawn(move || {
+                search(&reference, images, tolerance, &options, &sender, &ctx);
+                ctx.request_repaint(); // Let the UI notice the closed channel
+            })
+            .expect("failed to spawn face search thread");
+        Self {
+            receiver,
+            total,
+            matches: Vec::new(),
+            misses: Vec::new(),
+            unreadable: 0,
+            error: None,
+            finished: false,
+        }
+    }
+
+    pub fn checked(&self) -> usize {
+        self.matches.len() + self.misses.len() + self.unreadable
+    }
+
+    /// Collects what the thread found since the last call.
+    pub fn poll(&mut self) {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(FaceMessage::Checked { path, matched: true }) => self.matches.push(path),
+                Ok(FaceMessage::Checked { path, matched: false }) => self.misses.push(path),
+                Ok(FaceMessage::Unreadable) => self.unreadable += 1,
+                Ok(FaceMessage::Failed(error)) => self.error = Some(error),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    break;
+                },
+            }
+        }
+    }
+}
+
+#[cfg(feature = "faces")]
+fn search(
+    reference: &std::path::Path,
+    images: Vec<PathBuf>,
+    tolerance: f64,
+    options: &DecodeOptions,
+    sender: &Sender<FaceMessage>,
+    ctx: &egui::Context,
+) {
+    use dlib_face_recognition::{
+        FaceDetector, FaceDetectorTrait, FaceEncoderNetwork, FaceEncoderTrait, FaceEncoding, ImageMatrix, LandmarkPredictor,
+        LandmarkPredictorTrait,
+    };
+
+    let detector = FaceDetector::default();
+    let predictor = LandmarkPredictor::default();
+    let encoder = FaceEncoderNetwork::default();
+    let encodings = |image: &egui::ColorImage| -> Vec<FaceEncoding> {
+        let [width, height] = image.size;
+        let pixels: Vec<u8> = image.pixels.iter().flat_map(|pixel| [pixel.r(), pixel.g(), pixel.b()]).collect();
+        // SAFETY: `pixels` holds width * height RGB triples and outlives the matrix, which copies them
+        let matrix = unsafe { ImageMatrix::new(width, height, pixels.as_ptr()) };
+        let landmarks: Vec<_> = detector
+            .face_locations(&matrix)
+            .iter()
+            .map(|face| predictor.face_landmarks(&matrix, face))
+            .collect();
+        encoder.get_face_encodings(&matrix, &landmarks, 0).to_vec()
+    };
+
+    let reference_faces = match crate::decode::decode_image(reference, options) {
+        Ok(image) => encodings(&image),
+        Err(e) => {
+            let _ = sender.send(FaceMessage::Failed(format!("The reference photo could not be read: {}", e)));
+            return;
+        },
+    };
+    let [reference_face] = reference_faces.as_slice() else {
+        let _ = sender.send(FaceMessage::Failed(format!(
+            "The reference photo needs exactly one face, it has {}",
+            reference_faces.len()
+        )));
+        return;
+    };
+    for path in images {
+        let message = match crate::decode::decode_image(&path, options) {
+            Ok(image) => {
+                let matched = encodings(&image).iter().any(|face| face.distance(reference_face) <= tolerance);
+                FaceMessage::Checked { path, matched }
+            },
+            Err(e) => {
+                log::warn!("Could not look for faces in {}: {}", path.display(), e);
+                FaceMessage::Unreadable
+            },
+        };
+        if sender.send(message).is_err() {
+            return; // The search was cancelled
+        }
+        ctx.request_repaint();
+    }
+}
+
+#[cfg(not(feature = "faces"))]
+fn search(
+    _reference: &std::path::Path,
+    _images: Vec<PathBuf>,
+    _tolerance: f64,
+    _options: &DecodeOptions,
+    sender: &Sender<FaceMessage>,
+    _ctx: &egui::Context,
+) {
+    let _ = sender.send(FaceMessage::Failed("This build was made without the faces feature".to_string()));
+}
+
+/// Faces found in one image, as their boxes in texture coordinates (0 to 1), largest first.
+pub type FaceBoxes = Vec<egui::Rect>;
+
+/// Face detection in the decoded current image on its own thread, for zooming to a face.
+/// Only finds faces, without recognizing whose they are, so it is quick.
+pub struct FaceDetection {
+    pub path: PathBuf,
+    receiver: Receiver<Result<FaceBoxes, String>>,
+}
+
+impl FaceDetection {
+    pub fn start(path: PathBuf, image: Arc<egui::ColorImage>, ctx: egui::Context) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("face detection".to_string())
+            .spawn(move || {
+                let _ = sender.send(detect(&image));
+                ctx.request_repaint();
+            })
+            .expect("failed to spawn face detection thread");
+        Self { path, receiver }
+    }
+
+    /// The faces once the thread is done with them.
+    pub fn poll(&self) -> Option<Result<FaceBoxes, String>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => Some(Err("the face detection thread stopped unexpectedly".to_string())),
+        }
+    }
+}
+
+#[cfg(feature = "faces")]
+fn detect(image: &egui::ColorImage) -> Result<FaceBoxes, String> {
+    use dlib_face_recognition::{FaceDetector, FaceDetectorTrait, ImageMatrix};
+
+    // Every step-th pixel, which is plenty to find faces in and keeps the detector fast
+    let [width, height] = image.size;
+    let step = (width.max(height) as u32).div_ceil(FACE_MAX_DIMENSION).max(1) as usize;
+    let (small_width, small_height) = (width.div_ceil(step), height.div_ceil(step));
+    let pixels: Vec<u8> = (0..height)
+        .step_by(step)
+        .flat_map(|y| (0..width).step_by(step).map(move |x| image.pixels[y * width + x]))
+        .flat_map(|pixel| [pixel.r(), pixel.g(), pixel.b()])
+        .collect();
+    // SAFETY: `pixels` holds small_width * small_height RGB triples and outlives the matrix, which copies them
+    let matrix = unsafe { ImageMatrix::new(small_width, small_height, pixels.as_ptr()) };
+    let size = egui::vec2(small_width as f32, small_height as f32);
+    let mut faces: FaceBoxes = FaceDetector::default()
+        .face_locations(&matrix)
+        .iter()
+        .map(|face| {
+            let min = egui::pos2(face.left as f32, face.top as f32);
+            let max = egui::pos2(face.right as f32, face.bottom as f32);
+            egui::Rect::from_min_max((min.to_vec2() / size).to_pos2(), (max.to_vec2() / size).to_pos2())
+        })
+        .collect();
+    faces.sort_by(|a, b| b.area().total_cmp(&a.area()));
+    Ok(faces)
+}
+
+#[cfg(not(feature = "faces"))]
+fn detect(_image: &egui::ColorImage) -> Result<FaceBoxes, String> {
+    Err("This build was made without the faces feature".to_string())
+}
+
@@ -0,0 +1,79 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use eframe::egui;
+
+use crate::{scan::FileSysNode, Decision};
+
+/// Decision counts for one folder including everything below it, mirroring the tree.
+#[derive(Default)]
+pub struct FolderTally {
+    pub kept: usize,
+    pub discarded: usize,
+    pub remaining: usize,
+    pub children: Vec<FolderTally>,
+}
+
+impl FolderTally {
+    pub fn total(&self) -> usize {
+        self.kept + self.discarded + self.remaining
+    }
+}
+
+/// Tallies `node` against the decision map; images without a decision count as remaining.
+pub fn tally(node: &FileSysNode, base_path: &Path, decisions: &HashMap<PathBuf, Decision>) -> FolderTally {
+    let mut result = FolderTally::default();
+    for image in &node.images {
+        match decisions.get(&base_path.join(image)) {
+            Some(Decision::Keep) => result.kept += 1,
+            Some(Decision::Discard) => result.discarded += 1,
+            None => result.remaining += 1,
+        }
+    }
+    for child in &node.children {
+        let child_tally = tally(child, &base_path.join(&child.name), decisions);
+        result.kept += child_tally.kept;
+        result.discarded += child_tally.discarded;
+        result.remaining += child_tally.remaining;
+        result.children.push(child_tally);
+    }
+    result
+}
+
+/// Draws `node` and its subfolders as a collapsible tree with per-folder progress.
+pub fn show_folder_tree(ui: &mut egui::Ui, node: &FileSysNode, tally: &FolderTally, id_path: &Path) {
+    let name = display_name(node);
+    let id = ui.make_persistent_id(id_path);
+    if node.children.is_empty() {
+        ui.horizontal(|ui| {
+            ui.add_space(ui.spacing().indent);
+            folder_row(ui, &name, tally);
+        });
+        return;
+    }
+    egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, false)
+        .show_header(ui, |ui| folder_row(ui, &name, tally))
+        .body(|ui| {
+            for (child, child_tally) in node.children.iter().zip(&tally.children) {
+                show_folder_tree(ui, child, child_tally, &id_path.join(&child.name));
+            }
+        });
+}
+
+fn folder_row(ui: &mut egui::Ui, name: &str, tally: &FolderTally) {
+    let decided = tally.kept + tally.discarded;
+    let fraction = if tally.total() > 0 { decided as f32 / tally.total() as f32 } else { 1.0 };
+    ui.label(name);
+    ui.add(egui::ProgressBar::new(fraction).desired_width(60.0));
+    ui.weak(format!("✅{} ❌{} ⏳{}", tally.kept, tally.discarded, tally.remaining));
+}
+
+/// Roots are named by their full path; show just the last component like any other folder.
+fn display_name(node: &FileSysNode) -> String {
+    let path = Path::new(&node.name);
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string_lossy().to_string())
+}
@@ -0,0 +1,80 @@
+use std::path::{Path, PathBuf};
+
+use ab_glyph::Font;
+use eframe::egui;
+
+/// DejaVu Sans, for the arrows and symbols in labels that egui's built-in fonts lack. Bundled so
+/// the labels read the same everywhere; see `assets/DejaVuSans-LICENSE.txt`.
+const BUNDLED_FONT: &[u8] = include_bytes!("../assets/DejaVuSans.ttf");
+
+/// Installs egui's default fonts, the font file at `configured` if one is set, and the bundled
+/// font behind them for whatever glyphs are still missing. Returns the configured font in use,
+/// or why it could not be used (the others are installed either way).
+pub fn install_fallback_font(ctx: &egui::Context, configured: &str) -> Result<Option<PathBuf>, String> {
+    let configured = configured.trim();
+    let configured = if configured.is_empty() {
+        Ok(None)
+    } else {
+        let path = PathBuf::from(configured);
+        load_font(&path).map(|data| Some((path, data)))
+    };
+
+    let mut fonts = egui::FontDefinitions::default();
+    let fallbacks = configured.as_ref().ok().and_then(Option::as_ref).map(|(_, data)| ("configured", data.clone()));
+    for (name, data) in fallbacks.into_iter().chain([("bundled", BUNDLED_FONT.to_vec())]) {
+        fonts.font_data.insert(name.to_string(), std::sync::Arc::new(egui::FontData::from_owned(data)));
+        for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+            fonts.families.entry(family).or_default().push(name.to_string()); // Last, so it only fills gaps
+        }
+    }
+    ctx.set_fonts(fonts);
+    configured.map(|configured| configured.map(|(path, _)| path))
+}
+
+/// Reads a font file and checks it the way egui will, which would otherwise panic on it.
+fn load_font(path: &Path) -> Result<Vec<u8>, String> {
+    let data = std::fs::read(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    let font = ab_glyph::FontRef::try_from_slice(&data).map_err(|e| format!("{} is not a usable font: {}", path.display(), e))?;
+    let units_per_em = font.units_per_em().unwrap_or(0.0);
+    if !(16.0..=16384.0).contains(&units_per_em) {
+        return Err(format!("{} is not a usable font: unusual glyph size", path.display()));
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_symbol_in_the_labels_has_a_glyph_without_system_fonts() {
+        let fonts: Vec<ab_glyph::FontRef> = [
+            epaint_default_fonts::UBUNTU_LIGHT,
+            epaint_default_fonts::NOTO_EMOJI_REGULAR,
+            epaint_default_fonts::EMOJI_ICON,
+            BUNDLED_FONT,
+        ]
+        .into_iter()
+        .map(|data| ab_glyph::FontRef::try_from_slice(data).unwrap())
+        .collect();
+        let sources = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap();
+        let mut missing: Vec<char> = sources
+            .flat_map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap().chars().collect::<Vec<_>>())
+            .filter(|symbol| !symbol.is_ascii() && !fonts.iter().any(|font| font.glyph_id(*symbol).0 != 0))
+            .collect();
+        missing.sort_unstable();
+        missing.dedup();
+        assert_eq!(missing, Vec::<char>::new());
+    }
+
+    #[test]
+    fn a_broken_font_setting_is_reported_and_the_bundled_font_used_anyway() {
+        let ctx = egui::Context::default();
+        let broken = install_fallback_font(&ctx, "/nonexistent/font.ttf");
+        let _ = ctx.run(egui::RawInput::default(), |_| {}); // Fonts take effect with the next frame
+        let arrows = ctx.fonts(|fonts| fonts.has_glyphs(&egui::FontId::proportional(14.0), "←→⇅"));
+        assert!(broken.unwrap_err().starts_with("Could not read /nonexistent/font.ttf"));
+        assert!(arrows, "none of egui's own fonts has these");
+        assert_eq!(install_fallback_font(&ctx, " "), Ok(None));
+    }
+}
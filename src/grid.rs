@@ -0,0 +1,51 @@
+use std::{collections::HashSet, path::PathBuf};
+
+use eframe::egui;
+
+pub const THUMBNAIL_SIZE: f32 = 96.0; // Grid cells are square
+
+/// Thumbnails picked in the grid view, to be decided together with one key press.
+#[derive(Default)]
+pub struct GridSelection {
+    pub selected: HashSet<PathBuf>,
+    anchor: Option<usize>, // Index of the last plain or ctrl click, where shift-click ranges start
+}
+
+impl GridSelection {
+    /// Click selects only the clicked image, ctrl/cmd-click adds or removes it, and
+    /// shift-click selects everything from the last click to it (on top of the selection
+    /// when ctrl/cmd is held as well).
+    pub fn click(&mut self, index: usize, images: &[PathBuf], modifiers: egui::Modifiers) {
+        let Some(image) = images.get(index) else {
+            return;
+        };
+        if modifiers.shift
+            && let Some(anchor) = self.anchor.filter(|anchor| *anchor < images.len())
+        {
+            if !modifiers.command {
+                self.selected.clear();
+            }
+            let range = anchor.min(index)..=anchor.max(index);
+            self.selected.extend(images[range].iter().cloned());
+            return;
+        }
+        if modifiers.command {
+            if !self.selected.remove(image) {
+                self.selected.insert(image.clone());
+            }
+        } else {
+            self.selected.clear();
+            self.selected.insert(image.clone());
+        }
+        self.anchor = Some(index);
+    }
+
+    pub fn select_all(&mut self, images: &[PathBuf]) {
+        self.selected.extend(images.iter().cloned());
+    }
+
+    pub fn clear(&mut self) {
+        self.selected.clear();
+        self.anchor = None;
+    }
+}
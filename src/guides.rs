@@ -0,0 +1,64 @@
+use eframe::egui;
+
+const GOLDEN_SECTION: f32 = 0.381_966; // 1 - 1/φ
+
+/// Composition guides drawn over the image in the viewer.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Guide {
+    #[default]
+    Off,
+    RuleOfThirds,
+    CenterCross,
+    GoldenRatio,
+}
+
+impl Guide {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::RuleOfThirds,
+            Self::RuleOfThirds => Self::CenterCross,
+            Self::CenterCross => Self::GoldenRatio,
+            Self::GoldenRatio => Self::Off,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Off => "No guides",
+            Self::RuleOfThirds => "Rule of thirds",
+            Self::CenterCross => "Center cross",
+            Self::GoldenRatio => "Golden ratio",
+        }
+    }
+
+    /// Draws the guide over `image_rect`, the on-screen rectangle of the displayed image.
+    pub fn paint(self, painter: &egui::Painter, image_rect: egui::Rect) {
+        // A dark line under a light one stays visible on bright and dark images alike
+        let strokes = [
+            egui::Stroke::new(3.0, egui::Color32::from_black_alpha(90)),
+            egui::Stroke::new(1.0, egui::Color32::from_white_alpha(180)),
+        ];
+        let fractions: &[f32] = match self {
+            Self::Off => return,
+            Self::RuleOfThirds => &[1.0 / 3.0, 2.0 / 3.0],
+            Self::GoldenRatio => &[GOLDEN_SECTION, 1.0 - GOLDEN_SECTION],
+            Self::CenterCross => {
+                let center = image_rect.center();
+                let arm = image_rect.width().min(image_rect.height()) * 0.05;
+                for stroke in strokes {
+                    painter.line_segment([center - egui::vec2(arm, 0.0), center + egui::vec2(arm, 0.0)], stroke);
+                    painter.line_segment([center - egui::vec2(0.0, arm), center + egui::vec2(0.0, arm)], stroke);
+                }
+                return;
+            },
+        };
+        for stroke in strokes {
+            for &fraction in fractions {
+                let x = image_rect.left() + image_rect.width() * fraction;
+                let y = image_rect.top() + image_rect.height() * fraction;
+                painter.vline(x, image_rect.y_range(), stroke);
+                painter.hline(image_rect.x_range(), y, stroke);
+            }
+        }
+    }
+}
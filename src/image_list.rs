@@ -0,0 +1,82 @@
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use crate::{scan::FileSysNode, selection};
+
+/// Images to review as listed in a text file by another tool, instead of found by a scan.
+pub struct ImageList {
+    pub base: PathBuf, // Deepest folder holding every listed image, or the list's own folder if they share none
+    pub images: Vec<PathBuf>, // In list order, without duplicates
+    pub missing: Vec<String>, // Listed, but not an existing file
+}
+
+/// Reads one path per line, with blank lines and `#` comments skipped like a selection
+/// file. Relative paths are taken from the list's folder.
+pub fn read_image_list(file: &Path) -> std::io::Result<ImageList> {
+    let list_folder = std::path::absolute(file)?.parent().map(Path::to_path_buf).unwrap_or_default();
+    let mut images = Vec::new();
+    let mut seen = HashSet::new();
+    let mut missing = Vec::new();
+    for entry in selection::read_selection_file(file)? {
+        let path = without_dot_dot(&list_folder.join(entry.trim_matches('"')));
+        if !path.is_file() {
+            log::warn!("Skipping {} from {}: no such file", entry, file.display());
+            missing.push(entry);
+        } else if seen.insert(path.clone()) {
+            images.push(path);
+        }
+    }
+    let base = common_folder(&images).unwrap_or(list_folder);
+    Ok(ImageList { base, images, missing })
+}
+
+/// `path` with `.` and `..` resolved by name, so relative entries line up with absolute ones.
+fn without_dot_dot(path: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {},
+            std::path::Component::ParentDir => {
+                resolved.pop();
+            },
+            component => resolved.push(component),
+        }
+    }
+    resolved
+}
+
+/// The deepest folder all of `images` are in. Never the root of a drive, where the progress
+/// and output folders would end up.
+fn common_folder(images: &[PathBuf]) -> Option<PathBuf> {
+    let mut common = images.first()?.parent()?.to_path_buf();
+    for image in images {
+        while !image.starts_with(&common) {
+            common = common.parent()?.to_path_buf();
+        }
+    }
+    common.parent().is_some().then_some(common)
+}
+
+/// The folder tree of the listed images, as a scan of `base` would have built it with only
+/// them in it. Images outside `base` hang below nodes named by their absolute folder, which
+/// joining onto `base` resolves to.
+pub fn build_tree(base: &Path, images: &[PathBuf]) -> FileSysNode {
+    let mut root = FileSysNode {
+        name: base.as_os_str().to_os_string(),
+        ..FileSysNode::default()
+    };
+    for image in images {
+        let (Some(folder), Some(name)) = (image.parent(), image.file_name()) else {
+            continue;
+        };
+        let relative_path: Vec<OsString> = match folder.strip_prefix(base) {
+            Ok(relative) => relative.iter().map(OsString::from).collect(),
+            Err(_) => vec![folder.as_os_str().to_os_string()],
+        };
+        root.insert_folder(&relative_path, vec![name.to_os_string()]);
+    }
+    root
+}
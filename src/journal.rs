@@ -0,0 +1,74 @@
+use std::{
+    collections::HashSet,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{metadata::ColorLabel, PROGRESS_FILE};
+
+pub const JOURNAL_FILE: &str = "bildsak_journal.jsonl";
+
+/// One action since the progress file was last written, as one JSON line of the journal.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum JournalEntry {
+    Decide { path: PathBuf, keep: bool, bucket: Option<String> },
+    Undecide { path: PathBuf }, // Back to the front of the queue
+    Skip { path: PathBuf },
+    Rate { path: PathBuf, rating: Option<u8> },
+    Label { path: PathBuf, label: Option<ColorLabel> },
+    Tag { path: PathBuf, tags: HashSet<String> }, // All of the image's tags after the edit
+    // Written before the files are moved, so a crash halfway can be sorted out from what is where
+    Move { path: PathBuf, files: Vec<(PathBuf, PathBuf)> }, // Image and sidecars, from and to
+    MoveBack { path: PathBuf },
+}
+
+/// Adds `entry` to the end of the journal in `working_path`. Every entry is written out
+/// on its own, so a crash loses at most the action being recorded.
+pub fn append(working_path: &Path, entry: &JournalEntry) -> std::io::Result<()> {
+    let mut line = serde_json::to_string(entry).map_err(std::io::Error::other)?;
+    line.push('\n');
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(working_path.join(JOURNAL_FILE))?;
+    file.write_all(line.as_bytes())
+}
+
+/// Entries of a journal written after the progress file, i.e. actions a crash kept from
+/// being saved. A line cut short by the crash is dropped.
+pub fn read_unsaved(working_path: &Path) -> Vec<JournalEntry> {
+    let journal_path = working_path.join(JOURNAL_FILE);
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    let Some(journal_modified) = modified(&journal_path) else {
+        return Vec::new();
+    };
+    if modified(&working_path.join(PROGRESS_FILE)).is_some_and(|progress_modified| progress_modified > journal_modified) {
+        return Vec::new();
+    }
+    let Ok(text) = std::fs::read_to_string(&journal_path) else {
+        return Vec::new();
+    };
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                log::warn!("Skipping unreadable journal line in {}: {}", journal_path.display(), e);
+                None
+            },
+        })
+        .collect()
+}
+
+/// Empties the journal once everything in it is part of the progress file.
+pub fn clear(working_path: &Path) {
+    let journal_path = working_path.join(JOURNAL_FILE);
+    if let Err(e) = std::fs::remove_file(&journal_path)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        log::warn!("Could not remove {}: {}", journal_path.display(), e);
+    }
+}
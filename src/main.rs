@@ -1,274 +1,4002 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window in release mode (Windows only - Linux GUI apps don't show console by default)
 
+mod about;
+mod blur;
+mod burst;
+mod contact_sheet;
+mod decode;
+mod decode_pool;
+mod enhance;
+mod exif_info;
+mod export;
+mod faces;
+mod folders;
+mod fonts;
+mod grid;
+mod guides;
+mod image_list;
+mod journal;
+mod metadata;
+mod orientation;
+mod pacing;
+mod pages;
+mod palette;
+mod queue_filter;
+mod quota;
+mod reveal;
+mod rules;
+mod scan;
+mod selection;
+mod sequence;
+mod settings;
+mod summary;
+#[cfg(test)]
+mod testing;
+mod thumbnail_cache;
+mod watermark;
+mod zoom;
+
 use std::{
+    collections::{HashMap, HashSet},
     ffi::OsString,
-    fs::DirEntry
 };
 
 use eframe::egui;
+use rand::{seq::SliceRandom, SeedableRng};
 use serde::{Serialize, Deserialize};
 
+use burst::{BurstChoice, BurstGroup};
+use decode_pool::{DecodeJob, DecodePool, DecodeResult};
+use exif_info::ExifInfo;
+use export::CopyReport;
+use journal::JournalEntry;
+use metadata::{ColorLabel, XmpFields};
+use scan::{FileSysNode, ScanOptions, ScannedFolder};
+use settings::{Bucket, Settings};
+
+const APP_TITLE: &str = "Image sifter";
+const PROGRESS_FILE: &str = "bildsak_progress.json";
+const KEPT_STRIP_LENGTH: usize = 50; // Most recently kept images shown as thumbnails while culling
+const DECISION_TRAIL_LENGTH: usize = 8;
+const PRELOAD_AHEAD: usize = 3; // Images after the current one to decode in the background
+const PROTECTED_RATING: u8 = 4; // Discarding an image rated this high needs a confirmation
+const MAX_COUNTED_PAUSE: f64 = 30.0; // Longer gaps between frames count as being away, not reviewing
+const PANORAMA_ASPECT: f32 = 3.0; // Images this many times wider than tall can be scrolled through
+const TOAST_DURATION: std::time::Duration = std::time::Duration::from_secs(2); // How long confirmations like "Copied" stay up
+const MIN_UI_SCALE: f32 = 0.75;
+const MAX_UI_SCALE: f32 = 2.5;
+const GRID_PALETTE_HEIGHT: f32 = 8.0; // Of the band of main colors along the bottom of grid thumbnails
+const FACE_ZOOM_MARGIN: f32 = 0.5; // Of the face's size shown around it on each side when zooming to it
+const FLASH_WIDTH: f32 = 8.0; // Of the band along the window edge that flashes after a decision
+const JOURNAL_COMPACT_AFTER: usize = 200; // Journal entries after which the progress file is written even without a pause
+
 fn main() -> eframe::Result {
+    env_logger::init();
+
+    // `--list FILE` starts out reviewing the images listed in FILE
+    let mut args = std::env::args_os().skip(1);
+    let list_file = args.by_ref().find(|arg| arg == "--list").and_then(|_| args.next()).map(std::path::PathBuf::from);
+
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default().with_inner_size([800.0, 600.0]),
+        viewport: Settings::load().startup_viewport(),
         ..Default::default()
     };
 
     eframe::run_native(
-        "Image sifter",
+        APP_TITLE,
         options,
         Box::new(|cc| {
             egui_extras::install_image_loaders(&cc.egui_ctx);
 
-            Ok(Box::<MyApp>::default())
+            Ok(Box::new(MyApp::new(cc, list_file)))
         }),
     )
 }
 
-#[derive(Default)]
-struct FileSysNode {
-    images: Vec<OsString>,
-    children: Vec<Box<FileSysNode>>,
-    name: OsString,
-}
-
 #[derive(Default)]
 struct MyApp {
     working_path: Option<OsString>,
+    extra_roots: Vec<std::path::PathBuf>, // Further folders reviewed together with working_path
+    status_message: Option<String>,
+    copy_report: Option<Result<CopyReport, String>>, // Outcome of the last copy, shown until the next one
+    copy_job: Option<(export::CopyJob, bool)>, // Copy under way, and whether kept JPEGs are straightened after it
+    orientation_pass: Option<orientation::OrientationPass>, // Straightening the kept JPEGs of the last copy
+    contact_sheet_job: Option<contact_sheet::ContactSheetJob>, // Rendering or rendered contact sheet, until the next session
+    selection_report: Option<selection::SelectionReport>, // Outcome of the last selection file import
+    raw_only_folders: Vec<(std::path::PathBuf, usize)>, // Folders of the last scan with RAW files but no JPEGs, and how many
+    provisional_order_folders: Vec<std::path::PathBuf>, // Folders a fast scan queued by name instead of the configured order
     images: Option<Box<FileSysNode>>,
     image_paths: Vec<std::path::PathBuf>, // All images in traversal order
     kept_images: Vec<std::path::PathBuf>,
-    discarded_count: usize,
-    is_loading: bool,
+    discarded_images: Vec<std::path::PathBuf>,
+    skipped_count: usize,
+    shuffle_seed: Option<u64>, // Seed the queue was last shuffled with, if it was
+    time_spent: f64, // Seconds spent reviewing over all sessions in this folder
+    stretch: pacing::Stretch, // Decisions since the last break, for suggesting the next one
+    unsaved_since: Option<std::time::Instant>, // Last change not yet in the progress file
+    journal_length: usize, // Entries appended since the progress file was last written
+    replaying_journal: bool,
+    pending_recovery: Option<Vec<JournalEntry>>, // Actions a crash kept from being saved, until replayed or dropped
+    pending_reset: bool, // Reset was clicked and waits for a confirmation
+    pending_export: Option<PendingExport>, // A copy into folders that hold other files, until one is chosen
+    folder_bucket_offer: Option<Vec<Bucket>>, // Buckets for subfolders that look like an earlier manual sort, until taken up or dismissed
+    scan: Option<RunningScan>, // Background scan of the session's roots, until it has walked every folder
+    list_file: Option<std::path::PathBuf>, // Text file the images under review were listed in, instead of scanned
     image_counter: u64, // Counter to make unique image URIs
     texture: Option<egui::TextureHandle>, // Holds the current image texture
+    current_image: Option<std::sync::Arc<egui::ColorImage>>, // Pixels behind `texture`
+    show_enhanced: bool, // Show an auto-enhanced copy next to the current image
+    enhanced_texture: Option<egui::TextureHandle>, // Built from `current_image` when first shown
+    reference: Option<std::path::PathBuf>, // Pinned image to compare every following image against
+    reference_texture: Option<egui::TextureHandle>,
+    show_reference: bool,
+    guide: guides::Guide, // Composition guide drawn over the current image
+    zoom: zoom::Zoom,
+    show_grid: bool, // Thumbnails of the whole queue instead of one image at a time
+    show_quota: bool,
+    quota_images: Option<Vec<std::path::PathBuf>>, // Queued images being scored for the quota, until it is applied
+    quota_decided: Vec<std::path::PathBuf>, // Decided by the last quota, in queue order, for taking it back
+    bulk_scope: BulkScope,
+    bulk_undo: Vec<(std::path::PathBuf, XmpFields, HashSet<String>)>, // Metadata before the last bulk edit, for taking it back
+    show_blur_pass: bool,
+    blur_images: Option<Vec<std::path::PathBuf>>, // Queued images scored for the blur pass, until it is applied or dropped
+    blur_decided: Vec<std::path::PathBuf>, // Discarded by the last blur pass, in queue order, for taking it back
+    show_faces: bool,
+    face_search: Option<faces::FaceSearch>,
+    face_boxes: HashMap<std::path::PathBuf, faces::FaceBoxes>, // Faces found for zooming to, per image
+    face_detection: Option<faces::FaceDetection>, // Finding the faces of the current image to zoom to the largest
+    faces_decided: Vec<std::path::PathBuf>, // Decided by the last face search, in queue order, for taking it back
+    grid_selection: grid::GridSelection,
+    queue_filter: queue_filter::QueueFilter,
+    grid_show_decided: bool, // Also list decided images in the grid, so they can be sorted again
+    grid_all_images: (usize, Vec<std::path::PathBuf>), // Image count of the tree and all its images, rebuilt when the count changes
+    labels: HashMap<std::path::PathBuf, ColorLabel>,
+    ratings: HashMap<std::path::PathBuf, u8>, // 1 to 5 stars
+    tags: HashMap<std::path::PathBuf, HashSet<String>>,
+    tag_names: Vec<String>, // Every tag of the session in the order it was created, used or not
+    new_tag: String, // Text field of the tag panel
+    show_tags: bool,
+    sequence_gaps: Option<Vec<(std::path::PathBuf, Vec<sequence::SequenceGap>)>>, // Result of the last gap check, shown until closed
+    decisions: HashMap<std::path::PathBuf, Decision>, // Final keep/discard per discovered image
+    bucket_assignments: HashMap<std::path::PathBuf, String>, // Bucket name for images sorted with a bucket key or button
+    exif_cache: HashMap<std::path::PathBuf, Option<ExifInfo>>,
+    settings: Settings,
+    show_settings: bool,
+    show_about: bool,
+    font_status: String, // Which fallback font is in use, or why the configured one is not
+    window_title: String, // Last title sent to the OS window
+    toast: Option<(String, std::time::Instant)>, // Short confirmation shown over the window, and since when
+    flash: Option<(bool, std::time::Instant)>, // Whether the last decision kept, and when it was made
+    window: Option<(egui::Vec2, bool)>, // Size of the window while not maximized, and whether it is maximized now
+    trail: Vec<std::path::PathBuf>, // Images decided most recently, oldest first
+    last_decided: Option<std::path::PathBuf>, // Image of the last decision made from the viewer, which Shift+bucket key sorts again
+    pending_discard: Option<(std::path::PathBuf, usize)>, // Protected image and the discarding bucket, waiting for confirmation
+    review_kept: Option<std::path::PathBuf>, // Last kept image from the folder of the pending discard
+    review_texture: Option<egui::TextureHandle>,
+    decode_pool: Option<DecodePool>,
+    preloaded: HashMap<std::path::PathBuf, egui::ColorImage>, // Decoded images waiting to become textures
+    pending_decodes: HashSet<std::path::PathBuf>, // Submitted to the pool but not back yet
+    decode_errors: HashMap<std::path::PathBuf, String>, // Images that could not be decoded, and why
+    moved: HashMap<std::path::PathBuf, Vec<(std::path::PathBuf, std::path::PathBuf)>>, // Decided images moved out as they were decided, with their sidecars: from, to
+    move_queue: Option<export::MoveQueue>, // Moving the files in `moved` in the background, once there is any
+    moving_back: HashMap<std::path::PathBuf, AfterMoveBack>, // Moved images on their way back, and what to do with them then
+    thumbnails: HashMap<std::path::PathBuf, egui::TextureHandle>,
+    pending_thumbnails: HashSet<std::path::PathBuf>,
+    palettes: HashMap<std::path::PathBuf, Vec<palette::Swatch>>, // From the thumbnail or the full image, whichever was decoded first
+    sharpness: HashMap<std::path::PathBuf, f64>, // Variance of the Laplacian, higher is sharper
+    pending_scores: HashSet<std::path::PathBuf>,
+    burst: Option<BurstGroup>, // Burst starting at the current image, offered as one decision
+    burst_checked: Option<std::path::PathBuf>, // Image the burst detection last ran for
+    dismissed_bursts: HashSet<std::path::PathBuf>, // Frames the user chose to review one by one
+}
+
+fn is_panorama(size: [usize; 2]) -> bool {
+    size[0] as f32 >= size[1] as f32 * PANORAMA_ASPECT
+}
+
+/// Draws the capture settings into the bottom left corner of the visible part of `image_rect`.
+fn paint_capture_badge(ui: &egui::Ui, image_rect: egui::Rect, text: Option<&str>) {
+    let Some(text) = text else {
+        return;
+    };
+    let visible = image_rect.intersect(ui.clip_rect());
+    let galley = ui.painter().layout_no_wrap(text.to_string(), egui::FontId::proportional(13.0), egui::Color32::WHITE);
+    let padding = egui::vec2(6.0, 3.0);
+    let badge = egui::Rect::from_min_size(
+        visible.left_bottom() + egui::vec2(8.0, -8.0 - galley.size().y - 2.0 * padding.y),
+        galley.size() + 2.0 * padding,
+    );
+    ui.painter().rect_filled(badge, 4.0, egui::Color32::from_black_alpha(160));
+    ui.painter().galley(badge.min + padding, galley, egui::Color32::WHITE);
+}
+
+fn with_thousands_separators(number: usize) -> String {
+    let digits = number.to_string();
+    let mut grouped = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+enum TagAction {
+    Keep,
+    Discard,
+    Export,
+}
+
+/// Metadata given to every image of a folder or burst at once.
+#[derive(Clone)]
+enum BulkEdit {
+    Rate(Option<u8>),
+    Label(Option<ColorLabel>),
+    AddTag(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+enum BulkScope {
+    #[default]
+    Folder,
+    Burst,
+}
+
+/// Tags are free text; keep them from escaping the export folder.
+fn tag_folder_name(tag: &str) -> String {
+    match tag {
+        "." | ".." => "_".to_string(),
+        _ => tag.replace(['/', '\\', ':'], "_"),
+    }
+}
+
+/// What is left to do with a moved image once the move queue has put it back where it was
+/// found.
+#[derive(Clone, Copy)]
+enum AfterMoveBack {
+    Undecide,
+    Redecide(bool), // To be kept or not
+    Resort(usize), // Into the bucket with this index
+    Reset,
+}
+
+/// A copy held back because its output folders already hold files it would not write.
+struct PendingExport {
+    images: Vec<std::path::PathBuf>,
+    conflicts: Vec<(export::OutputConflict, String)>, // With the folder to use instead, if picked
+}
+
+/// A scan of the session's roots whose folders are still arriving.
+struct RunningScan {
+    receiver: std::sync::mpsc::Receiver<ScannedFolder>,
+    root: std::path::PathBuf,
+    later_roots: Vec<std::path::PathBuf>, // Walked one after the other once `root` is done
+    kind: ScanKind,
+    queued: HashSet<std::path::PathBuf>, // Queued before the scan got to them, e.g. restored from the progress file
+    queue_new_images: bool, // Off for old progress files, which cannot tell discarded images from new ones
+    found: usize, // New images queued or auto-kept so far
+    reverse_when_done: bool, // A new session with the queue reversed by default
+    shuffle_when_done: bool, // A new session with the queue shuffled by default
+    held_back: Vec<std::path::PathBuf>, // New images of a shuffled session, queued once they are all found
+    auto_kept: usize,
+}
+
+impl RunningScan {
+    /// Starts walking `roots` in the background, queueing new images as they turn up.
+    fn start(mut roots: Vec<std::path::PathBuf>, kind: ScanKind, options: ScanOptions, ctx: &egui::Context) -> Self {
+        let root = roots.remove(0);
+        Self {
+            receiver: scan::spawn_scan(root.clone(), options, ctx.clone()),
+            root,
+            later_roots: roots,
+            kind,
+            queued: HashSet::new(),
+            queue_new_images: true,
+            found: 0,
+            reverse_when_done: false,
+            shuffle_when_done: false,
+            held_back: Vec::new(),
+            auto_kept: 0,
+        }
+    }
+}
+
+/// Why a scan runs, which decides what happens once it is done.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ScanKind {
+    Session, // The roots of a session being opened
+    ListRoots, // Further folders of a list session, walked for the tree alone
+    Rescan, // All roots again, for images that appeared since
+    AddedRoot, // A folder added to the session
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+enum Decision {
+    Keep,
+    Discard,
 }
 
 #[derive(Serialize, Deserialize, Default)]
 struct ProgressData {
     kept_images: Vec<String>,
-    discarded_count: usize,
+    discarded_count: usize, // Still written for older versions, which only know the count
     image_counter: u64,
     remaining_queue: Vec<String>,
+    #[serde(default)]
+    labels: HashMap<String, ColorLabel>,
+    #[serde(default)]
+    ratings: HashMap<String, u8>,
+    #[serde(default)]
+    decisions: HashMap<String, Decision>,
+    #[serde(default)]
+    extra_roots: Vec<String>,
+    #[serde(default)]
+    tags: HashMap<String, HashSet<String>>,
+    #[serde(default)]
+    tag_names: Vec<String>,
+    #[serde(default)]
+    buckets: HashMap<String, String>,
+    #[serde(default)]
+    skipped_count: usize,
+    #[serde(default)]
+    shuffle_seed: Option<u64>,
+    #[serde(default)]
+    time_spent: f64,
+    #[serde(default)]
+    discarded_images: Vec<String>,
+    #[serde(default)]
+    moved: HashMap<String, Vec<(String, String)>>,
 }
 
-fn insert_children(parent: &mut FileSysNode, dir_entry: &DirEntry) -> Result<(), Box<dyn std::error::Error>> {
-    if let Ok(entries) = dir_entry.path().read_dir() {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                if let Ok(metadata) = entry.metadata() {
-                    if metadata.is_dir() {
-                        // Create a new child node for the directory
-                        let mut child_node = FileSysNode {
-                            name: entry.file_name(),
-                            ..FileSysNode::default()
-                        };
-                        
-                        // Recursively populate the child node
-                        insert_children(&mut child_node, &entry)?;
-                        
-                        // Add the child to the parent
-                        parent.children.push(Box::new(child_node));
-                    } else {
-                        // Check if it's an image file before adding
-                        if let Some(extension) = entry.path().extension() {
-                            if let Some(ext_str) = extension.to_str() {
-                                let ext_lower = ext_str.to_lowercase();
-                                if matches!(ext_lower.as_str(), "jpg" | "jpeg") {
-                                    parent.images.push(entry.file_name());
-                                }
+impl MyApp {
+    fn new(cc: &eframe::CreationContext<'_>, list_file: Option<std::path::PathBuf>) -> Self {
+        let settings = Settings::load();
+        let mut app = Self {
+            decode_pool: Some(DecodePool::new(
+                settings.decode_threads,
+                settings.jpeg_backend,
+                settings.decode_timeout(),
+                settings.thumbnail_cache(),
+                &cc.egui_ctx,
+            )),
+            settings,
+            ..Self::default()
+        };
+        app.install_fonts(&cc.egui_ctx);
+        app.apply_appearance(&cc.egui_ctx);
+        if let Some(cache) = app.settings.thumbnail_cache() {
+            // Trimmed at startup, off the UI thread as it lists the whole cache, and then as thumbnails are added
+            let _ = std::thread::Builder::new().name("thumbnail-cache".to_string()).spawn(move || cache.evict());
+        }
+        if let Some(file) = list_file {
+            app.open_image_list(file, &cc.egui_ctx);
+        }
+        app
+    }
+
+    fn install_fonts(&mut self, ctx: &egui::Context) {
+        self.font_status = match fonts::install_fallback_font(ctx, &self.settings.fallback_font) {
+            Ok(Some(path)) => format!("Symbols from {}", path.display()),
+            Ok(None) => "Symbols from the bundled font".to_string(),
+            Err(e) => {
+                log::warn!("{}", e);
+                format!("❌ {}", e)
+            },
+        };
+    }
+
+    /// Sets the theme and interface size from the settings.
+    fn apply_appearance(&self, ctx: &egui::Context) {
+        ctx.set_theme(self.settings.theme.preference());
+        ctx.set_zoom_factor(self.settings.ui_scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE));
+    }
+
+    /// Replaces the decode pool (leaving the old workers to stop on their own) and forgets
+    /// everything it was working on.
+    fn restart_decode_pool(&mut self, ctx: &egui::Context) {
+        if let Some(mut pool) = self.decode_pool.take() {
+            pool.shutdown();
+        }
+        self.preloaded.clear();
+        self.pending_decodes.clear();
+        self.pending_thumbnails.clear();
+        self.pending_scores.clear();
+        self.decode_pool = Some(DecodePool::new(
+            self.settings.decode_threads,
+            self.settings.jpeg_backend,
+            self.settings.decode_timeout(),
+            self.settings.thumbnail_cache(),
+            ctx,
+        ));
+    }
+
+    /// Collects finished decodes and keeps the current image plus the next few queued in the pool.
+    fn update_preload(&mut self, ctx: &egui::Context) {
+        let Some(pool) = &mut self.decode_pool else {
+            return;
+        };
+        pool.abandon_stuck_workers();
+        let mut window: HashSet<&std::path::PathBuf> = self.image_paths.iter().take(PRELOAD_AHEAD + 1).collect();
+        let reference_needed = self.reference.as_ref().filter(|_| self.reference_texture.is_none());
+        let review_needed = self.review_kept.as_ref().filter(|_| self.review_texture.is_none());
+        let also_needed: Vec<&std::path::PathBuf> = reference_needed.into_iter().chain(review_needed).collect();
+        window.extend(also_needed.iter().copied());
+
+        for result in pool.finished() {
+            match result {
+                DecodeResult::Display { path, image } => {
+                    self.pending_decodes.remove(&path);
+                    match image {
+                        Ok(image) if window.contains(&path) => {
+                            self.preloaded.insert(path, image);
+                        },
+                        Ok(_) => {},
+                        Err(e) => {
+                            log::warn!("Could not decode {}: {}", path.display(), e);
+                            self.decode_errors.insert(path, e.to_string());
+                        },
+                    }
+                },
+                DecodeResult::Sharpness { path, score } => {
+                    self.pending_scores.remove(&path);
+                    self.sharpness.insert(path, score);
+                },
+                DecodeResult::Thumbnail { path, image } => {
+                    self.pending_thumbnails.remove(&path);
+                    match image {
+                        Ok(image) => {
+                            if !self.palettes.contains_key(&path) {
+                                self.palettes.insert(path.clone(), palette::dominant_colors(&image, palette::PALETTE_SIZE));
                             }
-                        }
+                            let uri = format!("bytes://thumbnail/{}", path.display());
+                            let texture = ctx.load_texture(uri, image, self.settings.texture_filter.texture_options());
+                            self.thumbnails.insert(path, texture);
+                        },
+                        Err(e) => {
+                            self.decode_errors.insert(path, e.to_string());
+                        },
+                    }
+                },
+            }
+        }
+        self.preloaded.retain(|path, _| window.contains(path));
+
+        for path in self.image_paths.iter().take(PRELOAD_AHEAD + 1).chain(also_needed) {
+            let is_current_texture = self.texture.is_some() && Some(path) == self.image_paths.first();
+            if !is_current_texture
+                && !self.preloaded.contains_key(path)
+                && !self.decode_errors.contains_key(path)
+                && self.pending_decodes.insert(path.clone())
+            {
+                pool.submit(DecodeJob::Display(path.clone()));
+            }
+        }
+    }
+
+    /// Pins the current image as the reference, or unpins it if it already is.
+    fn toggle_reference(&mut self) {
+        let current = self.image_paths.first().cloned();
+        if self.reference.is_some() && self.reference == current {
+            self.reference = None;
+            self.reference_texture = None;
+            self.show_reference = false;
+        } else if current.is_some() {
+            self.reference = current;
+            self.reference_texture = self.texture.clone(); // Decoded again by the pool if not shown yet
+            self.show_reference = true;
+        }
+    }
+
+    /// Finds the burst the current image starts, if any, and scores its frames off-thread.
+    /// Once every frame has a score the sharpest one is proposed as the keeper.
+    fn update_burst(&mut self) {
+        let Some(current) = self.image_paths.first().cloned() else {
+            self.burst = None;
+            return;
+        };
+        if !self.settings.detect_bursts {
+            self.burst = None;
+            return;
+        }
+
+        if self.burst_checked.as_ref() != Some(&current) {
+            self.burst_checked = Some(current.clone());
+            self.burst = None;
+            if self.dismissed_bursts.contains(&current) {
+                return;
+            }
+            let lookahead: Vec<std::path::PathBuf> = self.image_paths.iter().take(burst::MAX_BURST_FRAMES).cloned().collect();
+            let max_gap = self.settings.burst_max_gap_ms as f64 / 1000.0;
+            let frames = burst::find_burst(&lookahead, max_gap, |path| self.exif_info(path)?.captured_at);
+            if frames.len() < 2 {
+                return;
+            }
+            if let Some(pool) = &self.decode_pool {
+                for frame in &frames {
+                    if !self.sharpness.contains_key(frame) && self.pending_scores.insert(frame.clone()) {
+                        pool.submit(DecodeJob::Sharpness(frame.clone()));
                     }
                 }
             }
+            self.burst = Some(BurstGroup::new(frames));
+        }
+
+        if let Some(burst) = &mut self.burst
+            && !burst.preselected
+            && let Some(scores) = burst.frames.iter().map(|frame| self.sharpness.get(frame).copied()).collect::<Option<Vec<f64>>>()
+        {
+            burst.preselect_sharpest(&scores);
         }
     }
-    Ok(())
-}
 
-impl FileSysNode {
-    fn count_images(&self) -> usize {
-        let mut count = self.images.len();
-        for child in &self.children {
-            count += child.count_images();
-        }
-        count
-    }
-    
-    
-    fn get_images_depth_first_current_priority(&self, base_path: &std::path::Path) -> Vec<std::path::PathBuf> {
-        let mut all_images = Vec::new();
-        
-        // First, add all images from the current directory
-        for image in &self.images {
-            let image_path = base_path.join(image);
-            all_images.push(image_path);
-        }
-        
-        // Then, recursively add images from subdirectories (depth-first)
-        for child in &self.children {
-            let child_path = base_path.join(&child.name);
-            all_images.extend(child.get_images_depth_first_current_priority(&child_path));
-        }
-        
-        all_images
+    /// Decides every frame of the burst as proposed (or as the user flipped them).
+    fn apply_burst(&mut self) {
+        let Some(burst) = self.burst.take() else {
+            return;
+        };
+        for (frame, keep) in burst.frames.iter().zip(&burst.keep) {
+            self.record_decision(frame, *keep);
+        }
+        self.mark_unsaved();
     }
-}
 
-impl MyApp {
-    fn copy_kept_images(&self) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(working_path) = &self.working_path {
-            let working_path = std::path::PathBuf::from(working_path);
-            let output_folder = working_path.join("kept_images");
-            
-            // Create the output folder if it doesn't exist
-            std::fs::create_dir_all(&output_folder)?;
-            
-            for kept_image_path in &self.kept_images {
-                // Calculate relative path from working directory
-                let relative_path = kept_image_path.strip_prefix(&working_path)?;
-                let destination_path = output_folder.join(relative_path);
-                
-                // Create parent directories if they don't exist
-                if let Some(parent) = destination_path.parent() {
-                    std::fs::create_dir_all(parent)?;
+    /// Keeps only the frame with the best sharpness score and discards the rest of the
+    /// burst in one go. Does nothing until every frame is scored.
+    fn keep_sharpest_of_burst(&mut self) {
+        let Some(burst) = &mut self.burst else {
+            return;
+        };
+        let Some(scores) = burst.frames.iter().map(|frame| self.sharpness.get(frame).copied()).collect::<Option<Vec<f64>>>() else {
+            return;
+        };
+        burst.preselect_sharpest(&scores);
+        self.apply_burst();
+    }
+
+    /// Leaves the burst's frames to be reviewed one by one.
+    fn dismiss_burst(&mut self) {
+        if let Some(burst) = self.burst.take() {
+            self.dismissed_bursts.extend(burst.frames);
+        }
+    }
+
+    fn show_burst_panel(&mut self, ui: &mut egui::Ui) -> Option<BurstChoice> {
+        let burst = self.burst.as_mut()?;
+        let mut choice = None;
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!("🎞 Burst of {} frames", burst.frames.len()));
+                if !burst.preselected {
+                    ui.spinner();
+                    let scored = burst.frames.iter().filter(|frame| self.sharpness.contains_key(*frame)).count();
+                    ui.label(format!("Scoring sharpness {}/{}", scored, burst.frames.len()));
                 }
-                
-                // Copy the JPEG file
-                std::fs::copy(kept_image_path, &destination_path)?;
-                
-                // Check for corresponding CR3 (Canon RAW) file and copy it too
-                if let Some(stem) = kept_image_path.file_stem() {
-                    let cr3_path = kept_image_path.with_file_name(format!("{}.CR3", stem.to_string_lossy()));
-                    let cr3_path_lower = kept_image_path.with_file_name(format!("{}.cr3", stem.to_string_lossy()));
-                    
-                    // Try both uppercase and lowercase CR3 extensions
-                    for potential_cr3 in [&cr3_path, &cr3_path_lower] {
-                        if potential_cr3.exists() {
-                            let cr3_relative = potential_cr3.strip_prefix(&working_path)?;
-                            let cr3_destination = output_folder.join(cr3_relative);
-                            
-                            // Create parent directories for CR3 if needed
-                            if let Some(parent) = cr3_destination.parent() {
-                                std::fs::create_dir_all(parent)?;
-                            }
-                            
-                            // Copy the CR3 file
-                            std::fs::copy(potential_cr3, &cr3_destination)?;
-                            break; // Only copy one CR3 file if both exist
-                        }
+            });
+            let best = burst
+                .frames
+                .iter()
+                .filter_map(|frame| self.sharpness.get(frame))
+                .copied()
+                .fold(None, |best: Option<f64>, score| Some(best.map_or(score, |b| b.max(score))));
+            for (frame, keep) in burst.frames.iter().zip(burst.keep.iter_mut()) {
+                ui.horizontal(|ui| {
+                    ui.checkbox(keep, frame.file_name().unwrap_or_default().to_string_lossy());
+                    if let Some(score) = self.sharpness.get(frame) {
+                        let marker = if Some(*score) == best { " (sharpest)" } else { "" };
+                        ui.weak(format!("sharpness {:.0}{}", score, marker));
                     }
-                }
+                });
             }
+            ui.horizontal(|ui| {
+                let kept = burst.keep.iter().filter(|keep| **keep).count();
+                if ui
+                    .add_enabled(burst.preselected, egui::Button::new(format!("✔ Keep {} / discard {}", kept, burst.frames.len() - kept)))
+                    .clicked()
+                {
+                    choice = Some(BurstChoice::Apply);
+                }
+                if ui
+                    .add_enabled(burst.preselected, egui::Button::new("⚡ Keep sharpest only (B)"))
+                    .on_hover_text("Keep the sharpest frame and discard all others, whatever is checked above")
+                    .clicked()
+                {
+                    choice = Some(BurstChoice::KeepSharpest);
+                }
+                if ui.button("Review individually").clicked() {
+                    choice = Some(BurstChoice::ReviewIndividually);
+                }
+            });
+        });
+        choice
+    }
+
+    fn open_working_folder(&mut self, path: std::path::PathBuf, ctx: &egui::Context) {
+        self.start_session(&path, ctx);
+
+        // The tree and the queue fill up as the background scan reads folders
+        self.images = Some(Box::new(FileSysNode {
+            name: path.as_os_str().to_os_string(),
+            ..FileSysNode::default()
+        }));
+
+        // Load progress after setting working_path and images
+        let has_old_progress_file = self.load_progress(&path);
+        let is_new_session = self.image_paths.is_empty() && self.decisions.is_empty();
+        self.check_journal(&path);
+        let roots = std::iter::once(path).chain(self.extra_roots.iter().cloned()).collect();
+        self.scan = Some(RunningScan {
+            queued: self.image_paths.iter().cloned().collect(),
+            queue_new_images: !has_old_progress_file,
+            reverse_when_done: is_new_session && self.settings.reverse_new_sessions && !self.settings.shuffle_new_sessions,
+            shuffle_when_done: is_new_session && self.settings.shuffle_new_sessions,
+            ..RunningScan::start(roots, ScanKind::Session, ScanOptions::from_settings(&self.settings), ctx)
+        });
+    }
+
+    /// Reviews the images listed in a text file rather than those in a folder. Their deepest
+    /// common folder serves as the working folder, for progress and output; when they have
+    /// none, the list's own folder does and images outside it are copied without folders.
+    fn open_image_list(&mut self, file: std::path::PathBuf, ctx: &egui::Context) {
+        let list = match image_list::read_image_list(&file) {
+            Ok(list) if !list.images.is_empty() => list,
+            Ok(_) => {
+                self.status_message = Some(format!("❌ {} lists no existing images", file.display()));
+                return;
+            },
+            Err(e) => {
+                self.status_message = Some(format!("❌ Could not read {}: {}", file.display(), e));
+                return;
+            },
+        };
+        self.start_session(&list.base, ctx);
+        self.list_file = Some(file.clone());
+        self.images = Some(Box::new(image_list::build_tree(&list.base, &list.images)));
+        self.load_progress(&list.base);
+
+        // Earlier progress in the same folder may know images that are not on this list
+        let listed: HashSet<&std::path::PathBuf> = list.images.iter().collect();
+        self.image_paths.retain(|image| listed.contains(image));
+        let known: HashSet<&std::path::PathBuf> = self.image_paths.iter().chain(self.decisions.keys()).collect();
+        let new_images: Vec<std::path::PathBuf> = list.images.iter().filter(|image| !known.contains(image)).cloned().collect();
+        let auto_kept = self.enqueue_new_images(new_images);
+        self.check_journal(&list.base);
+        if !self.extra_roots.is_empty() {
+            self.scan = Some(RunningScan {
+                queue_new_images: false, // Only what is listed gets reviewed
+                ..RunningScan::start(self.extra_roots.clone(), ScanKind::ListRoots, ScanOptions::from_settings(&self.settings), ctx)
+            });
+        }
+
+        let mut status = format!("Reviewing {} images listed in {}", list.images.len(), file.display());
+        if !list.missing.is_empty() {
+            status.push_str(&format!(", {} listed files not found", list.missing.len()));
+        }
+        if auto_kept > 0 {
+            status.push_str(&format!(", {} auto-kept", auto_kept));
         }
-        Ok(())
+        self.status_message = Some(status);
+        self.mark_unsaved();
     }
 
-    fn save_progress(&self) {
-        if let Some(working_path) = &self.working_path {
-            let progress = ProgressData {
-                kept_images: self.kept_images.iter().map(|p| p.to_string_lossy().to_string()).collect(),
-                discarded_count: self.discarded_count,
-                image_counter: self.image_counter,
-                remaining_queue: self.image_paths.iter().map(|p| p.to_string_lossy().to_string()).collect(),
+    /// Offers to replay a journal that outlived the last session in `path`, or clears it.
+    fn check_journal(&mut self, path: &std::path::Path) {
+        let unsaved = journal::read_unsaved(path);
+        if unsaved.is_empty() {
+            journal::clear(path); // Everything in it already made it into the progress file
+        } else {
+            self.pending_recovery = Some(unsaved);
+        }
+    }
+
+    /// Saves the session so far and forgets it, to start over in `path`.
+    fn start_session(&mut self, path: &std::path::Path, ctx: &egui::Context) {
+        self.finish_moves(None);
+        if self.unsaved_since.is_some() {
+            self.save_progress(); // Still for the folder that was open so far
+        }
+        self.working_path = Some(path.as_os_str().to_os_string());
+        self.restart_decode_pool(ctx);
+        self.texture = None;
+
+        self.image_paths.clear();
+        self.kept_images.clear();
+        self.discarded_images.clear();
+        self.skipped_count = 0;
+        self.shuffle_seed = None;
+        self.time_spent = 0.0;
+        self.image_counter = 0;
+        self.labels.clear();
+        self.ratings.clear();
+        self.tags.clear();
+        self.tag_names.clear();
+        self.decisions.clear();
+        self.bucket_assignments.clear();
+        self.moved.clear();
+        self.extra_roots.clear();
+        self.exif_cache.clear();
+        self.decode_errors.clear();
+        self.thumbnails.clear();
+        self.palettes.clear();
+        self.reference = None;
+        self.reference_texture = None;
+        self.show_reference = false;
+        self.sharpness.clear();
+        self.burst = None;
+        self.burst_checked = None;
+        self.dismissed_bursts.clear();
+        self.status_message = None;
+        self.copy_report = None;
+        self.copy_job = None; // Left to finish on its own
+        self.orientation_pass = None;
+        self.contact_sheet_job = None;
+        self.selection_report = None;
+        self.raw_only_folders.clear();
+        self.provisional_order_folders.clear();
+        self.quota_images = None;
+        self.quota_decided.clear();
+        self.bulk_undo.clear();
+        self.sequence_gaps = None;
+        self.last_decided = None;
+        self.trail.clear();
+        self.blur_images = None;
+        self.blur_decided.clear();
+        self.face_search = None;
+        self.faces_decided.clear();
+        self.face_boxes.clear();
+        self.face_detection = None;
+        self.unsaved_since = None;
+        self.journal_length = 0;
+        self.pending_recovery = None;
+        self.pending_reset = false;
+        self.pending_export = None;
+        self.queue_filter.reset();
+        self.list_file = None;
+        self.scan = None;
+        self.folder_bucket_offer = self.buckets_for_existing_folders(path);
+    }
+
+    /// Hangs the folders the background scan has read since the last frame into the tree
+    /// and queues their images, unless they are already queued or decided. A shuffled session
+    /// holds them back until the scan ends, or the first folders would be reviewed first.
+    fn poll_scan(&mut self, ctx: &egui::Context) {
+        let Some(running) = &mut self.scan else {
+            return;
+        };
+        let mut new_images = Vec::new();
+        let finished = loop {
+            let folder = match running.receiver.try_recv() {
+                Ok(folder) => folder,
+                Err(std::sync::mpsc::TryRecvError::Empty) => break false,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) if running.later_roots.is_empty() => break true,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    running.root = running.later_roots.remove(0);
+                    running.receiver = scan::spawn_scan(running.root.clone(), ScanOptions::from_settings(&self.settings), ctx.clone());
+                    continue;
+                },
+            };
+            let folder_path = folder.relative_path.iter().fold(running.root.clone(), |path, name| path.join(name));
+            if folder.raw_only > 0 {
+                log::info!(
+                    "{} has {} RAW files and no JPEGs: {}",
+                    folder_path.display(),
+                    folder.raw_only,
+                    self.settings.raw_only_folders.name()
+                );
+                self.raw_only_folders.push((folder_path.clone(), folder.raw_only));
+            }
+            if folder.provisional_order && !self.provisional_order_folders.contains(&folder_path) {
+                self.provisional_order_folders.push(folder_path.clone());
+            }
+            // Ratings and labels given here win over what other programs left in the sidecars
+            for (image, fields) in &folder.sidecars {
+                let image = folder_path.join(image);
+                if let Some(rating) = fields.rating {
+                    self.ratings.entry(image.clone()).or_insert(rating);
+                }
+                if let Some(label) = fields.label {
+                    self.labels.entry(image).or_insert(label);
+                }
+            }
+            if running.queue_new_images {
+                new_images.extend(
+                    folder
+                        .images
+                        .iter()
+                        .map(|image| folder_path.join(image))
+                        .filter(|image| !running.queued.contains(image) && !self.decisions.contains_key(image)),
+                );
+            }
+            let tree = self.images.get_or_insert_with(Box::default);
+            let root_node = if self.extra_roots.is_empty() {
+                Some(tree.as_mut())
+            } else {
+                tree.children.iter_mut().find(|root| root.name == running.root.as_os_str())
             };
-            let file_path = std::path::PathBuf::from(working_path).join("bildsak_progress.json");
-            if let Ok(json) = serde_json::to_string_pretty(&progress) {
-                let _ = std::fs::write(file_path, json);
+            if let Some(root_node) = root_node {
+                root_node.insert_folder(&folder.relative_path, folder.images);
+            }
+        };
+
+        if let Some(running) = &mut self.scan
+            && running.shuffle_when_done
+        {
+            running.held_back.append(&mut new_images);
+            if finished {
+                new_images = std::mem::take(&mut running.held_back);
             }
         }
+        let found = new_images.len();
+        let auto_kept = self.enqueue_new_images(new_images);
+        if let Some(running) = &mut self.scan {
+            running.found += found;
+            running.auto_kept += auto_kept;
+        }
+
+        if finished && let Some(running) = self.scan.take() {
+            if running.kind == ScanKind::Session && !running.queue_new_images {
+                self.rebuild_decisions(); // Needs the whole tree to tell what was discarded
+            }
+            match (running.kind, running.found, running.auto_kept) {
+                (ScanKind::Session | ScanKind::ListRoots, _, 0) => {},
+                (ScanKind::Session | ScanKind::ListRoots, _, auto_kept) => {
+                    self.status_message = Some(format!("Auto-kept {} images by the auto-keep settings", auto_kept));
+                },
+                (ScanKind::Rescan, 0, _) => self.status_message = Some("No new images found".to_string()),
+                (ScanKind::Rescan, 1, 0) => self.status_message = Some("Added 1 new image".to_string()),
+                (ScanKind::Rescan, found, 0) => self.status_message = Some(format!("Added {} new images", found)),
+                (ScanKind::Rescan, found, auto_kept) => {
+                    self.status_message = Some(format!("Added {} new images, {} auto-kept", found, auto_kept));
+                },
+                (ScanKind::AddedRoot, found, 0) => {
+                    self.status_message = Some(format!("Added {} images from {}", found, running.root.display()));
+                },
+                (ScanKind::AddedRoot, found, auto_kept) => {
+                    self.status_message = Some(format!("Added {} images from {}, {} auto-kept", found, running.root.display(), auto_kept));
+                },
+            }
+            if running.reverse_when_done {
+                self.reverse_queue();
+            }
+            if running.shuffle_when_done {
+                self.shuffle_queue(self.settings.shuffle_seed);
+            }
+            self.save_progress();
+        }
     }
 
-    fn load_progress(&mut self, working_path: &std::path::Path) {
-        let file_path = working_path.join("bildsak_progress.json");
-        if let Ok(json) = std::fs::read_to_string(&file_path) {
-            if let Ok(progress) = serde_json::from_str::<ProgressData>(&json) {
-                self.kept_images = progress.kept_images.iter().map(|s| std::path::PathBuf::from(s)).collect();
-                self.discarded_count = progress.discarded_count;
-                self.image_counter = progress.image_counter;
-                self.image_paths = progress.remaining_queue.iter().map(|s| std::path::PathBuf::from(s)).collect();
+    /// Queues newly found images, except those matching an auto-keep rule or, if enabled, alone
+    /// in their folder: they are kept right away (and can be un-kept like any other). Returns
+    /// how many were auto-kept.
+    fn enqueue_new_images(&mut self, images: Vec<std::path::PathBuf>) -> usize {
+        let mut auto_kept = 0;
+        for image in images {
+            let is_auto_kept = (self.settings.auto_keep_lone_images && self.is_lone_image(&image))
+                || (!self.settings.auto_keep_rules.is_empty()
+                    && self
+                        .output_relative_path(&image)
+                        .is_some_and(|relative_path| rules::matches_any(&self.settings.auto_keep_rules, &relative_path)));
+            if is_auto_kept {
+                self.kept_images.push(image.clone());
+                self.decisions.insert(image, Decision::Keep);
+                auto_kept += 1;
+            } else if let Some(image) = self.queue_filter.admit(image) {
+                self.image_paths.push(image);
             }
         }
+        auto_kept
     }
-}
 
-impl eframe::App for MyApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                if ui.button("Select working folder").clicked() {
-                    if let Some(path) = rfd::FileDialog::new().pick_folder() {
-                        self.working_path = Some(path.as_os_str().to_os_string());
-                        
-                        // Create the root node
-                        let mut root_node = FileSysNode {
-                            name: path.as_os_str().to_os_string(),
-                            ..FileSysNode::default()
-                        };
+    /// Whether `image` is the only image in its folder of the tree, so there is nothing to
+    /// choose between.
+    fn is_lone_image(&self, image: &std::path::Path) -> bool {
+        image.parent().and_then(|folder| self.folder_node(folder)).is_some_and(|node| node.images.len() == 1)
+    }
 
-                        // Create a DirEntry-like structure for the root path
-                        if let Ok(entries) = path.read_dir() {
-                            for entry in entries {
-                                if let Ok(entry) = entry {
-                                    if let Ok(metadata) = entry.metadata() {
-                                        if metadata.is_dir() {
-                                            // Create child node and recursively populate it
-                                            let mut child_node = FileSysNode {
-                                                name: entry.file_name(),
-                                                ..FileSysNode::default()
-                                            };
-                                            
-                                            if let Err(_e) = insert_children(&mut child_node, &entry) {
-                                                // Silently ignore directory processing errors
-                                            }
-                                            
-                                            root_node.children.push(Box::new(child_node));
-                                        } else {
-                                            // Check if it's an image file before adding to root
-                                            if let Some(extension) = entry.path().extension() {
-                                                if let Some(ext_str) = extension.to_str() {
-                                                    let ext_lower = ext_str.to_lowercase();
-                                                    if matches!(ext_lower.as_str(), "jpg" | "jpeg") {
-                                                        root_node.images.push(entry.file_name());
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    } else {
-                                        // Silently ignore metadata errors
-                                    }
-                                }
-                            }
-                        }
+    /// Every image found in the folder of `image`, decided or not, in queue order.
+    fn folder_images(&self, image: &std::path::Path) -> Vec<std::path::PathBuf> {
+        let Some(folder) = image.parent() else {
+            return Vec::new();
+        };
+        self.folder_node(folder).map_or_else(Vec::new, |node| node.images.iter().map(|name| folder.join(name)).collect())
+    }
 
-                        // Populate the image paths in correct traversal order
-                        self.image_paths = root_node.get_images_depth_first_current_priority(&path);
-                        
-                        self.kept_images.clear();
-                        self.discarded_count = 0;
-                        self.image_counter = 0;
-                        self.is_loading = true;
-                        
-                        self.images = Some(Box::new(root_node));
-                        
-                        // Load progress after setting working_path and images
-                        self.load_progress(&path);
-                        self.is_loading = false;
+    /// The node of the tree for `folder`, under whichever root it is in.
+    fn folder_node(&self, folder: &std::path::Path) -> Option<&FileSysNode> {
+        let tree = self.images.as_ref()?;
+        let roots: Vec<(std::path::PathBuf, &FileSysNode)> = if self.extra_roots.is_empty() {
+            self.working_path.iter().map(|working_path| (std::path::PathBuf::from(working_path), tree.as_ref())).collect()
+        } else {
+            tree.children.iter().map(|root| (std::path::PathBuf::from(&root.name), root)).collect()
+        };
+        roots.into_iter().find_map(|(root, node)| node.find_folder(folder.strip_prefix(&root).ok()?))
+    }
+
+    /// Turns the queue of undecided images around, e.g. to start with the newest shoot.
+    /// Decided images stay decided.
+    fn reverse_queue(&mut self) {
+        if self.image_paths.len() < 2 {
+            return;
+        }
+        self.image_paths.reverse();
+        self.grid_selection.clear(); // Its shift-click anchor is an index into the queue
+        self.texture = None;
+        self.image_counter += 1;
+        self.mark_unsaved();
+    }
+
+    /// Puts the queue of undecided images in random order, so the order they were taken in
+    /// does not sway the decisions. The same `seed` gives the same order for the same queue;
+    /// without one a new seed is picked. Either way it is remembered for the summary.
+    fn shuffle_queue(&mut self, seed: Option<u64>) {
+        if self.image_paths.len() < 2 {
+            return;
+        }
+        let seed = seed.unwrap_or_else(rand::random);
+        self.image_paths.shuffle(&mut rand::rngs::StdRng::seed_from_u64(seed));
+        self.shuffle_seed = Some(seed);
+        self.grid_selection.clear(); // Its shift-click anchor is an index into the queue
+        self.texture = None;
+        self.image_counter += 1;
+        self.mark_unsaved();
+    }
+
+    /// Adds another folder to the session; its images join the end of the queue as the
+    /// background scan finds them.
+    fn add_root_folder(&mut self, path: std::path::PathBuf, ctx: &egui::Context) {
+        let Some(working_path) = &self.working_path else {
+            return;
+        };
+        let working_path = std::path::PathBuf::from(working_path);
+        let overlapping = std::iter::once(&working_path)
+            .chain(&self.extra_roots)
+            .find(|root| path.starts_with(root) || root.starts_with(&path));
+        if let Some(root) = overlapping {
+            self.status_message = Some(format!("{} overlaps {}, which is already being reviewed", path.display(), root.display()));
+            return;
+        }
+
+        self.attach_root(&path);
+        self.scan = Some(RunningScan::start(vec![path], ScanKind::AddedRoot, ScanOptions::from_settings(&self.settings), ctx));
+        self.mark_unsaved();
+    }
+
+    /// Keeps the queued images listed in a selection file from another tool.
+    fn import_selection(&mut self, file: std::path::PathBuf) {
+        let entries = match selection::read_selection_file(&file) {
+            Ok(entries) => entries,
+            Err(e) => {
+                self.status_message = Some(format!("❌ Could not read {}: {}", file.display(), e));
+                return;
+            },
+        };
+        let (matched, mut report) = selection::match_selection(&entries, &self.image_paths, |image| self.output_relative_path(image));
+        for image in &matched {
+            self.record_decision(image, true);
+        }
+        self.mark_unsaved();
+        report.source = file;
+        self.selection_report = Some(report);
+    }
+
+    fn show_selection_report(&mut self, ui: &mut egui::Ui) {
+        let Some(report) = &self.selection_report else {
+            return;
+        };
+        let mut dismissed = false;
+        ui.horizontal(|ui| {
+            let unmatched = report.not_found.len() + report.ambiguous.len();
+            ui.label(format!(
+                "📋 Kept {} images listed in {}{}",
+                report.kept,
+                report.source.file_name().unwrap_or_default().to_string_lossy(),
+                if unmatched > 0 { format!(", {} entries not matched", unmatched) } else { String::new() }
+            ));
+            dismissed = ui.small_button("✖").clicked();
+        });
+        for (heading, entries) in [("Not found in the queue", &report.not_found), ("Ambiguous names", &report.ambiguous)] {
+            if !entries.is_empty() {
+                egui::CollapsingHeader::new(format!("{} ({})", heading, entries.len())).show(ui, |ui| {
+                    for entry in entries {
+                        ui.monospace(entry);
+                    }
+                });
+            }
+        }
+        if dismissed {
+            self.selection_report = None;
+        }
+    }
+
+    /// Which folders of the last scan only had RAW files, and what was done with them. Folders
+    /// skipped silently are only logged.
+    fn show_raw_only_report(&mut self, ui: &mut egui::Ui) {
+        let mode = self.settings.raw_only_folders;
+        if self.raw_only_folders.is_empty() || mode == settings::RawOnlyFolders::Skip {
+            return;
+        }
+        let files: usize = self.raw_only_folders.iter().map(|(_, count)| count).sum();
+        let mut dismissed = false;
+        ui.horizontal(|ui| {
+            ui.label(match mode {
+                settings::RawOnlyFolders::Preview => {
+                    format!("📷 Reviewing {} RAW files from {} folders without JPEGs by their previews", files, self.raw_only_folders.len())
+                },
+                _ => format!("⚠ Left out {} RAW files from {} folders without JPEGs", files, self.raw_only_folders.len()),
+            });
+            dismissed = ui.small_button("✖").clicked();
+        });
+        egui::CollapsingHeader::new(format!("RAW-only folders ({})", self.raw_only_folders.len())).show(ui, |ui| {
+            for (folder, count) in &self.raw_only_folders {
+                ui.monospace(format!("{} ({})", folder.display(), count));
+            }
+        });
+        if dismissed {
+            self.raw_only_folders.clear();
+        }
+    }
+
+    /// Which folders the fast scan queued by name because sorting them by date or size would
+    /// have meant reading the metadata of every file.
+    fn show_provisional_order(&mut self, ui: &mut egui::Ui) {
+        if self.provisional_order_folders.is_empty() {
+            return;
+        }
+        let mut dismissed = false;
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "🕓 {} folders are in name order for now: metadata not yet loaded, as the fast scan skips it",
+                self.provisional_order_folders.len()
+            ))
+            .on_hover_text("Turn off Fast scan and scan again to review them by the configured order.");
+            dismissed = ui.small_button("✖").clicked();
+        });
+        if dismissed {
+            self.provisional_order_folders.clear();
+        }
+    }
+
+    /// Walks all roots again in the background and queues images that appeared since the
+    /// last scan. Anything already queued or decided is left alone, so progress survives.
+    fn rescan_for_new_files(&mut self, ctx: &egui::Context) {
+        let Some(working_path) = self.working_path.as_ref().map(std::path::PathBuf::from) else {
+            return;
+        };
+        self.images = Some(Box::new(FileSysNode {
+            name: working_path.as_os_str().to_os_string(),
+            ..FileSysNode::default()
+        }));
+        for root in std::mem::take(&mut self.extra_roots) {
+            if root.is_dir() {
+                self.attach_root(&root);
+            }
+        }
+        let roots = std::iter::once(working_path).chain(self.extra_roots.iter().cloned()).collect();
+        self.scan = Some(RunningScan {
+            queued: self.image_paths.iter().chain(self.queue_filter.hidden()).cloned().collect(),
+            ..RunningScan::start(roots, ScanKind::Rescan, ScanOptions::from_settings(&self.settings), ctx)
+        });
+    }
+
+    /// Hangs an empty node for `path` into the tree next to the existing roots, for a scan to
+    /// fill. With more than one root the tree gets a nameless synthetic root whose children
+    /// are named by their absolute paths, so joining onto any base path still resolves.
+    fn attach_root(&mut self, path: &std::path::Path) {
+        let node = FileSysNode {
+            name: path.as_os_str().to_os_string(),
+            ..FileSysNode::default()
+        };
+        let tree = self.images.get_or_insert_with(Box::default);
+        if self.extra_roots.is_empty() {
+            let primary = std::mem::take(tree.as_mut());
+            tree.children.push(primary);
+        }
+        tree.children.push(node);
+        self.extra_roots.push(path.to_path_buf());
+    }
+
+    /// Every root of the session with the output subfolder its files go to when several
+    /// roots are open. Roots sharing a folder name are told apart by a counter.
+    fn root_output_names(&self) -> Vec<(std::path::PathBuf, String)> {
+        let mut names: Vec<(std::path::PathBuf, String)> = Vec::new();
+        let roots = self.working_path.iter().map(std::path::PathBuf::from).chain(self.extra_roots.iter().cloned());
+        for root in roots {
+            let base = root.file_name().map_or_else(|| "root".to_string(), |name| name.to_string_lossy().to_string());
+            let mut name = base.clone();
+            let mut counter = 2;
+            while names.iter().any(|(_, taken)| *taken == name) {
+                name = format!("{} ({})", base, counter);
+                counter += 1;
+            }
+            names.push((root, name));
+        }
+        names
+    }
+
+    /// Where a file from one of the roots goes inside the output folder. A single root keeps
+    /// its layout as-is; with several, each root's layout is kept under its own subfolder.
+    fn output_relative_path(&self, file: &std::path::Path) -> Option<std::path::PathBuf> {
+        if self.extra_roots.is_empty() {
+            let working_path = std::path::Path::new(self.working_path.as_ref()?);
+            return match file.strip_prefix(working_path) {
+                Ok(relative) => Some(relative.to_path_buf()),
+                // Listed images with no folder in common with the rest go straight into the output folder
+                Err(_) if self.list_file.is_some() => file.file_name().map(std::path::PathBuf::from),
+                Err(_) => None,
+            };
+        }
+        self.root_output_names()
+            .into_iter()
+            .find_map(|(root, name)| file.strip_prefix(&root).ok().map(|relative| std::path::Path::new(&name).join(relative)))
+    }
+
+    /// EXIF for an image, read from disk the first time it is asked for.
+    fn exif_info(&mut self, path: &std::path::Path) -> Option<&ExifInfo> {
+        self.exif_cache
+            .entry(path.to_path_buf())
+            .or_insert_with(|| ExifInfo::read(path))
+            .as_ref()
+    }
+
+    /// Follows the window's size, to open it the same way next time.
+    fn track_window(&mut self, ctx: &egui::Context) {
+        let (inner_rect, maximized) = ctx.input(|input| (input.viewport().inner_rect, input.viewport().maximized.unwrap_or(false)));
+        let Some(inner_rect) = inner_rect else {
+            return;
+        };
+        // The maximized size says nothing about the size to go back to
+        let size = match self.window {
+            Some((size, _)) if maximized => size,
+            _ => inner_rect.size(),
+        };
+        self.window = Some((size, maximized));
+    }
+
+    fn show_settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_settings;
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let running_threads = self.decode_pool.as_ref().map(|pool| pool.thread_count());
+                let (decoding_changed, cleared) = self.settings.decoding_ui(ui, running_threads);
+                if decoding_changed {
+                    self.settings.save();
+                    self.restart_decode_pool(ctx);
+                }
+                if let Some(freed) = cleared {
+                    self.status_message = Some(format!("Cleared {:.1} MB of cached thumbnails", freed as f64 / (1024.0 * 1024.0)));
+                }
+                if self.settings.scanning_ui(ui, self.shuffle_seed) {
+                    self.settings.save();
+                }
+                let texture_filter = self.settings.texture_filter;
+                if self.settings.reviewing_ui(ui) {
+                    self.settings.save();
+                    if self.settings.texture_filter != texture_filter {
+                        self.texture = None; // Rebuilt with the new filter on the next frame
+                        self.reference_texture = None;
                     }
                 }
-                if ui.button("📁 Copy Kept Images").clicked() {
-                    match self.copy_kept_images() {
-                        Ok(()) => {
-                            if let Some(working_path) = &self.working_path {
-                                let output_folder = std::path::PathBuf::from(working_path).join("kept_images");
-                                ui.label(format!("✅ {} images copied to: {}", self.kept_images.len(), output_folder.display()));
-                            }
-                        },
-                        Err(e) => {
-                            ui.label(format!("❌ Error copying images: {}", e));
-                        }
-                    }
+
+                ui.separator();
+                ui.heading("Window");
+                if self.settings.window_ui(ui, self.window.map(|(size, _)| size)) {
+                    self.settings.save();
+                }
+
+                ui.separator();
+                ui.heading("Appearance");
+                if self.settings.appearance_ui(ui) {
+                    self.settings.save();
+                    self.apply_appearance(ctx);
+                }
+
+                ui.separator();
+                ui.heading("Fonts");
+                if self.settings.fonts_ui(ui) {
+                    self.settings.save();
+                    self.install_fonts(ctx);
+                }
+                ui.weak(&self.font_status);
+
+                ui.separator();
+                ui.heading("Feedback");
+                if self.settings.feedback_ui(ui) {
+                    self.settings.save();
+                }
+
+                ui.separator();
+                ui.heading("Breaks");
+                if self.settings.breaks_ui(ui) {
+                    self.settings.save();
+                }
+
+                ui.separator();
+                ui.heading("Bursts");
+                if self.settings.bursts_ui(ui) {
+                    self.settings.save();
+                    self.burst_checked = None; // Look for a burst again with the new rules
+                }
+
+                ui.separator();
+                ui.heading("Buckets");
+                ui.label("Shift and a bucket's key moves the image decided last into that bucket.");
+                if settings::buckets_ui(&mut self.settings.buckets, ui) {
+                    self.settings.save();
+                }
+
+                ui.separator();
+                ui.heading("Export");
+                if self.settings.export_ui(ui) {
+                    self.settings.save();
+                }
+
+                ui.separator();
+                ui.heading("Auto-keep");
+                if self.settings.auto_keep_ui(ui) {
+                    self.settings.save();
+                }
+
+                ui.separator();
+                ui.heading("Saving");
+                if self.settings.saving_ui(ui) {
+                    self.settings.save();
+                }
+
+                ui.separator();
+                ui.heading("Copying");
+                if self.settings.copying_ui(ui) {
+                    self.settings.save();
+                }
+            });
+        self.show_settings = open;
+    }
+
+    /// Looks for gaps in the numbered file names of every folder and logs them, so missing
+    /// frames are noticed before the images are copied out.
+    fn check_sequence_gaps(&mut self) {
+        let (Some(tree), Some(working_path)) = (&self.images, &self.working_path) else {
+            return;
+        };
+        let folders = sequence::find_folder_gaps(tree, std::path::Path::new(working_path));
+        let mut missing = 0;
+        for (folder, gaps) in &folders {
+            for gap in gaps {
+                log::warn!("Missing from {}: {}", folder.display(), gap);
+                missing += gap.missing();
+            }
+        }
+        self.status_message = Some(match folders.len() {
+            0 => "No gaps in the file name sequences".to_string(),
+            count => format!("⚠ {} frames may be missing from {} folders", missing, count),
+        });
+        self.sequence_gaps = Some(folders);
+    }
+
+    fn show_sequence_gap_window(&mut self, ctx: &egui::Context) {
+        let Some(folders) = &self.sequence_gaps else {
+            return;
+        };
+        let mut open = true;
+        egui::Window::new("Sequence gaps").open(&mut open).show(ctx, |ui| {
+            if folders.is_empty() {
+                ui.label("Every folder's numbered file names run without gaps.");
+                return;
+            }
+            ui.label("Frame numbers missing from the file names. They may have been deleted before, or not copied off the card.");
+            egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                for (folder, gaps) in folders {
+                    egui::CollapsingHeader::new(format!("{} ({})", folder.display(), gaps.len()))
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            for gap in gaps {
+                                ui.monospace(gap.to_string());
+                            }
+                        });
+                }
+            });
+        });
+        if !open {
+            self.sequence_gaps = None;
+        }
+    }
+
+    fn show_tag_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_tags;
+        let mut removed = None;
+        egui::Window::new("Tags").open(&mut open).resizable(false).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let response = ui.add(egui::TextEdit::singleline(&mut self.new_tag).hint_text("New tag"));
+                let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if ui.button("➕ Add").clicked() || submitted {
+                    self.add_tag_name(self.new_tag.clone());
+                    self.new_tag.clear();
+                }
+            });
+            egui::Grid::new("tag_list").num_columns(3).show(ui, |ui| {
+                for tag in &self.tag_names {
+                    ui.label(tag);
+                    ui.weak(format!("{} images", self.images_tagged(tag).len()));
+                    if ui.small_button("🗑").on_hover_text("Remove the tag from every image").clicked() {
+                        removed = Some(tag.clone());
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+        if let Some(tag) = removed {
+            self.tag_names.retain(|name| *name != tag);
+            for image in self.images_tagged(&tag) {
+                let mut tags = self.tags[&image].clone();
+                tags.remove(&tag);
+                self.set_tags(&image, tags);
+            }
+            self.mark_unsaved();
+        }
+        self.show_tags = open;
+    }
+
+    fn add_tag_name(&mut self, tag: String) {
+        let tag = tag.trim().to_string();
+        if !tag.is_empty() && !self.tag_names.contains(&tag) {
+            self.tag_names.push(tag);
+            self.mark_unsaved();
+        }
+    }
+
+    fn toggle_tag(&mut self, path: &std::path::Path, tag: &str) {
+        let mut tags = self.tags.get(path).cloned().unwrap_or_default();
+        if !tags.remove(tag) {
+            tags.insert(tag.to_string());
+        }
+        self.set_tags(path, tags);
+    }
+
+    fn set_tags(&mut self, path: &std::path::Path, tags: HashSet<String>) {
+        self.journal(JournalEntry::Tag { path: path.to_path_buf(), tags: tags.clone() });
+        if tags.is_empty() {
+            self.tags.remove(path);
+        } else {
+            self.tags.insert(path.to_path_buf(), tags);
+        }
+        self.mark_unsaved();
+    }
+
+    /// Every image carrying `tag`, in no particular order.
+    fn images_tagged(&self, tag: &str) -> Vec<std::path::PathBuf> {
+        self.tags
+            .iter()
+            .filter(|(_, tags)| tags.contains(tag))
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    /// One toggle per tag for the current image.
+    fn show_tag_picker(&mut self, ui: &mut egui::Ui, path: &std::path::Path) {
+        if self.tag_names.is_empty() {
+            return;
+        }
+        let mut toggled = None;
+        ui.horizontal_wrapped(|ui| {
+            ui.label("🏷");
+            let current = self.tags.get(path);
+            for tag in &self.tag_names {
+                if ui.selectable_label(current.is_some_and(|tags| tags.contains(tag)), tag).clicked() {
+                    toggled = Some(tag.clone());
+                }
+            }
+        });
+        if let Some(tag) = toggled {
+            self.toggle_tag(path, &tag);
+        }
+    }
+
+    /// Rates, labels or tags every image of the current image's folder or burst in one go.
+    fn show_bulk_metadata(&mut self, ui: &mut egui::Ui, path: &std::path::Path) {
+        let folder = self.folder_images(path);
+        let burst = self.burst.as_ref().map(|burst| burst.frames.clone());
+        let mut edit = None;
+        let mut undo = false;
+        egui::CollapsingHeader::new("✏ Rate, label or tag the whole folder or burst").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut self.bulk_scope, BulkScope::Folder, format!("This folder ({})", folder.len()));
+                ui.add_enabled_ui(burst.is_some(), |ui| {
+                    let frames = burst.as_ref().map_or(0, Vec::len);
+                    ui.radio_value(&mut self.bulk_scope, BulkScope::Burst, format!("This burst ({})", frames));
+                });
+            });
+            let members = match (self.bulk_scope, &burst) {
+                (BulkScope::Burst, Some(frames)) => frames,
+                _ => &folder,
+            };
+            ui.add_enabled_ui(!members.is_empty(), |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Rating:");
+                    for rating in 1..=5 {
+                        if ui.button(metadata::stars(rating)).clicked() {
+                            edit = Some(BulkEdit::Rate(Some(rating)));
+                        }
+                    }
+                    if ui.button("None").clicked() {
+                        edit = Some(BulkEdit::Rate(None));
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Label:");
+                    for label in ColorLabel::ALL {
+                        if ui.button(egui::RichText::new("●").color(label.color())).on_hover_text(label.xmp_name()).clicked() {
+                            edit = Some(BulkEdit::Label(Some(label)));
+                        }
+                    }
+                    if ui.button("None").clicked() {
+                        edit = Some(BulkEdit::Label(None));
+                    }
+                });
+                if !self.tag_names.is_empty() {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label("Add tag:");
+                        for tag in &self.tag_names {
+                            if ui.button(tag).clicked() {
+                                edit = Some(BulkEdit::AddTag(tag.clone()));
+                            }
+                        }
+                    });
+                }
+            });
+            if !self.bulk_undo.is_empty() {
+                undo = ui.button(format!("↩ Undo the last bulk edit ({} images)", self.bulk_undo.len())).clicked();
+            }
+        });
+        if let Some(edit) = edit {
+            let members = match (self.bulk_scope, burst) {
+                (BulkScope::Burst, Some(frames)) => frames,
+                _ => folder,
+            };
+            self.apply_bulk_edit(&members, edit);
+        } else if undo {
+            self.undo_bulk_edit();
+        }
+    }
+
+    /// Gives every one of `images` the same rating, label or extra tag, remembering what they
+    /// had before so it can be taken back.
+    fn apply_bulk_edit(&mut self, images: &[std::path::PathBuf], edit: BulkEdit) {
+        self.bulk_undo = images
+            .iter()
+            .map(|image| {
+                let fields = XmpFields { rating: self.ratings.get(image).copied(), label: self.labels.get(image).copied() };
+                (image.clone(), fields, self.tags.get(image).cloned().unwrap_or_default())
+            })
+            .collect();
+        for image in images {
+            match &edit {
+                BulkEdit::Rate(rating) => self.set_rating(image, *rating),
+                BulkEdit::Label(label) => self.set_label(image, *label),
+                BulkEdit::AddTag(tag) => {
+                    let mut tags = self.tags.get(image).cloned().unwrap_or_default();
+                    tags.insert(tag.clone());
+                    self.set_tags(image, tags);
+                },
+            }
+        }
+        self.status_message = Some(format!("Applied to {} images", images.len()));
+        self.mark_unsaved();
+    }
+
+    /// Puts back the ratings, labels and tags the last bulk edit replaced.
+    fn undo_bulk_edit(&mut self) {
+        for (image, XmpFields { rating, label }, tags) in std::mem::take(&mut self.bulk_undo) {
+            if self.ratings.get(&image).copied() != rating {
+                self.set_rating(&image, rating);
+            }
+            if self.labels.get(&image).copied() != label {
+                self.set_label(&image, label);
+            }
+            if self.tags.get(&image).cloned().unwrap_or_default() != tags {
+                self.set_tags(&image, tags);
+            }
+        }
+        self.status_message = None;
+        self.mark_unsaved();
+    }
+
+    /// Batch keep/discard/export per tag once every image has been decided.
+    fn show_tag_actions(&mut self, ui: &mut egui::Ui) {
+        if self.tag_names.is_empty() {
+            return;
+        }
+        let mut action = None;
+        egui::CollapsingHeader::new("🏷 Act on tagged images").show(ui, |ui| {
+            egui::Grid::new("tag_actions").num_columns(5).show(ui, |ui| {
+                for tag in &self.tag_names {
+                    let images = self.images_tagged(tag);
+                    ui.label(tag);
+                    ui.weak(format!("{} images", images.len()));
+                    if ui.add_enabled(!images.is_empty(), egui::Button::new("👍 Keep all")).clicked() {
+                        action = Some((tag.clone(), TagAction::Keep));
+                    }
+                    if ui.add_enabled(!images.is_empty(), egui::Button::new("👎 Discard all")).clicked() {
+                        action = Some((tag.clone(), TagAction::Discard));
+                    }
+                    if ui
+                        .add_enabled(!images.is_empty(), egui::Button::new("📁 Export"))
+                        .on_hover_text(format!("Copy to tagged/{}", tag_folder_name(tag)))
+                        .clicked()
+                    {
+                        action = Some((tag.clone(), TagAction::Export));
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+        let Some((tag, action)) = action else {
+            return;
+        };
+        let mut images = self.images_tagged(&tag);
+        images.sort();
+        match action {
+            TagAction::Keep | TagAction::Discard => {
+                for image in &images {
+                    self.redecide(image, matches!(action, TagAction::Keep));
+                }
+                self.mark_unsaved();
+            },
+            TagAction::Export => {
+                if let Some(working_path) = &self.working_path {
+                    let output_folder = std::path::Path::new(working_path).join("tagged").join(tag_folder_name(&tag));
+                    let plan = self.copy_plan(vec![self.copy_batch(&images, output_folder)]);
+                    self.start_copy(plan, false, ui.ctx());
+                }
+            },
+        }
+    }
+
+    /// Images rated highly or given a label are worth a second look before they go.
+    fn needs_discard_confirmation(&self, path: &std::path::Path) -> bool {
+        self.settings.confirm_protected_discards
+            && (self.ratings.get(path).is_some_and(|rating| *rating >= PROTECTED_RATING) || self.labels.contains_key(path))
+    }
+
+    /// The most recently kept image from the same folder as `path`.
+    fn last_kept_in_folder(&self, path: &std::path::Path) -> Option<std::path::PathBuf> {
+        self.kept_images.iter().rev().find(|kept| kept.parent() == path.parent()).cloned()
+    }
+
+    fn show_discard_confirmation(&mut self, ctx: &egui::Context) {
+        let Some((path, bucket)) = self.pending_discard.clone() else {
+            return;
+        };
+        if self.review_texture.is_none()
+            && let Some(kept) = &self.review_kept
+            && let Some(color_image) = self.preloaded.remove(kept)
+        {
+            let uri = format!("bytes://review/{}", kept.display());
+            self.review_texture = Some(ctx.load_texture(uri, color_image, self.settings.texture_filter.texture_options()));
+        }
+        let bucket_name = self.settings.buckets.get(bucket).map_or("Discard", |bucket| bucket.name.as_str()).to_string();
+        let mut confirmed = false;
+        let response = egui::Modal::new(egui::Id::new("confirm_discard")).show(ctx, |ui| {
+            ui.heading(format!("{} this image?", bucket_name));
+            ui.horizontal(|ui| {
+                ui.monospace(path.file_name().unwrap_or_default().to_string_lossy());
+                if let Some(rating) = self.ratings.get(&path) {
+                    ui.label(metadata::stars(*rating));
+                }
+                if let Some(label) = self.labels.get(&path) {
+                    metadata::label_dot(ui, *label);
+                }
+            });
+            if self.settings.review_discards {
+                self.show_discard_review(ui);
+            }
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                confirmed = ui.button(format!("👎 {} (Enter)", bucket_name)).clicked() || ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if ui.button("Cancel (Esc)").clicked() {
+                    ui.close();
+                }
+            });
+        });
+        if confirmed {
+            self.pending_discard = None;
+            self.stretch.record_decision(std::time::Instant::now());
+            self.sort_image(&path, bucket);
+            self.mark_unsaved();
+        } else if response.should_close() {
+            self.pending_discard = None;
+        }
+        if self.pending_discard.is_none() {
+            self.review_kept = None;
+            self.review_texture = None;
+        }
+    }
+
+    /// The image about to be discarded next to the last one kept from its folder.
+    fn show_discard_review(&self, ui: &mut egui::Ui) {
+        let size = egui::vec2(320.0, 240.0);
+        ui.horizontal(|ui| {
+            ui.vertical(|ui| {
+                ui.label("Discarding");
+                if let Some(texture) = &self.texture {
+                    ui.add(egui::Image::new(texture).fit_to_exact_size(size));
+                }
+            });
+            ui.vertical(|ui| {
+                match (&self.review_kept, &self.review_texture) {
+                    (None, _) => {
+                        ui.label("⚠ Nothing kept from this folder yet");
+                    },
+                    (Some(kept), Some(texture)) => {
+                        ui.label(format!("Last kept: {}", kept.file_name().unwrap_or_default().to_string_lossy()));
+                        ui.add(egui::Image::new(texture).fit_to_exact_size(size));
+                    },
+                    (Some(kept), None) => {
+                        ui.label(format!("Last kept: {}", kept.file_name().unwrap_or_default().to_string_lossy()));
+                        ui.spinner();
+                    },
+                }
+            });
+        });
+    }
+
+    /// The bucket a decided image is in: the one it was sorted into, or else the first
+    /// bucket that keeps or discards like its decision.
+    fn bucket_of(&self, path: &std::path::Path) -> Option<&settings::Bucket> {
+        let keeps = *self.decisions.get(path)? == Decision::Keep;
+        let buckets = &self.settings.buckets;
+        self.bucket_assignments
+            .get(path)
+            .and_then(|name| buckets.iter().find(|bucket| bucket.name == *name && bucket.keeps == keeps))
+            .or_else(|| buckets.iter().find(|bucket| bucket.keeps == keeps))
+    }
+
+    /// Every decided image, kept ones first.
+    fn decided_images(&self) -> Vec<std::path::PathBuf> {
+        let mut discarded: Vec<std::path::PathBuf> = self
+            .decisions
+            .iter()
+            .filter(|(_, decision)| **decision == Decision::Discard)
+            .map(|(path, _)| path.clone())
+            .collect();
+        discarded.sort();
+        self.kept_images.iter().cloned().chain(discarded).collect()
+    }
+
+    /// Works out where each of `images` goes: the output folder of its bucket, or the
+    /// quarantine for images that could not be decoded. Images in buckets without an output
+    /// folder stay where they are.
+    fn plan_copy(&self, images: &[std::path::PathBuf]) -> export::CopyPlan {
+        let mut batches = Vec::new();
+        let Some(working_path) = self.working_path.as_ref().map(std::path::PathBuf::from) else {
+            return self.copy_plan(batches);
+        };
+        let quarantine = self.settings.quarantine_folder.trim();
+        let quarantines = self.settings.quarantine_decode_errors && !quarantine.is_empty();
+        let (unreadable, images): (Vec<std::path::PathBuf>, Vec<std::path::PathBuf>) = images
+            .iter()
+            .filter(|image| !self.moved.contains_key(*image)) // Already in their output folder
+            .cloned()
+            .partition(|image| quarantines && self.decode_errors.contains_key(image));
+        if !unreadable.is_empty() {
+            let mut batch = self.copy_batch(&unreadable, working_path.join(quarantine));
+            batch.decode_errors = Some(
+                unreadable
+                    .iter()
+                    .map(|image| {
+                        let shown_path = self.output_relative_path(image).unwrap_or_else(|| image.clone());
+                        (shown_path, self.decode_errors[image].clone())
+                    })
+                    .collect(),
+            );
+            batches.push(batch);
+        }
+        let mut output_folders = Vec::new();
+        for bucket in &self.settings.buckets {
+            let folder = bucket.output_folder.trim();
+            if folder.is_empty() || output_folders.contains(&working_path.join(folder)) {
+                continue; // Not copied, or a bucket before this one already shares its folder
+            }
+            let in_bucket: Vec<std::path::PathBuf> = images
+                .iter()
+                .filter(|image| self.bucket_of(image).is_some_and(|of| of.output_folder.trim() == folder))
+                .cloned()
+                .collect();
+            output_folders.push(working_path.join(folder));
+            batches.push(self.copy_batch(&in_bucket, working_path.join(folder)));
+        }
+        self.copy_plan(batches)
+    }
+
+    fn copy_plan(&self, batches: Vec<export::CopyBatch>) -> export::CopyPlan {
+        export::CopyPlan {
+            batches,
+            working_path: self.working_path.as_ref().map(std::path::PathBuf::from).unwrap_or_default(),
+            verify: self.settings.verify_copies,
+            preserve_timestamps: self.settings.preserve_timestamps,
+            watermark: self.settings.watermark.clone(),
+        }
+    }
+
+    fn copy_batch(&self, images: &[std::path::PathBuf], output_folder: std::path::PathBuf) -> export::CopyBatch {
+        let tasks = images
+            .iter()
+            .map(|image| {
+                let discarded = self.decisions.get(image) == Some(&Decision::Discard);
+                export::CopyTask {
+                    image: image.clone(),
+                    destination: self.destination_path(image, &output_folder),
+                    watermark: self.settings.watermark.enabled && !discarded && !self.decode_errors.contains_key(image),
+                    xmp: XmpFields { rating: self.ratings.get(image).copied(), label: self.labels.get(image).copied() },
+                    kept: self.decisions.get(image) == Some(&Decision::Keep),
+                }
+            })
+            .collect();
+        export::CopyBatch { output_folder, tasks, decode_errors: None }
+    }
+
+    /// Where a decided image goes in `output_folder`.
+    fn destination_path(&self, image: &std::path::Path, output_folder: &std::path::Path) -> Result<std::path::PathBuf, String> {
+        // Calculate relative path from the root the image was found in
+        let mut relative_path = self.output_relative_path(image)
+            .ok_or_else(|| format!("{} is outside the scanned folders", image.display()))?;
+        let split = &self.settings.export_split;
+        let discarded = self.decisions.get(image) == Some(&Decision::Discard);
+        if discarded && !self.settings.mirror_discard_folders {
+            // Flattened, with the folders in the name so files from different folders cannot collide
+            let flat_name = relative_path.iter().map(|component| component.to_string_lossy()).collect::<Vec<_>>().join("_");
+            relative_path = std::path::PathBuf::from(flat_name);
+        }
+        // Discards keep the layout of the originals, so they can be moved back as a whole
+        if split.enabled && !discarded {
+            let subfolder = split.subfolder(self.ratings.get(image).copied(), self.labels.get(image).copied());
+            relative_path = std::path::Path::new(subfolder).join(relative_path);
+        }
+        Ok(pages::export_path(&output_folder.join(relative_path)))
+    }
+
+    /// Starts copying on a thread of its own; the report replaces the last one when it is done.
+    fn start_copy(&mut self, plan: export::CopyPlan, straighten: bool, ctx: &egui::Context) {
+        if self.copy_job.is_some() {
+            return; // One copy at a time
+        }
+        self.copy_report = None;
+        self.copy_job = Some((export::CopyJob::start(plan, ctx.clone()), straighten));
+    }
+
+    /// Takes in the progress of the running copy and, once it is done, its report.
+    fn poll_copy(&mut self, ctx: &egui::Context) {
+        let Some((job, straighten)) = &mut self.copy_job else {
+            return;
+        };
+        job.poll();
+        let Some(result) = job.result.take() else {
+            return;
+        };
+        let straighten = *straighten;
+        self.copy_job = None;
+        self.copy_report = Some(result);
+        // Only copies: images moved out during the review are the originals themselves
+        if straighten
+            && self.settings.straighten_kept_jpegs
+            && orientation::LOSSLESS
+            && let Some(Ok(report)) = &self.copy_report
+        {
+            self.orientation_pass = Some(orientation::OrientationPass::start(report.kept_copies.clone(), ctx.clone()));
+        }
+    }
+
+    /// In move-as-you-go mode, moves a just decided image with its RAW and XMP sidecars into
+    /// the output folder of its bucket, so the source drains during the review. Images in
+    /// buckets without an output folder stay where they are.
+    fn move_decided(&mut self, path: &std::path::Path) {
+        // A page cannot leave without the rest of its file, so pages are copied at the end instead
+        if !self.settings.move_as_you_go || self.replaying_journal || self.moved.contains_key(path) || pages::is_page(path) {
+            return;
+        }
+        let (Some(working_path), Some(bucket)) = (&self.working_path, self.bucket_of(path)) else {
+            return;
+        };
+        let folder = bucket.output_folder.trim();
+        if folder.is_empty() {
+            return;
+        }
+        let output_folder = std::path::Path::new(working_path).join(folder);
+        let destination = match self.destination_path(path, &output_folder) {
+            Ok(destination) => destination,
+            Err(e) => {
+                self.status_message = Some(format!("❌ Could not move {}: {}", path.display(), e));
+                return;
+            },
+        };
+        let mut files = vec![(path.to_path_buf(), destination.clone())];
+        if let Some(raw) = export::raw_sidecar(path) {
+            let raw_destination = destination.with_extension(raw.extension().unwrap_or_default());
+            files.push((raw, raw_destination));
+        }
+        let xmp = metadata::sidecar_path(path);
+        if xmp.exists() {
+            files.push((xmp, metadata::sidecar_path(&destination)));
+        }
+        self.journal(JournalEntry::Move { path: path.to_path_buf(), files: files.clone() });
+        // Recorded as moved right away; files that do not arrive are taken back out when the
+        // move queue reports them
+        self.moved.insert(path.to_path_buf(), files.clone());
+        self.move_queue.get_or_insert_with(export::MoveQueue::start).submit(path.to_path_buf(), files, false, self.settings.verify_copies);
+    }
+
+    /// Takes in what the move queue has done since the last frame, and keeps checking while
+    /// it is busy.
+    fn poll_moves(&mut self, ctx: &egui::Context) {
+        let Some(queue) = &mut self.move_queue else {
+            return;
+        };
+        let outcomes = queue.finished();
+        if !queue.is_idle() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+        self.record_moves(outcomes);
+    }
+
+    /// Waits for the move queue to be done with `image`, or with everything for None.
+    fn finish_moves(&mut self, image: Option<&std::path::Path>) {
+        if let Some(queue) = &mut self.move_queue {
+            let outcomes = queue.wait_for(image);
+            self.record_moves(outcomes);
+        }
+    }
+
+    fn record_moves(&mut self, outcomes: Vec<export::MoveOutcome>) {
+        for outcome in outcomes {
+            if outcome.back {
+                self.record_move_back(outcome);
+                continue;
+            }
+            let Some((file, e)) = outcome.error else {
+                continue;
+            };
+            log::warn!("Could not move {}: {}", file.display(), e);
+            self.status_message = Some(format!("❌ Could not move {}: {}", file.display(), e));
+            if outcome.moved.is_empty() {
+                self.moved.remove(&outcome.image);
+            } else {
+                self.moved.insert(outcome.image, outcome.moved);
+            }
+        }
+    }
+
+    /// Starts putting an image moved out by `move_decided` back where it was found, to do
+    /// `then` with it once the move queue reports it back. Returns false when it is not moved
+    /// out, so there is nothing to wait for.
+    fn move_back(&mut self, path: &std::path::Path, then: AfterMoveBack) -> bool {
+        // While replaying, the journal's own move entries say where the files are
+        if self.replaying_journal || !self.moved.contains_key(path) {
+            return false;
+        }
+        if self.moving_back.contains_key(path) {
+            return true; // On its way already
+        }
+        let files = self.moved[path].iter().rev().map(|(from, to)| (to.clone(), from.clone())).collect();
+        self.journal(JournalEntry::MoveBack { path: path.to_path_buf() });
+        self.moving_back.insert(path.to_path_buf(), then);
+        self.move_queue
+            .get_or_insert_with(export::MoveQueue::start)
+            .submit(path.to_path_buf(), files, true, self.settings.verify_copies);
+        true
+    }
+
+    /// Does what waited for an image to be moved back. When some of its files could not be,
+    /// those stay recorded as moved and the image as it was.
+    fn record_move_back(&mut self, outcome: export::MoveOutcome) {
+        let then = self.moving_back.remove(&outcome.image);
+        let files = self.moved.remove(&outcome.image).unwrap_or_default();
+        self.decode_errors.remove(&outcome.image); // Anything that tried to read it while it was away failed
+        if let Some((file, e)) = outcome.error {
+            log::warn!("Could not move {} back: {}", file.display(), e);
+            self.status_message = Some(format!("❌ Could not move {} back: {}", file.display(), e));
+            let stuck = files
+                .into_iter()
+                .filter(|(from, to)| !outcome.moved.iter().any(|(back_from, back_to)| back_from == to && back_to == from))
+                .collect();
+            self.moved.insert(outcome.image, stuck);
+            if matches!(then, Some(AfterMoveBack::Reset)) {
+                self.reset_once_moved_back();
+            }
+            return;
+        }
+        match then {
+            Some(AfterMoveBack::Undecide) => self.undecide(&outcome.image),
+            Some(AfterMoveBack::Redecide(keep)) => self.redecide(&outcome.image, keep),
+            Some(AfterMoveBack::Resort(bucket)) => self.resort(&outcome.image, bucket),
+            Some(AfterMoveBack::Reset) => self.reset_once_moved_back(),
+            None => {},
+        }
+    }
+
+    /// Copies `images` into the output folders of their buckets in the background, but first
+    /// asks what to do when those folders already hold files from somewhere else.
+    fn run_copy(&mut self, images: Vec<std::path::PathBuf>, ctx: &egui::Context) {
+        if self.settings.confirm_output_conflicts {
+            let conflicts = self.output_conflicts(&images);
+            if !conflicts.is_empty() {
+                let conflicts = conflicts
+                    .into_iter()
+                    .map(|conflict| {
+                        let suggestion = export::free_sibling_folder(&conflict.folder, "");
+                        let name = suggestion.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                        (conflict, name)
+                    })
+                    .collect();
+                self.pending_export = Some(PendingExport { images, conflicts });
+                return;
+            }
+        }
+        self.copy_now(images, ctx);
+    }
+
+    /// Files in the output folders `images` would be copied to that neither this copy nor an
+    /// earlier one of this session wrote, by folder.
+    fn output_conflicts(&self, images: &[std::path::PathBuf]) -> Vec<export::OutputConflict> {
+        let Some(working_path) = &self.working_path else {
+            return Vec::new();
+        };
+        let working_path = std::path::PathBuf::from(working_path);
+        let plan = self.plan_copy(&self.decided_images());
+        let mut expected: Vec<(std::path::PathBuf, export::Origin)> = Vec::new();
+        for batch in &plan.batches {
+            for task in &batch.tasks {
+                let Ok(destination) = &task.destination else {
+                    continue;
+                };
+                let origin = if pages::is_page(&task.image) || task.watermark {
+                    export::Origin::Derived(pages::file_of(&task.image))
+                } else {
+                    export::Origin::Copy(task.image.clone())
+                };
+                expected.push((destination.clone(), origin));
+                expected.push((metadata::sidecar_path(destination), export::Origin::SidecarOf(destination.clone())));
+                if let Some(raw) = export::raw_sidecar(&task.image) {
+                    expected.push((destination.with_extension(raw.extension().unwrap_or_default()), export::Origin::Copy(raw)));
+                }
+            }
+            expected.push((batch.output_folder.join(self.settings.contact_sheet.file_name.trim()), export::Origin::Own));
+        }
+        expected.extend(self.moved.values().flatten().map(|(_, to)| (to.clone(), export::Origin::Own)));
+        let mut conflicts: Vec<export::OutputConflict> = Vec::new();
+        for bucket in &self.settings.buckets {
+            let folder = working_path.join(bucket.output_folder.trim());
+            if bucket.output_folder.trim().is_empty()
+                || !folder.is_dir()
+                || conflicts.iter().any(|conflict| conflict.folder == folder)
+                || !images.iter().any(|image| self.bucket_of(image).is_some_and(|of| of.output_folder.trim() == bucket.output_folder.trim()))
+            {
+                continue;
+            }
+            conflicts.extend(export::find_conflict(&folder, &expected));
+        }
+        conflicts
+    }
+
+    /// Moves what is in the conflicting output folders aside, next to them, then copies.
+    fn replace_and_copy(&mut self, pending: PendingExport, ctx: &egui::Context) {
+        let mut moved_aside = Vec::new();
+        for (conflict, _) in &pending.conflicts {
+            let aside = export::free_sibling_folder(&conflict.folder, "_previous");
+            if let Err(e) = export::move_conflict_aside(conflict, &aside, self.settings.verify_copies) {
+                log::warn!("Could not move the files in {} aside: {}", conflict.folder.display(), e);
+                self.status_message = Some(format!("❌ Could not move the files in {} aside: {}", conflict.folder.display(), e));
+                return;
+            }
+            moved_aside.push(aside.display().to_string());
+        }
+        self.status_message = Some(format!("♻ Moved the earlier files to {}", moved_aside.join(", ")));
+        self.copy_now(pending.images, ctx);
+    }
+
+    /// Points the buckets of every conflicting folder at the folder picked for it instead,
+    /// then copies, which checks the new folders in turn.
+    fn redirect_and_copy(&mut self, pending: PendingExport, ctx: &egui::Context) {
+        let Some(working_path) = self.working_path.clone().map(std::path::PathBuf::from) else {
+            return;
+        };
+        for (conflict, new_folder) in &pending.conflicts {
+            for bucket in &mut self.settings.buckets {
+                if working_path.join(bucket.output_folder.trim()) == conflict.folder {
+                    bucket.output_folder = new_folder.trim().to_string();
+                }
+            }
+        }
+        self.settings.save();
+        self.run_copy(pending.images, ctx);
+    }
+
+    fn copy_now(&mut self, images: Vec<std::path::PathBuf>, ctx: &egui::Context) {
+        let plan = self.plan_copy(&images);
+        self.start_copy(plan, true, ctx);
+    }
+
+    /// Progress of straightening the copied JPEGs, then how many needed it.
+    fn show_orientation_pass(&mut self, ui: &mut egui::Ui) {
+        let Some(pass) = &mut self.orientation_pass else {
+            return;
+        };
+        pass.poll();
+        let mut dismissed = false;
+        ui.horizontal(|ui| {
+            if pass.finished {
+                ui.label(format!("↻ Turned {} of {} kept images upright by their EXIF orientation", pass.turned, pass.total));
+                dismissed = ui.small_button("✖").clicked();
+            } else {
+                ui.spinner();
+                ui.add(egui::ProgressBar::new(pass.checked as f32 / pass.total.max(1) as f32).text(format!(
+                    "Straightening kept JPEGs: {} / {}",
+                    pass.checked, pass.total
+                )));
+            }
+        });
+        if !pass.failed.is_empty() {
+            egui::CollapsingHeader::new(format!("Could not straighten {} files", pass.failed.len())).show(ui, |ui| {
+                for (path, error) in &pass.failed {
+                    ui.monospace(format!("{}: {}", path.display(), error));
+                }
+            });
+        }
+        if dismissed {
+            self.orientation_pass = None;
+        }
+    }
+
+    /// Folder the last successful copy wrote to.
+    fn copied_output_folder(&self) -> Option<&std::path::Path> {
+        match &self.copy_report {
+            Some(Ok(report)) if report.output_folder.is_dir() => Some(&report.output_folder),
+            _ => None,
+        }
+    }
+
+    fn reveal_output_folder(&self) {
+        if let Some(folder) = self.copied_output_folder()
+            && let Err(e) = open::that(folder)
+        {
+            log::warn!("Could not open {}: {}", folder.display(), e);
+        }
+    }
+
+    fn show_folder_sidebar(&mut self, ctx: &egui::Context) {
+        if !self.settings.show_folder_tree {
+            return;
+        }
+        let (Some(tree), Some(working_path)) = (&self.images, &self.working_path) else {
+            return;
+        };
+        let base_path = std::path::PathBuf::from(working_path);
+        let tally = folders::tally(tree, &base_path, &self.decisions);
+        egui::SidePanel::left("folder_tree")
+            .resizable(true)
+            .default_width(280.0)
+            .show(ctx, |ui| {
+                ui.heading("Folders");
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    if self.extra_roots.is_empty() {
+                        folders::show_folder_tree(ui, tree, &tally, &base_path);
+                    } else {
+                        // Skip the nameless synthetic root and list the roots themselves
+                        for (root, root_tally) in tree.children.iter().zip(&tally.children) {
+                            folders::show_folder_tree(ui, root, root_tally, std::path::Path::new(&root.name));
+                        }
+                    }
+                });
+            });
+    }
+
+    /// File, capture and tag details of the current image in a panel on the right, as wide as
+    /// it was last dragged to.
+    fn show_info_sidebar(&mut self, ctx: &egui::Context) {
+        if !self.settings.show_info_pane {
+            return;
+        }
+        let Some(path) = self.image_paths.first().cloned() else {
+            return;
+        };
+        let file_size = std::fs::metadata(pages::file_of(&path)).ok().map(|metadata| metadata.len());
+        let texture_size = self.texture.as_ref().map(|texture| texture.size());
+        let exif = self.exif_info(&path).cloned().unwrap_or_default();
+        let response = egui::SidePanel::right("info_pane")
+            .resizable(true)
+            .default_width(self.settings.info_pane_width)
+            .width_range(160.0..=640.0)
+            .show(ctx, |ui| {
+                ui.heading("Info");
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("info_pane_grid").num_columns(2).striped(true).show(ui, |ui| {
+                        ui.label("File");
+                        ui.add(egui::Label::new(path.file_name().unwrap_or_default().to_string_lossy()).wrap());
+                        ui.end_row();
+                        ui.label("Folder");
+                        ui.add(egui::Label::new(path.parent().unwrap_or(&path).display().to_string()).wrap());
+                        ui.end_row();
+                        if let Some(file_size) = file_size {
+                            ui.label("Size");
+                            ui.label(summary::format_bytes(file_size));
+                            ui.end_row();
+                        }
+                        if let Some([width, height]) = texture_size {
+                            ui.label("Pixels");
+                            ui.label(format!("{} × {}", width, height));
+                            ui.end_row();
+                        }
+                        if let Some(capture_settings) = exif.capture_settings.summary() {
+                            ui.label("Camera");
+                            ui.add(egui::Label::new(capture_settings).wrap());
+                            ui.end_row();
+                        }
+                        if let Some(gps) = exif.gps {
+                            ui.label("Position");
+                            ui.vertical(|ui| {
+                                ui.label(gps.to_string());
+                                if ui.button("🗺 Open map").clicked()
+                                    && let Err(e) = open::that(gps.map_url())
+                                {
+                                    log::warn!("Could not open map for {}: {}", gps, e);
+                                }
+                            });
+                            ui.end_row();
+                        }
+                        if let Some(rating) = self.ratings.get(&path) {
+                            ui.label("Rating");
+                            ui.label(metadata::stars(*rating));
+                            ui.end_row();
+                        }
+                        if let Some(label) = self.labels.get(&path) {
+                            ui.label("Label");
+                            metadata::label_dot(ui, *label);
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        // Saved once the divider is let go rather than on every dragged frame
+        let width = response.response.rect.width();
+        if (width - self.settings.info_pane_width).abs() >= 1.0 && !ctx.input(|input| input.pointer.any_down()) {
+            self.settings.info_pane_width = width;
+            self.settings.save();
+        }
+    }
+
+    fn show_completion_screen(&mut self, ui: &mut egui::Ui) {
+        self.texture = None; // Ensure no image is displayed
+        ui.label("🎉 All images processed!");
+        ui.horizontal(|ui| {
+            ui.label(format!("Kept: {}", self.kept_images.len()));
+            ui.label(format!("Discarded: {}", self.discarded_images.len()));
+        });
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            if ui.add_enabled(self.copy_job.is_none(), egui::Button::new("📁 Copy Kept Images")).clicked() {
+                self.run_copy(self.decided_images(), ui.ctx());
+            }
+            let reveal_enabled = self.copied_output_folder().is_some();
+            let reveal = ui
+                .add_enabled(reveal_enabled, egui::Button::new("📂 Reveal output folder"))
+                .on_hover_text("Open the copied images in the file manager (O)")
+                .on_disabled_hover_text("Copy the kept images first")
+                .clicked();
+            let reveal_key = !ui.ctx().wants_keyboard_input() && ui.input(|i| i.modifiers.is_none() && i.key_pressed(egui::Key::O));
+            if reveal || (reveal_enabled && reveal_key) {
+                self.reveal_output_folder();
+            }
+            if ui.button("📝 Save summary").on_hover_text(format!("Write {} to the working folder", summary::SUMMARY_FILE)).clicked() {
+                self.save_summary();
+            }
+            if ui.button("🔄 Reset").clicked() {
+                if self.settings.confirm_reset {
+                    self.pending_reset = true;
+                } else {
+                    self.reset_progress();
+                }
+            }
+        });
+        self.show_contact_sheet(ui);
+        self.show_tag_actions(ui);
+    }
+
+    /// Where the copies of kept images go: the first keeping bucket's output folder.
+    fn kept_output_folder(&self) -> Option<std::path::PathBuf> {
+        let working_path = std::path::Path::new(self.working_path.as_ref()?);
+        let folder = self.settings.buckets.iter().find(|bucket| bucket.keeps && !bucket.output_folder.trim().is_empty())?;
+        Some(working_path.join(folder.output_folder.trim()))
+    }
+
+    /// The button and options for a contact sheet of the kept images, and how rendering it goes.
+    fn show_contact_sheet(&mut self, ui: &mut egui::Ui) {
+        if let Some(job) = &mut self.contact_sheet_job {
+            job.poll();
+        }
+        let running = self.contact_sheet_job.as_ref().is_some_and(|job| job.result.is_none());
+        let output_folder = self.kept_output_folder().or_else(|| self.working_path.as_ref().map(std::path::PathBuf::from));
+        ui.horizontal(|ui| {
+            let enabled = !running && !self.kept_images.is_empty() && output_folder.is_some();
+            if ui
+                .add_enabled(enabled, egui::Button::new("🖼 Contact sheet"))
+                .on_hover_text("Tile all kept images, with their names, onto one image in the output folder")
+                .clicked()
+                && let Some(output_folder) = output_folder
+            {
+                let mut images: Vec<std::path::PathBuf> = self
+                    .kept_images
+                    .iter()
+                    .map(|image| {
+                        // Moved-out images are read from where they are now
+                        self.moved.get(image).and_then(|files| files.first()).map_or_else(|| image.clone(), |(_, to)| to.clone())
+                    })
+                    .collect();
+                images.sort();
+                self.contact_sheet_job = Some(contact_sheet::ContactSheetJob::start(
+                    self.settings.contact_sheet.clone(),
+                    images,
+                    output_folder,
+                    self.settings.jpeg_backend,
+                    ui.ctx().clone(),
+                ));
+            }
+            ui.add_enabled_ui(!running, |ui| {
+                if self.settings.contact_sheet.ui(ui) {
+                    self.settings.save();
+                }
+            });
+        });
+        match self.contact_sheet_job.as_ref().map(|job| (job.done, job.total, &job.result)) {
+            Some((done, total, None)) => {
+                ui.add(egui::ProgressBar::new(done as f32 / total.max(1) as f32).text(format!("Contact sheet: {} / {}", done, total)));
+            },
+            Some((_, _, Some(Ok(path)))) => {
+                ui.label(format!("✅ Contact sheet saved to {}", path.display()));
+            },
+            Some((_, _, Some(Err(e)))) => {
+                ui.label(format!("❌ Could not make the contact sheet: {}", e));
+            },
+            None => {},
+        }
+    }
+
+    fn save_summary(&mut self) {
+        let (Some(tree), Some(working_path)) = (&self.images, &self.working_path) else {
+            return;
+        };
+        let base_path = std::path::PathBuf::from(working_path);
+        let summary = summary::SessionSummary::new(
+            tree,
+            &base_path,
+            &self.decisions,
+            self.skipped_count,
+            self.shuffle_seed,
+            std::time::Duration::from_secs_f64(self.time_spent),
+        );
+        let file_path = base_path.join(summary::SUMMARY_FILE);
+        self.status_message = Some(match std::fs::write(&file_path, summary.to_text()) {
+            Ok(()) => format!("Summary saved to {}", file_path.display()),
+            Err(e) => format!("❌ Could not save the summary: {}", e),
+        });
+    }
+
+    fn show_copy_report(&mut self, ui: &mut egui::Ui) {
+        if let Some((job, _)) = &self.copy_job {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.add(egui::ProgressBar::new(job.done as f32 / job.total.max(1) as f32).text(format!("Copying: {} / {}", job.done, job.total)));
+            });
+        }
+        let mut retry = None;
+        match &self.copy_report {
+            Some(Ok(report)) if report.failed.is_empty() => {
+                ui.label(format!(
+                    "✅ {} images copied to: {}{}",
+                    report.copied,
+                    report.output_folder.display(),
+                    if report.verified { ", each checked against its original" } else { "" }
+                ));
+                if report.recopied > 0 {
+                    ui.label(format!("⚠ {} copies did not match their originals at first and were copied again", report.recopied));
+                }
+                if report.quarantined > 0 {
+                    ui.label(format!("⚠ {} images that could not be decoded were copied to {}/", report.quarantined, self.settings.quarantine_folder.trim()));
+                }
+                if report.unwatermarked > 0 {
+                    ui.label(format!("⚠ {} kept images are not JPEGs and were copied without the watermark", report.unwatermarked));
+                }
+            },
+            Some(Ok(report)) => {
+                ui.label(format!(
+                    "⚠ {} images copied to: {}, {} failed",
+                    report.copied,
+                    report.output_folder.display(),
+                    report.failed.len()
+                ));
+                if report.quarantined > 0 {
+                    ui.label(format!("⚠ {} images that could not be decoded were copied to {}/", report.quarantined, self.settings.quarantine_folder.trim()));
+                }
+                if report.unwatermarked > 0 {
+                    ui.label(format!("⚠ {} kept images are not JPEGs and were copied without the watermark", report.unwatermarked));
+                }
+                egui::CollapsingHeader::new("Failed files").show(ui, |ui| {
+                    for (path, error) in &report.failed {
+                        ui.monospace(format!("{}: {}", path.display(), error));
+                    }
+                });
+                if ui.button("🔁 Retry failed").clicked() {
+                    retry = Some(report.failed.iter().map(|(path, _)| path.clone()).collect());
+                }
+            },
+            Some(Err(e)) => {
+                ui.label(format!("❌ Error copying images: {}", e));
+            },
+            None => {},
+        }
+        if let Some(failed) = retry {
+            self.run_copy(failed, ui.ctx());
+        }
+        self.show_orientation_pass(ui);
+    }
+
+    /// Sorts the image at the front of the queue into `bucket` and advances.
+    fn decide_current(&mut self, bucket: usize) {
+        if let Some(path) = self.image_paths.first().cloned() {
+            self.stretch.record_decision(std::time::Instant::now());
+            if let Some(bucket) = self.settings.buckets.get(bucket) {
+                self.flash = Some((bucket.keeps, std::time::Instant::now()));
+            }
+            self.sort_image(&path, bucket);
+            self.last_decided = Some(path);
+            self.mark_unsaved();
+        }
+    }
+
+    /// Puts the image decided last into another bucket without going back to it. In
+    /// move-as-you-go mode its files move over to the new bucket's folder.
+    fn resort_last_decided(&mut self, bucket: usize) {
+        if let Some(path) = self.last_decided.clone() {
+            self.resort(&path, bucket);
+        }
+    }
+
+    fn resort(&mut self, path: &std::path::Path, bucket_index: usize) {
+        let Some(bucket) = self.settings.buckets.get(bucket_index).cloned() else {
+            return;
+        };
+        let path = path.to_path_buf();
+        if !self.decisions.contains_key(&path) || self.bucket_of(&path).is_some_and(|current| current.name == bucket.name) {
+            return; // Taken back in the meantime, or there already
+        }
+        if self.move_back(&path, AfterMoveBack::Resort(bucket_index)) {
+            return; // Goes on once its files are out of the old bucket's folder
+        }
+        self.journal(JournalEntry::Decide { path: path.clone(), keep: bucket.keeps, bucket: Some(bucket.name.clone()) });
+        self.kept_images.retain(|kept| *kept != path);
+        self.discarded_images.retain(|discarded| *discarded != path);
+        if bucket.keeps {
+            self.kept_images.push(path.clone());
+            self.decisions.insert(path.clone(), Decision::Keep);
+        } else {
+            self.discarded_images.push(path.clone());
+            self.decisions.insert(path.clone(), Decision::Discard);
+        }
+        self.bucket_assignments.insert(path.clone(), bucket.name.clone());
+        self.move_decided(&path);
+        self.flash = Some((bucket.keeps, std::time::Instant::now()));
+        self.show_toast(format!("↪ {} is now in {}", path.file_name().unwrap_or_default().to_string_lossy(), bucket.name));
+        self.mark_unsaved();
+    }
+
+    /// Takes `path` out of the queue, wherever it is, and records the decision.
+    fn record_decision(&mut self, path: &std::path::Path, keep: bool) {
+        let Some(index) = self.image_paths.iter().position(|queued| queued == path) else {
+            return;
+        };
+        let path = self.image_paths.remove(index);
+        self.journal(JournalEntry::Decide {
+            path: path.clone(),
+            keep,
+            bucket: self.bucket_assignments.get(&path).cloned(),
+        });
+        if keep {
+            self.kept_images.push(path.clone());
+            self.decisions.insert(path.clone(), Decision::Keep);
+        } else {
+            self.discarded_images.push(path.clone());
+            self.decisions.insert(path.clone(), Decision::Discard);
+        }
+        self.move_decided(&path);
+        self.trail.retain(|decided| *decided != path);
+        self.trail.push(path.clone());
+        if self.trail.len() > DECISION_TRAIL_LENGTH {
+            self.trail.remove(0);
+        }
+        if index == 0 {
+            // Drop the previous texture
+            self.texture = None;
+            self.image_counter += 1;
+        }
+    }
+
+    /// Sorts a queued image into `bucket`, wherever it is in the queue.
+    fn sort_image(&mut self, path: &std::path::Path, bucket: usize) {
+        let Some(bucket) = self.settings.buckets.get(bucket) else {
+            return;
+        };
+        if !self.image_paths.iter().any(|queued| queued == path) {
+            return;
+        }
+        self.bucket_assignments.insert(path.to_path_buf(), bucket.name.clone());
+        self.record_decision(path, bucket.keeps);
+    }
+
+    /// Changes the decision of an image that may already have one; queued images are decided
+    /// as usual.
+    fn redecide(&mut self, path: &std::path::Path, keep: bool) {
+        let changes = matches!(
+            (self.decisions.get(path), keep),
+            (Some(Decision::Keep), false) | (Some(Decision::Discard), true)
+        );
+        if changes && self.move_back(path, AfterMoveBack::Redecide(keep)) {
+            return; // Goes on once its files are out of the old bucket's folder
+        }
+        if self.decisions.contains_key(path) {
+            self.bucket_assignments.remove(path); // Falls back to the first bucket of the new kind
+        }
+        match (self.decisions.get(path), keep) {
+            (None, _) => self.record_decision(path, keep),
+            (Some(Decision::Keep), false) => {
+                self.journal(JournalEntry::Decide { path: path.to_path_buf(), keep, bucket: None });
+                self.kept_images.retain(|kept| kept != path);
+                self.discarded_images.push(path.to_path_buf());
+                self.decisions.insert(path.to_path_buf(), Decision::Discard);
+            },
+            (Some(Decision::Discard), true) => {
+                self.journal(JournalEntry::Decide { path: path.to_path_buf(), keep, bucket: None });
+                self.kept_images.push(path.to_path_buf());
+                self.discarded_images.retain(|discarded| discarded != path);
+                self.decisions.insert(path.to_path_buf(), Decision::Keep);
+            },
+            _ => {},
+        }
+        if changes {
+            self.move_decided(path);
+        }
+    }
+
+    /// Takes a kept image back out of the kept set and puts it at the front of the queue
+    /// to be decided again.
+    fn unkeep(&mut self, path: &std::path::Path) {
+        if self.decisions.get(path) == Some(&Decision::Keep) {
+            self.stretch.record_undo(std::time::Instant::now());
+            self.undecide(path);
+        }
+    }
+
+    /// Forgets the decision on an image and puts it at the front of the queue.
+    fn undecide(&mut self, path: &std::path::Path) {
+        if !self.decisions.contains_key(path) || self.move_back(path, AfterMoveBack::Undecide) {
+            return; // Requeued once its files are back
+        }
+        let Some(decision) = self.decisions.remove(path) else {
+            return;
+        };
+        self.journal(JournalEntry::Undecide { path: path.to_path_buf() });
+        match decision {
+            Decision::Keep => self.kept_images.retain(|kept| kept != path),
+            Decision::Discard => self.discarded_images.retain(|discarded| discarded != path),
+        }
+        self.bucket_assignments.remove(path);
+        self.image_paths.insert(0, path.to_path_buf());
+        self.texture = None;
+        self.image_counter += 1;
+        self.mark_unsaved();
+    }
+
+    /// Thumbnails of the most recently kept images, newest first. Returns the one clicked.
+    fn show_kept_strip(&mut self, ui: &mut egui::Ui) -> Option<std::path::PathBuf> {
+        if self.kept_images.is_empty() {
+            return None;
+        }
+        let recent: Vec<std::path::PathBuf> = self.kept_images.iter().rev().take(KEPT_STRIP_LENGTH).cloned().collect();
+        let mut clicked = None;
+        egui::CollapsingHeader::new(format!("✅ Kept so far ({})", self.kept_images.len()))
+            .id_salt("kept_strip")
+            .show(ui, |ui| {
+                egui::ScrollArea::horizontal().show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        for path in recent {
+                            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                            if self.thumbnail_button(ui, &path, 64.0).on_hover_text(format!("{}\nClick to un-keep", name)).clicked() {
+                                clicked = Some(path);
+                            }
+                        }
+                    });
+                });
+            });
+        clicked
+    }
+
+    /// The last few decisions, oldest first, edged in the color of their verdict. Returns the
+    /// one clicked, to be decided again.
+    fn show_decision_trail(&mut self, ui: &mut egui::Ui) -> Option<std::path::PathBuf> {
+        if !self.settings.show_decision_trail {
+            return None;
+        }
+        let trail: Vec<(std::path::PathBuf, bool, String)> = self
+            .trail
+            .iter()
+            .filter_map(|path| {
+                let keeps = *self.decisions.get(path)? == Decision::Keep;
+                let bucket = self.bucket_of(path).map_or_else(String::new, |bucket| bucket.name.clone());
+                Some((path.clone(), keeps, bucket))
+            })
+            .collect();
+        if trail.is_empty() {
+            return None;
+        }
+        let mut clicked = None;
+        ui.horizontal(|ui| {
+            ui.label("🕘").on_hover_text("Your last decisions, newest on the right");
+            for (path, keeps, bucket) in trail {
+                let color = self.settings.flash_color(keeps);
+                let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                let response = egui::Frame::new()
+                    .stroke(egui::Stroke::new(2.0, color))
+                    .inner_margin(1.0)
+                    .show(ui, |ui| {
+                        let response = self.thumbnail_button(ui, &path, 40.0);
+                        let icon = if keeps { "✔" } else { "✖" };
+                        ui.painter().text(
+                            response.rect.right_bottom() - egui::vec2(3.0, 1.0),
+                            egui::Align2::RIGHT_BOTTOM,
+                            icon,
+                            egui::FontId::proportional(12.0),
+                            color,
+                        );
+                        response
+                    })
+                    .inner;
+                if response.on_hover_text(format!("{}\n{}\nClick to decide it again", name, bucket)).clicked() {
+                    clicked = Some(path);
+                }
+            }
+        });
+        clicked
+    }
+
+    /// The thumbnail of `path` as a button, or a placeholder while it is being decoded.
+    fn thumbnail_button(&mut self, ui: &mut egui::Ui, path: &std::path::Path, size: f32) -> egui::Response {
+        match self.thumbnails.get(path) {
+            Some(texture) => ui.add(egui::ImageButton::new(egui::Image::new(texture).max_height(size))),
+            None => {
+                if !self.decode_errors.contains_key(path)
+                    && !self.moved.contains_key(path)
+                    && let Some(pool) = &self.decode_pool
+                    && self.pending_thumbnails.insert(path.to_path_buf())
+                {
+                    pool.submit(DecodeJob::Thumbnail(path.to_path_buf()));
+                }
+                ui.add_sized([size, size], egui::Button::new("…"))
+            },
+        }
+    }
+
+    /// What the grid shows: the queue, or with decided images shown every image in tree order.
+    fn grid_images(&mut self) -> Vec<std::path::PathBuf> {
+        if !self.grid_show_decided {
+            return self.image_paths.clone();
+        }
+        let (Some(tree), Some(working_path)) = (&self.images, &self.working_path) else {
+            return self.image_paths.clone();
+        };
+        let count = tree.count_images();
+        if self.grid_all_images.0 != count {
+            // With several roots their nodes are named by absolute paths, so any base works
+            self.grid_all_images = (count, tree.get_images_depth_first_current_priority(std::path::Path::new(working_path)));
+        }
+        self.grid_all_images.1.clone()
+    }
+
+    /// The queue as a grid of thumbnails. Click, ctrl-click and shift-click select, a bucket
+    /// key or button sorts every selected image, Ctrl+A selects all and Esc clears.
+    fn show_queue_grid(&mut self, ui: &mut egui::Ui) {
+        let images = self.grid_images();
+        let mut sort_into = None;
+        let hotkeys_enabled = self.pending_discard.is_none() && self.pending_recovery.is_none() && !self.pending_reset && self.pending_export.is_none() && !ui.ctx().wants_keyboard_input();
+        ui.input(|i| {
+            if !hotkeys_enabled {
+                return;
+            }
+            sort_into = self
+                .settings
+                .buckets
+                .iter()
+                .position(|bucket| bucket.key().is_some_and(|key| i.key_pressed(key)));
+            if i.modifiers.command && i.key_pressed(egui::Key::A) {
+                self.grid_selection.select_all(&images);
+            }
+            if i.key_pressed(egui::Key::Escape) {
+                self.grid_selection.clear();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let selected = self.grid_selection.selected.len();
+            ui.label(format!("{} of {} selected", selected, images.len()));
+            for (index, bucket) in self.settings.buckets.iter().enumerate() {
+                let icon = if bucket.keeps { "👍" } else { "👎" };
+                if ui.add_enabled(selected > 0, egui::Button::new(format!("{} {}", icon, bucket.name))).clicked() {
+                    sort_into = Some(index);
+                }
+            }
+            ui.separator();
+            if ui.checkbox(&mut self.grid_show_decided, "Show decided").changed() {
+                self.grid_selection.clear();
+            }
+        });
+        ui.separator();
+
+        let cell = grid::THUMBNAIL_SIZE + ui.spacing().item_spacing.x + 2.0 * ui.spacing().button_padding.x;
+        let columns = ((ui.available_width() / cell) as usize).max(1);
+        let rows = images.len().div_ceil(columns);
+        let mut clicked = None;
+        let mut missing = Vec::new();
+        egui::ScrollArea::vertical().auto_shrink(false).show_rows(ui, cell, rows, |ui, row_range| {
+            for row in row_range {
+                ui.horizontal(|ui| {
+                    let start = row * columns;
+                    for (index, path) in images.iter().enumerate().skip(start).take(columns) {
+                        let selected = self.grid_selection.selected.contains(path);
+                        let size = egui::Vec2::splat(grid::THUMBNAIL_SIZE);
+                        let bucket = self.decisions.contains_key(path).then(|| self.bucket_of(path)).flatten();
+                        let tint = if bucket.is_some() { egui::Color32::from_gray(110) } else { egui::Color32::WHITE }; // Decided ones fade back
+                        let button = match self.thumbnails.get(path) {
+                            Some(texture) => egui::Button::image(egui::Image::new(texture).fit_to_exact_size(size).tint(tint)),
+                            None => {
+                                missing.push(path.clone());
+                                let placeholder = if self.decode_errors.contains_key(path) { "⚠" } else { "…" };
+                                egui::Button::new(placeholder).min_size(size)
+                            },
+                        };
+                        let name = path.file_name().unwrap_or_default().to_string_lossy();
+                        let response = ui.add(button.selected(selected));
+                        if self.settings.show_palette
+                            && let Some(swatches) = self.palettes.get(path)
+                        {
+                            let band = egui::Rect::from_min_max(response.rect.left_bottom() - egui::vec2(0.0, GRID_PALETTE_HEIGHT), response.rect.right_bottom());
+                            palette::paint_band(ui.painter(), band.shrink2(egui::vec2(3.0, 2.0)), swatches);
+                        }
+                        let response = match bucket {
+                            Some(bucket) => {
+                                let icon = if bucket.keeps { "👍" } else { "👎" };
+                                ui.painter().text(
+                                    response.rect.left_top() + egui::vec2(4.0, 4.0),
+                                    egui::Align2::LEFT_TOP,
+                                    icon,
+                                    egui::FontId::proportional(16.0),
+                                    egui::Color32::WHITE,
+                                );
+                                response.on_hover_text(format!("{}\n{}", name, bucket.name))
+                            },
+                            None => response.on_hover_text(name),
+                        };
+                        if response.clicked() {
+                            clicked = Some(index);
+                        }
+                    }
+                });
+            }
+        });
+
+        if let Some(pool) = &self.decode_pool {
+            for path in missing {
+                if !self.decode_errors.contains_key(&path) && !self.moved.contains_key(&path) && self.pending_thumbnails.insert(path.clone()) {
+                    pool.submit(DecodeJob::Thumbnail(path));
+                }
+            }
+        }
+        if let Some(index) = clicked {
+            let modifiers = ui.input(|i| i.modifiers);
+            self.grid_selection.click(index, &images, modifiers);
+        }
+        if let Some(bucket) = sort_into {
+            self.sort_selection(&images, bucket);
+        }
+    }
+
+    /// Sorts every image of `images` selected in the grid into `bucket`; decided ones are
+    /// moved over. Images that would need a confirmation to be discarded stay selected,
+    /// to be looked at one by one.
+    fn sort_selection(&mut self, images: &[std::path::PathBuf], bucket: usize) {
+        let Some((name, keeps)) = self.settings.buckets.get(bucket).map(|bucket| (bucket.name.clone(), bucket.keeps)) else {
+            return;
+        };
+        let selected: Vec<std::path::PathBuf> = images
+            .iter()
+            .filter(|path| self.grid_selection.selected.contains(*path))
+            .cloned()
+            .collect();
+        let (protected, sorted): (Vec<_>, Vec<_>) = selected.into_iter().partition(|path| !keeps && self.needs_discard_confirmation(path));
+        for path in &sorted {
+            if self.decisions.contains_key(path) {
+                self.redecide(path, keeps);
+                self.bucket_assignments.insert(path.clone(), name.clone());
+            } else {
+                self.sort_image(path, bucket);
+            }
+        }
+        self.grid_selection.clear();
+        if !protected.is_empty() {
+            self.status_message = Some(format!(
+                "Sorted {} images; {} rated or labelled ones are still selected, discard them one by one",
+                sorted.len(),
+                protected.len()
+            ));
+            self.grid_selection.selected.extend(protected);
+        }
+        self.mark_unsaved();
+    }
+
+    /// Scores every queued image; once all are in, `update_quota` applies the quota.
+    fn start_quota(&mut self) {
+        let images = self.image_paths.clone();
+        if let Some(pool) = &self.decode_pool {
+            for image in &images {
+                if !self.sharpness.contains_key(image) && self.pending_scores.insert(image.clone()) {
+                    pool.submit(DecodeJob::Sharpness(image.clone()));
+                }
+            }
+        }
+        self.quota_images = Some(images);
+    }
+
+    fn update_quota(&mut self) {
+        let Some(images) = &self.quota_images else {
+            return;
+        };
+        if images.iter().all(|image| self.sharpness.contains_key(image))
+            && let Some(images) = self.quota_images.take()
+        {
+            self.apply_quota(&images);
+        }
+    }
+
+    /// Keeps the sharpest share of each folder's queued images and discards the rest.
+    /// Images that need a confirmation to be discarded stay in the queue.
+    fn apply_quota(&mut self, images: &[std::path::PathBuf]) {
+        let images: Vec<std::path::PathBuf> = images.iter().filter(|image| self.image_paths.contains(image)).cloned().collect();
+        let fraction = f64::from(self.settings.quota_percent) / 100.0;
+        let (keep, discard) = quota::split_by_quota(&images, &self.sharpness, fraction);
+        let discard: Vec<std::path::PathBuf> = discard.into_iter().filter(|image| !self.needs_discard_confirmation(image)).collect();
+        for image in &keep {
+            self.record_decision(image, true);
+        }
+        for image in &discard {
+            self.record_decision(image, false);
+        }
+        self.quota_decided = images.into_iter().filter(|image| self.decisions.contains_key(image)).collect();
+        self.status_message = Some(format!("Quota kept {} and discarded {} images", keep.len(), discard.len()));
+        self.mark_unsaved();
+    }
+
+    /// Puts every image the last quota decided back into the queue, in its old order.
+    fn undo_quota(&mut self) {
+        for image in std::mem::take(&mut self.quota_decided).iter().rev() {
+            self.undecide(image);
+        }
+        self.status_message = None;
+    }
+
+    fn show_quota_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_quota;
+        let mut start = false;
+        let mut cancel = false;
+        let mut undo = false;
+        egui::Window::new("Keep best per folder").open(&mut open).resizable(false).show(ctx, |ui| {
+            ui.label("Keeps the sharpest images of each folder in the queue and discards the rest, as a starting point to refine.");
+            if ui
+                .add(egui::Slider::new(&mut self.settings.quota_percent, 1..=100).suffix(" %").text("kept per folder"))
+                .changed()
+            {
+                self.settings.save();
+            }
+            match &self.quota_images {
+                Some(images) => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        let scored = images.iter().filter(|image| self.sharpness.contains_key(*image)).count();
+                        ui.label(format!("Scoring sharpness {}/{}", scored, images.len()));
+                        cancel = ui.button("Cancel").clicked();
+                    });
+                },
+                None => {
+                    let enabled = !self.image_paths.is_empty() && self.scan.is_none();
+                    start = ui
+                        .add_enabled(enabled, egui::Button::new(format!("⚖ Apply to {} queued images", self.image_paths.len())))
+                        .clicked();
+                },
+            }
+            if !self.quota_decided.is_empty() {
+                undo = ui.button(format!("↩ Undo the last quota ({} images)", self.quota_decided.len())).clicked();
+            }
+        });
+        self.show_quota = open;
+        if start {
+            self.start_quota();
+        } else if cancel {
+            self.quota_images = None;
+        } else if undo {
+            self.undo_quota();
+        }
+    }
+
+    /// Scores every queued image, so the blur pass can show their distribution.
+    fn start_blur_pass(&mut self) {
+        let images = self.image_paths.clone();
+        if let Some(pool) = &self.decode_pool {
+            for image in &images {
+                if !self.sharpness.contains_key(image) && self.pending_scores.insert(image.clone()) {
+                    pool.submit(DecodeJob::Sharpness(image.clone()));
+                }
+            }
+        }
+        self.blur_images = Some(images);
+    }
+
+    /// Discards the still queued images scoring below the threshold, except those that need
+    /// a confirmation to be discarded.
+    fn apply_blur_pass(&mut self, images: &[std::path::PathBuf]) {
+        let queued: HashSet<&std::path::PathBuf> = self.image_paths.iter().collect();
+        let images: Vec<std::path::PathBuf> = images.iter().filter(|image| queued.contains(image)).cloned().collect();
+        let discard: Vec<std::path::PathBuf> = blur::below_threshold(&images, &self.sharpness, self.settings.blur_threshold)
+            .into_iter()
+            .filter(|image| !self.needs_discard_confirmation(image))
+            .collect();
+        for image in &discard {
+            self.record_decision(image, false);
+        }
+        self.status_message = Some(format!("Discarded {} blurry images", discard.len()));
+        self.blur_decided = discard;
+        self.mark_unsaved();
+    }
+
+    /// Puts every image the last blur pass discarded back into the queue, in its old order.
+    fn undo_blur_pass(&mut self) {
+        for image in std::mem::take(&mut self.blur_decided).iter().rev() {
+            self.undecide(image);
+        }
+        self.status_message = None;
+    }
+
+    fn show_blur_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_blur_pass;
+        let mut start = false;
+        let mut apply = false;
+        let mut cancel = false;
+        let mut undo = false;
+        egui::Window::new("Discard blurry images").open(&mut open).resizable(false).show(ctx, |ui| {
+            ui.label("Scores the sharpness of every queued image, then discards the ones below the threshold.");
+            match &self.blur_images {
+                Some(images) => {
+                    let scores: Vec<f64> = images.iter().filter_map(|image| self.sharpness.get(image).copied()).collect();
+                    if scores.len() < images.len() {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label(format!("Scoring sharpness {}/{}", scores.len(), images.len()));
+                        });
+                    }
+                    let mut changed = ui
+                        .add(egui::Slider::new(&mut self.settings.blur_threshold, 1.0..=10_000.0).logarithmic(true).text("Threshold"))
+                        .changed();
+                    changed |= blur::show_histogram(ui, &scores, &mut self.settings.blur_threshold);
+                    if changed {
+                        self.settings.save();
+                    }
+                    let below = scores.iter().filter(|score| **score < self.settings.blur_threshold).count();
+                    ui.label(format!("{} of {} scored images are below the threshold", below, scores.len()));
+                    ui.horizontal(|ui| {
+                        apply = ui
+                            .add_enabled(scores.len() == images.len() && below > 0, egui::Button::new(format!("🗑 Discard {} images", below)))
+                            .on_disabled_hover_text("Wait for every image to be scored")
+                            .clicked();
+                        cancel = ui.button("Cancel").clicked();
+                    });
+                },
+                None => {
+                    let enabled = !self.image_paths.is_empty() && self.scan.is_none();
+                    start = ui
+                        .add_enabled(enabled, egui::Button::new(format!("🔅 Score {} queued images", self.image_paths.len())))
+                        .clicked();
+                },
+            }
+            if !self.blur_decided.is_empty() {
+                undo = ui.button(format!("↩ Undo the last blur pass ({} images)", self.blur_decided.len())).clicked();
+            }
+        });
+        self.show_blur_pass = open;
+        if start {
+            self.start_blur_pass();
+        } else if apply && let Some(images) = self.blur_images.take() {
+            self.apply_blur_pass(&images);
+        } else if cancel {
+            self.blur_images = None;
+        } else if undo {
+            self.undo_blur_pass();
+        }
+    }
+
+    /// Looks for the person in the current image through the whole queue.
+    fn start_face_search(&mut self, ctx: &egui::Context) {
+        let Some(reference) = self.image_paths.first().cloned() else {
+            return;
+        };
+        self.face_search = Some(faces::FaceSearch::start(
+            reference,
+            self.image_paths.clone(),
+            self.settings.face_tolerance,
+            self.settings.jpeg_backend,
+            ctx.clone(),
+        ));
+    }
+
+    fn update_face_search(&mut self) {
+        let Some(search) = &mut self.face_search else {
+            return;
+        };
+        search.poll();
+        if search.finished
+            && search.error.is_none()
+            && let Some(search) = self.face_search.take()
+        {
+            self.apply_face_search(&search.matches, &search.misses);
+        }
+    }
+
+    /// Keeps the queued images the person was found in and discards the ones they were not,
+    /// except those that need a confirmation to be discarded.
+    fn apply_face_search(&mut self, matches: &[std::path::PathBuf], misses: &[std::path::PathBuf]) {
+        let queued: HashSet<&std::path::PathBuf> = self.image_paths.iter().collect();
+        let keep: Vec<std::path::PathBuf> = matches.iter().filter(|image| queued.contains(image)).cloned().collect();
+        let discard: Vec<std::path::PathBuf> = misses
+            .iter()
+            .filter(|image| queued.contains(image) && !self.needs_discard_confirmation(image))
+            .cloned()
+            .collect();
+        let decided: HashSet<&std::path::PathBuf> = keep.iter().chain(&discard).collect();
+        let decided: Vec<std::path::PathBuf> = self.image_paths.iter().filter(|image| decided.contains(image)).cloned().collect();
+        for image in &keep {
+            self.record_decision(image, true);
+        }
+        for image in &discard {
+            self.record_decision(image, false);
+        }
+        self.faces_decided = decided;
+        self.status_message = Some(format!("Face search kept {} and discarded {} images", keep.len(), discard.len()));
+        self.mark_unsaved();
+    }
+
+    /// Puts every image the last face search decided back into the queue, in its old order.
+    fn undo_face_search(&mut self) {
+        for image in std::mem::take(&mut self.faces_decided).iter().rev() {
+            self.undecide(image);
+        }
+        self.status_message = None;
+    }
+
+    fn show_faces_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_faces;
+        let mut start = false;
+        let mut cancel = false;
+        let mut undo = false;
+        egui::Window::new("Find a person").open(&mut open).resizable(false).show(ctx, |ui| {
+            ui.label(
+                "Keeps the queued images the person in the current image is in and discards the rest. \
+                 The current image should show one face only.",
+            );
+            if ui
+                .add(egui::Slider::new(&mut self.settings.face_tolerance, 0.3..=0.8).text("Tolerance"))
+                .on_hover_text("Higher finds the person in more images, but also mistakes others for them")
+                .changed()
+            {
+                self.settings.save();
+            }
+            match &self.face_search {
+                Some(search) if search.error.is_some() => {
+                    ui.colored_label(ui.visuals().warn_fg_color, search.error.as_deref().unwrap_or_default());
+                    cancel = ui.button("OK").clicked();
+                },
+                Some(search) => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(format!(
+                            "Looking through {}/{}, found in {}",
+                            search.checked(),
+                            search.total,
+                            search.matches.len()
+                        ));
+                        cancel = ui.button("Cancel").clicked();
+                    });
+                },
+                None => {
+                    let enabled = faces::FACES_AVAILABLE && !self.image_paths.is_empty() && self.scan.is_none();
+                    start = ui
+                        .add_enabled(enabled, egui::Button::new(format!("👤 Search {} queued images", self.image_paths.len())))
+                        .on_disabled_hover_text(if faces::FACES_AVAILABLE {
+                            "Needs a queue and a finished scan"
+                        } else {
+                            "This build was made without the faces feature"
+                        })
+                        .clicked();
+                },
+            }
+            if !self.faces_decided.is_empty() {
+                undo = ui.button(format!("↩ Undo the last face search ({} images)", self.faces_decided.len())).clicked();
+            }
+        });
+        self.show_faces = open;
+        if start {
+            self.start_face_search(ctx);
+        } else if cancel {
+            self.face_search = None;
+        } else if undo {
+            self.undo_face_search();
+        }
+    }
+
+    /// Buckets for subfolders of `path` like keep/ and reject/, unless the buckets already
+    /// copy into all of them.
+    fn buckets_for_existing_folders(&self, path: &std::path::Path) -> Option<Vec<Bucket>> {
+        let folder_names: Vec<String> = std::fs::read_dir(path)
+            .ok()?
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect();
+        let buckets = settings::buckets_from_folders(&folder_names);
+        let covered = buckets
+            .iter()
+            .filter(|bucket| !bucket.output_folder.is_empty())
+            .all(|bucket| self.settings.buckets.iter().any(|existing| existing.output_folder == bucket.output_folder));
+        (!buckets.is_empty() && !covered).then_some(buckets)
+    }
+
+    /// Offers to sort into the folders of an existing manual sort found in the working folder.
+    fn show_folder_bucket_offer(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let Some(buckets) = &self.folder_bucket_offer else {
+            return;
+        };
+        let mut accept = false;
+        let mut dismiss = false;
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            let folders: Vec<String> = buckets
+                .iter()
+                .filter(|bucket| !bucket.output_folder.is_empty())
+                .map(|bucket| format!("{}/", bucket.output_folder))
+                .collect();
+            ui.horizontal_wrapped(|ui| {
+                ui.label(format!("📂 This folder already has {} subfolders. Sort into them as buckets?", folders.join(", ")));
+                accept = ui.button("Use as buckets").on_hover_text("Replaces the current buckets; they can be changed in the settings").clicked();
+                dismiss = ui.button("Not now").clicked();
+            });
+        });
+        if accept
+            && let (Some(buckets), Some(working_path)) = (self.folder_bucket_offer.take(), self.working_path.clone())
+        {
+            self.settings.buckets = buckets;
+            self.settings.save();
+            // Scanned again, now leaving out the bucket folders like any other output
+            self.open_working_folder(std::path::PathBuf::from(working_path), ctx);
+        } else if dismiss {
+            self.folder_bucket_offer = None;
+        }
+    }
+
+    /// A dismissable suggestion to take a break after a long stretch of culling.
+    fn show_break_nudge(&mut self, ui: &mut egui::Ui) {
+        if !self.settings.suggest_breaks {
+            return;
+        }
+        let now = std::time::Instant::now();
+        let max_duration = std::time::Duration::from_secs(u64::from(self.settings.break_after_minutes) * 60);
+        if !self.stretch.is_due(now, max_duration, self.settings.break_after_decisions as usize) {
+            return;
+        }
+        let mut dismissed = false;
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label(format!(
+                    "☕ {} decisions in {} minutes without a pause, {:.0}% taken back. A few minutes away helps avoid mistakes.",
+                    self.stretch.decisions,
+                    self.stretch.duration(now).as_secs() / 60,
+                    self.stretch.undo_rate() * 100.0
+                ));
+                dismissed = ui.button("Keep going").clicked();
+            });
+        });
+        if dismissed {
+            self.stretch.dismiss(now);
+        }
+    }
+
+    /// Defers the current image to the end of the queue without deciding on it.
+    fn skip_current(&mut self) {
+        if self.image_paths.len() < 2 {
+            return;
+        }
+        self.journal(JournalEntry::Skip { path: self.image_paths[0].clone() });
+        self.image_paths.rotate_left(1);
+        self.texture = None;
+        self.image_counter += 1;
+        self.skipped_count += 1;
+        self.mark_unsaved();
+    }
+
+    /// Queued images in the folder of the current one, in queue order, the current one first.
+    fn queued_in_current_folder(&self) -> Vec<std::path::PathBuf> {
+        let Some(folder) = self.image_paths.first().and_then(|current| current.parent()) else {
+            return Vec::new();
+        };
+        self.image_paths.iter().filter(|queued| queued.parent() == Some(folder)).cloned().collect()
+    }
+
+    /// Defers what is left of the current folder to the end of the queue, like skipping
+    /// each of its images, so the first image of the next folder comes up.
+    fn next_folder(&mut self) {
+        let rest = self.queued_in_current_folder();
+        if rest.is_empty() || rest.len() == self.image_paths.len() {
+            self.show_toast("No other folder left in the queue".to_string());
+            return;
+        }
+        for path in &rest {
+            self.journal(JournalEntry::Skip { path: path.clone() });
+        }
+        let deferred: HashSet<&std::path::PathBuf> = rest.iter().collect();
+        self.image_paths.retain(|queued| !deferred.contains(queued));
+        self.image_paths.extend(rest.iter().cloned());
+        self.texture = None;
+        self.image_counter += 1;
+        self.skipped_count += rest.len();
+        self.mark_unsaved();
+    }
+
+    /// Sorts what is left of the current folder into the first discarding bucket. Rated or
+    /// labelled images that would need a confirmation are deferred to the end of the queue
+    /// instead, to be looked at one by one.
+    fn skip_rest_of_folder(&mut self) {
+        let Some(bucket) = self.settings.buckets.iter().position(|bucket| !bucket.keeps) else {
+            self.show_toast("There is no discarding bucket to put the rest of the folder in".to_string());
+            return;
+        };
+        let (protected, discarded): (Vec<_>, Vec<_>) =
+            self.queued_in_current_folder().into_iter().partition(|path| self.needs_discard_confirmation(path));
+        for path in &discarded {
+            self.sort_image(path, bucket);
+        }
+        for path in &protected {
+            if let Some(index) = self.image_paths.iter().position(|queued| queued == path) {
+                self.journal(JournalEntry::Skip { path: path.clone() });
+                let path = self.image_paths.remove(index);
+                self.image_paths.push(path);
+                self.skipped_count += 1;
+            }
+        }
+        if !protected.is_empty() {
+            self.texture = None;
+            self.image_counter += 1;
+        }
+        let bucket_name = &self.settings.buckets[bucket].name;
+        self.show_toast(match protected.len() {
+            0 => format!("⏬ {} images put in {}", discarded.len(), bucket_name),
+            kept_back => format!("⏬ {} images put in {}, {} rated or labelled ones moved to the end", discarded.len(), bucket_name, kept_back),
+        });
+        self.mark_unsaved();
+    }
+
+    /// Images with a final decision and the number of distinct images discovered.
+    /// Skipping never moves this, so it only ever advances and hits the total
+    /// exactly when every image is either kept or discarded.
+    fn progress(&self) -> (usize, usize) {
+        let total = self.images.as_ref().map_or(0, |node| node.count_images());
+        (self.decisions.len().min(total), total)
+    }
+
+    /// Title for the OS window and taskbar: progress while culling, the result once done.
+    fn window_title(&self) -> String {
+        if self.working_path.is_none() {
+            return APP_TITLE.to_string();
+        }
+        if self.image_paths.is_empty() && self.scan.is_none() && !self.queue_filter.is_active() {
+            return format!("{} — Done — {} kept", APP_TITLE, with_thousands_separators(self.kept_images.len()));
+        }
+        let (decided, total) = self.progress();
+        let percent = (decided * 100).checked_div(total).unwrap_or(0);
+        format!(
+            "{} — {} / {} ({}%)",
+            APP_TITLE,
+            with_thousands_separators(decided),
+            with_thousands_separators(total),
+            percent
+        )
+    }
+
+    /// Search box narrowing the queue down to matching file names and paths as you type.
+    fn show_queue_filter(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("🔍");
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.queue_filter.text)
+                    .hint_text("Filter the queue by name or path")
+                    .desired_width(240.0),
+            );
+            let clear = ui.add_enabled(self.queue_filter.is_active(), egui::Button::new("✖ Clear")).clicked();
+            if clear {
+                self.queue_filter.text.clear();
+            }
+            if response.changed() || clear {
+                self.queue_filter.apply(&mut self.image_paths);
+                self.texture = None;
+                self.image_counter += 1;
+                self.grid_selection.clear(); // Its shift-click anchor is an index into the queue
+            }
+            if self.queue_filter.is_active() {
+                ui.label(format!("{} match, {} set aside", self.image_paths.len(), self.queue_filter.hidden().len()));
+                if self.image_paths.is_empty() && self.scan.is_none() {
+                    ui.label("— no queued image matches");
+                }
+            }
+        });
+    }
+
+    /// Progress files written before decisions were tracked only list the kept images
+    /// and what was left; everything else discovered must have been discarded.
+    fn rebuild_decisions(&mut self) {
+        let (Some(images_node), Some(working_path)) = (&self.images, &self.working_path) else {
+            return;
+        };
+        let remaining: HashSet<&std::path::PathBuf> = self.image_paths.iter().chain(self.queue_filter.hidden()).collect();
+        let kept: HashSet<&std::path::PathBuf> = self.kept_images.iter().collect();
+        for path in images_node.get_images_depth_first_current_priority(std::path::Path::new(working_path)) {
+            if kept.contains(&path) {
+                self.decisions.insert(path, Decision::Keep);
+            } else if !remaining.contains(&path) {
+                self.discarded_images.push(path.clone());
+                self.decisions.insert(path, Decision::Discard);
+            }
+        }
+    }
+
+    /// Puts every image back into the queue, once those moved out are moved back.
+    fn reset_progress(&mut self) {
+        for then in self.moving_back.values_mut() {
+            *then = AfterMoveBack::Reset;
+        }
+        let moved: Vec<std::path::PathBuf> = self.moved.keys().cloned().collect();
+        for path in moved {
+            self.move_back(&path, AfterMoveBack::Reset);
+        }
+        if self.moving_back.is_empty() {
+            self.finish_reset();
+        } else {
+            self.show_toast(format!("Moving {} images back before resetting…", self.moving_back.len()));
+        }
+    }
+
+    /// Finishes the reset once the last image it waited for is moved back, or could not be.
+    fn reset_once_moved_back(&mut self) {
+        if !self.moving_back.values().any(|then| matches!(then, AfterMoveBack::Reset)) {
+            self.finish_reset();
+        }
+    }
+
+    fn finish_reset(&mut self) {
+        // Still in their output folder, so they stay decided and recorded as moved
+        let stuck: Vec<(std::path::PathBuf, Option<Decision>, Option<String>)> = self
+            .moved
+            .keys()
+            .map(|path| (path.clone(), self.decisions.get(path).copied(), self.bucket_assignments.get(path).cloned()))
+            .collect();
+        if let (Some(images_node), Some(working_path)) = (&self.images, &self.working_path) {
+            let path = std::path::PathBuf::from(working_path);
+            self.image_paths = images_node.get_images_depth_first_current_priority(&path);
+        }
+        self.queue_filter.reset();
+        self.kept_images.clear();
+        self.discarded_images.clear();
+        self.decisions.clear();
+        self.bucket_assignments.clear();
+        self.image_counter = 0;
+        self.skipped_count = 0;
+        self.shuffle_seed = None; // Back in folder order
+        self.time_spent = 0.0;
+        for (path, decision, bucket) in &stuck {
+            self.image_paths.retain(|queued| queued != path);
+            match decision {
+                Some(Decision::Keep) => self.kept_images.push(path.clone()),
+                Some(Decision::Discard) => self.discarded_images.push(path.clone()),
+                None => {},
+            }
+            if let Some(decision) = decision {
+                self.decisions.insert(path.clone(), *decision);
+            }
+            if let Some(bucket) = bucket {
+                self.bucket_assignments.insert(path.clone(), bucket.clone());
+            }
+        }
+        if !stuck.is_empty() {
+            self.status_message = Some(format!(
+                "⚠ {} moved images could not be moved back and stay decided in their output folder",
+                stuck.len()
+            ));
+        }
+        // Folders the scan has yet to deliver get queued when they arrive
+        if let Some(running) = &mut self.scan {
+            running.queued = self.image_paths.iter().cloned().collect();
+            running.queue_new_images = true;
+            running.shuffle_when_done = false;
+            running.held_back.clear(); // Already in the tree, so queued above
+        }
+        self.save_progress(); // The journal only makes sense on top of what it was reset to
+    }
+
+    /// Notes that the progress file is out of date. It is written once nothing has changed
+    /// for a while; the journal covers the time in between.
+    fn mark_unsaved(&mut self) {
+        if self.settings.autosave_idle_secs == 0 && !self.replaying_journal {
+            self.save_progress();
+        } else {
+            self.unsaved_since = Some(std::time::Instant::now());
+        }
+    }
+
+    /// Writes the progress file when changes have rested long enough, or the journal has
+    /// grown long enough to be worth folding in, and otherwise wakes up in time to do it.
+    fn autosave(&mut self, ctx: &egui::Context) {
+        let Some(unsaved_since) = self.unsaved_since else {
+            return;
+        };
+        let idle_time = std::time::Duration::from_secs(self.settings.autosave_idle_secs.into());
+        let idle = unsaved_since.elapsed();
+        if idle >= idle_time || self.journal_length >= JOURNAL_COMPACT_AFTER {
+            self.save_progress();
+        } else {
+            ctx.request_repaint_after(idle_time - idle);
+        }
+    }
+
+    /// Records an action in the journal right away, so it survives a crash before the next save.
+    fn journal(&mut self, entry: JournalEntry) {
+        let Some(working_path) = &self.working_path else {
+            return;
+        };
+        if self.replaying_journal {
+            return;
+        }
+        match journal::append(std::path::Path::new(working_path), &entry) {
+            Ok(()) => self.journal_length += 1,
+            Err(e) => log::warn!("Could not write to the journal: {}", e),
+        }
+    }
+
+    fn set_label(&mut self, path: &std::path::Path, label: Option<ColorLabel>) {
+        self.journal(JournalEntry::Label { path: path.to_path_buf(), label });
+        match label {
+            Some(label) => self.labels.insert(path.to_path_buf(), label),
+            None => self.labels.remove(path),
+        };
+        self.mark_unsaved();
+    }
+
+    fn set_rating(&mut self, path: &std::path::Path, rating: Option<u8>) {
+        self.journal(JournalEntry::Rate { path: path.to_path_buf(), rating });
+        match rating {
+            Some(rating) => self.ratings.insert(path.to_path_buf(), rating),
+            None => self.ratings.remove(path),
+        };
+        self.mark_unsaved();
+    }
+
+    /// Redoes the actions of a journal on top of the progress file it was written after.
+    /// Images the background scan has not delivered yet are decided all the same; the
+    /// scan leaves decided images out of the queue.
+    fn replay_journal(&mut self, entries: Vec<JournalEntry>) {
+        self.replaying_journal = true;
+        for entry in entries {
+            match entry {
+                JournalEntry::Decide { path, keep, bucket } => {
+                    if self.decisions.contains_key(&path) || self.image_paths.contains(&path) {
+                        self.redecide(&path, keep);
+                    } else if keep {
+                        self.kept_images.push(path.clone());
+                        self.decisions.insert(path.clone(), Decision::Keep);
+                    } else {
+                        self.discarded_images.push(path.clone());
+                        self.decisions.insert(path.clone(), Decision::Discard);
+                    }
+                    // After redeciding, which forgets the bucket of a decided image
+                    if let Some(bucket) = bucket {
+                        self.bucket_assignments.insert(path, bucket);
+                    }
+                },
+                JournalEntry::Undecide { path } => self.undecide(&path),
+                JournalEntry::Skip { path } => {
+                    if let Some(index) = self.image_paths.iter().position(|queued| *queued == path) {
+                        let path = self.image_paths.remove(index);
+                        self.image_paths.push(path);
+                        self.skipped_count += 1;
+                    }
+                },
+                JournalEntry::Rate { path, rating } => self.set_rating(&path, rating),
+                JournalEntry::Label { path, label } => self.set_label(&path, label),
+                JournalEntry::Tag { path, tags } => {
+                    for tag in &tags {
+                        self.add_tag_name(tag.clone());
+                    }
+                    self.set_tags(&path, tags);
+                },
+                JournalEntry::Move { path, files } => {
+                    let arrived: Vec<_> = files.into_iter().filter(|(from, to)| to.exists() && !from.exists()).collect();
+                    if !arrived.is_empty() {
+                        self.moved.insert(path, arrived);
+                    }
+                },
+                JournalEntry::MoveBack { path } => {
+                    if let Some(files) = self.moved.remove(&path) {
+                        let stuck: Vec<_> = files.into_iter().filter(|(from, to)| to.exists() && !from.exists()).collect();
+                        if !stuck.is_empty() {
+                            self.moved.insert(path, stuck);
+                        }
+                    }
+                },
+            }
+        }
+        self.replaying_journal = false;
+        self.texture = None;
+        self.image_counter += 1;
+    }
+
+    fn show_recovery_prompt(&mut self, ctx: &egui::Context) {
+        let Some(entries) = &self.pending_recovery else {
+            return;
+        };
+        let mut replay = false;
+        let mut discard = false;
+        let moves = entries.iter().filter(|entry| matches!(entry, JournalEntry::Move { .. })).count();
+        egui::Modal::new(egui::Id::new("recover_journal")).show(ctx, |ui| {
+            ui.heading("Recover unsaved progress?");
+            ui.label(format!(
+                "The last session in this folder ended before {} actions were saved, probably in a crash.",
+                entries.len()
+            ));
+            if moves > 0 {
+                ui.label(format!(
+                    "⚠ {} of them moved images into an output folder. Those images keep their decision either way, \
+                     so they are not lost track of.",
+                    moves
+                ));
+            }
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                replay = ui.button("♻ Replay them").clicked();
+                discard = ui.button("Discard them").clicked();
+            });
+        });
+        if replay && let Some(entries) = self.pending_recovery.take() {
+            let count = entries.len();
+            self.replay_journal(entries);
+            self.save_progress();
+            self.status_message = Some(format!("Recovered {} actions from the journal", count));
+        } else if discard && let Some(entries) = self.pending_recovery.take() {
+            self.drop_journal(entries);
+        }
+    }
+
+    /// Forgets the actions of a journal, except where files were moved: those moves, and the
+    /// decisions of the images still moved out after them, are replayed, as the files are in
+    /// their output folder whatever the progress file says.
+    fn drop_journal(&mut self, entries: Vec<JournalEntry>) {
+        let (moves, others): (Vec<JournalEntry>, Vec<JournalEntry>) = entries
+            .into_iter()
+            .partition(|entry| matches!(entry, JournalEntry::Move { .. } | JournalEntry::MoveBack { .. }));
+        if moves.is_empty() {
+            if let Some(working_path) = &self.working_path {
+                journal::clear(std::path::Path::new(working_path));
+            }
+            return;
+        }
+        self.replay_journal(moves);
+        let decisions = others
+            .into_iter()
+            .filter(|entry| matches!(entry, JournalEntry::Decide { path, .. } if self.moved.contains_key(path)))
+            .collect();
+        self.replay_journal(decisions);
+        self.save_progress();
+    }
+
+    fn show_toast(&mut self, message: String) {
+        self.toast = Some((message, std::time::Instant::now()));
+    }
+
+    /// Shows the toast at the bottom of the window until it has been up for a while.
+    fn show_toast_area(&mut self, ctx: &egui::Context) {
+        let Some((message, shown_at)) = &self.toast else {
+            return;
+        };
+        let Some(remaining) = TOAST_DURATION.checked_sub(shown_at.elapsed()) else {
+            self.toast = None;
+            return;
+        };
+        egui::Area::new(egui::Id::new("toast"))
+            .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -40.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(message.as_str());
+                });
+            });
+        ctx.request_repaint_after(remaining);
+    }
+
+    /// Zooms to the largest face in the current image, finding the faces first if need be,
+    /// or back out to the whole image when zoomed in already.
+    fn toggle_face_zoom(&mut self, ctx: &egui::Context) {
+        if self.zoom.is_zoomed() {
+            self.zoom.reset();
+            return;
+        }
+        let Some(current) = self.image_paths.first().cloned() else {
+            return;
+        };
+        if let Some(faces) = self.face_boxes.get(&current) {
+            match faces.first() {
+                Some(face) => self.zoom.zoom_to(face.expand2(face.size() * FACE_ZOOM_MARGIN)),
+                None => self.show_toast("👤 No faces found in this image".to_string()),
+            }
+        } else if !faces::FACES_AVAILABLE {
+            self.show_toast("👤 This build was made without the faces feature".to_string());
+        } else if let Some(image) = self.current_image.clone().filter(|_| self.texture.is_some())
+            && self.face_detection.as_ref().is_none_or(|detection| detection.path != current)
+        {
+            self.face_detection = Some(faces::FaceDetection::start(current, image, ctx.clone()));
+        }
+    }
+
+    /// Takes in the faces of a finished detection and zooms to the largest, if the image it
+    /// ran for is still the current one.
+    fn update_face_detection(&mut self, ctx: &egui::Context) {
+        let Some(result) = self.face_detection.as_ref().and_then(faces::FaceDetection::poll) else {
+            return;
+        };
+        let Some(detection) = self.face_detection.take() else {
+            return;
+        };
+        match result {
+            Ok(faces) => {
+                self.face_boxes.insert(detection.path.clone(), faces);
+                if self.image_paths.first() == Some(&detection.path) && !self.zoom.is_zoomed() {
+                    self.toggle_face_zoom(ctx);
+                }
+            },
+            Err(e) => {
+                log::warn!("Could not look for faces in {}: {}", detection.path.display(), e);
+                self.show_toast(format!("👤 Could not look for faces: {}", e));
+            },
+        }
+    }
+
+    /// Switches between fit, fill and actual pixels, saying which it is now.
+    fn cycle_fit_mode(&mut self) {
+        self.zoom.mode = self.zoom.mode.next();
+        self.zoom.reset();
+        self.show_toast(format!("🔍 {}", self.zoom.mode.name()));
+    }
+
+    /// Flashes the window edge in the keep or discard color for a moment after a decision,
+    /// fading out, so fast culling can tell the key press registered.
+    fn paint_decision_flash(&mut self, ctx: &egui::Context) {
+        let Some((keep, decided_at)) = self.flash.filter(|_| self.settings.flash_decisions) else {
+            return;
+        };
+        let duration = std::time::Duration::from_millis(self.settings.flash_millis.into());
+        let elapsed = decided_at.elapsed();
+        if elapsed >= duration {
+            self.flash = None;
+            return;
+        }
+        let strength = 1.0 - elapsed.as_secs_f32() / duration.as_secs_f32();
+        let color = self.settings.flash_color(keep).gamma_multiply(strength);
+        let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("decision_flash")));
+        painter.rect_stroke(
+            ctx.screen_rect().shrink(FLASH_WIDTH / 2.0),
+            0.0,
+            egui::Stroke::new(FLASH_WIDTH, color),
+            egui::StrokeKind::Middle,
+        );
+        ctx.request_repaint_after(std::time::Duration::from_millis(16)); // Keeps the fade going
+    }
+
+    fn show_reset_confirmation(&mut self, ctx: &egui::Context) {
+        if !self.pending_reset {
+            return;
+        }
+        let mut reset = false;
+        let mut cancel = false;
+        let mut dont_ask_again = !self.settings.confirm_reset;
+        egui::Modal::new(egui::Id::new("confirm_reset")).show(ctx, |ui| {
+            ui.heading("Reset all decisions?");
+            let files = if self.settings.move_as_you_go || !self.moved.is_empty() {
+                format!("The {} images moved into their output folders are moved back to where they were found.", self.moved.len())
+            } else {
+                "The image files themselves are not touched.".to_string()
+            };
+            ui.label(format!(
+                "The {} kept and {} discarded images go back into the queue to be decided again. {}",
+                self.kept_images.len(),
+                self.discarded_images.len(),
+                files
+            ));
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                if ui.button("📁 Copy kept images first").clicked() {
+                    self.run_copy(self.decided_images(), ctx);
+                }
+                if ui.button("📝 Save summary first").clicked() {
+                    self.save_summary();
+                }
+            });
+            self.show_copy_report(ui);
+            ui.checkbox(&mut dont_ask_again, "Don't ask again");
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                reset = ui.button("🔄 Reset").clicked();
+                cancel = ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape));
+            });
+        });
+        if reset {
+            if dont_ask_again {
+                self.settings.confirm_reset = false;
+                self.settings.save();
+            }
+            self.pending_reset = false;
+            self.reset_progress();
+        } else if cancel {
+            self.pending_reset = false;
+        }
+    }
+
+    fn show_export_confirmation(&mut self, ctx: &egui::Context) {
+        let Some(pending) = &mut self.pending_export else {
+            return;
+        };
+        let working_path = self.working_path.clone().map(std::path::PathBuf::from).unwrap_or_default();
+        let (mut merge, mut replace, mut redirect, mut cancel) = (false, false, false, false);
+        egui::Modal::new(egui::Id::new("confirm_export")).show(ctx, |ui| {
+            ui.heading("Output folder already in use");
+            ui.label("These output folders hold files this session did not copy there, e.g. from sorting other images:");
+            ui.add_space(6.0);
+            for (conflict, new_folder) in &mut pending.conflicts {
+                egui::CollapsingHeader::new(format!(
+                    "{}: {} files, {}",
+                    conflict.folder.display(),
+                    conflict.files.len(),
+                    summary::format_bytes(conflict.bytes)
+                ))
+                .id_salt(&conflict.folder)
+                .show(ui, |ui| {
+                    egui::ScrollArea::vertical().max_height(120.0).show(ui, |ui| {
+                        for file in &conflict.files {
+                            ui.monospace(file.strip_prefix(&conflict.folder).unwrap_or(file).display().to_string());
+                        }
+                    });
+                });
+                ui.horizontal(|ui| {
+                    ui.label("New folder:");
+                    ui.add(egui::TextEdit::singleline(new_folder).desired_width(200.0));
+                    if ui.button("Browse…").clicked()
+                        && let Some(path) = rfd::FileDialog::new().set_directory(&working_path).pick_folder()
+                    {
+                        *new_folder = path.strip_prefix(&working_path).unwrap_or(&path).display().to_string();
+                    }
+                });
+            }
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                merge = ui.button("➕ Merge").on_hover_text("Copy in alongside the files that are there").clicked();
+                replace = ui
+                    .button("♻ Replace")
+                    .on_hover_text("Move the files that are there into a folder of their own next to it first, named …_previous")
+                    .clicked();
+                let picked = pending.conflicts.iter().all(|(conflict, new_folder)| {
+                    !new_folder.trim().is_empty() && working_path.join(new_folder.trim()) != conflict.folder
+                });
+                redirect = ui.add_enabled(picked, egui::Button::new("📂 Use the new folders")).clicked();
+                cancel = ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape));
+            });
+        });
+        if merge || replace || redirect || cancel {
+            let pending = self.pending_export.take().expect("checked above");
+            if merge {
+                self.copy_now(pending.images, ctx);
+            } else if replace {
+                self.replace_and_copy(pending, ctx);
+            } else if redirect {
+                self.redirect_and_copy(pending, ctx);
+            }
+        }
+    }
+
+    /// Writes the progress file and empties the journal, which it now covers.
+    fn save_progress(&mut self) {
+        if let Some(working_path) = &self.working_path {
+            let progress = ProgressData {
+                kept_images: self.kept_images.iter().map(|p| p.to_string_lossy().to_string()).collect(),
+                discarded_count: self.discarded_images.len(),
+                image_counter: self.image_counter,
+                remaining_queue: self.queue_filter.full_queue(&self.image_paths).iter().map(|p| p.to_string_lossy().to_string()).collect(),
+                labels: self.labels.iter().map(|(p, label)| (p.to_string_lossy().to_string(), *label)).collect(),
+                ratings: self.ratings.iter().map(|(p, rating)| (p.to_string_lossy().to_string(), *rating)).collect(),
+                tags: self.tags.iter().map(|(p, tags)| (p.to_string_lossy().to_string(), tags.clone())).collect(),
+                tag_names: self.tag_names.clone(),
+                buckets: self.bucket_assignments.iter().map(|(p, bucket)| (p.to_string_lossy().to_string(), bucket.clone())).collect(),
+                skipped_count: self.skipped_count,
+                shuffle_seed: self.shuffle_seed,
+                time_spent: self.time_spent,
+                discarded_images: self.discarded_images.iter().map(|p| p.to_string_lossy().to_string()).collect(),
+                decisions: self.decisions.iter().map(|(p, decision)| (p.to_string_lossy().to_string(), *decision)).collect(),
+                extra_roots: self.extra_roots.iter().map(|p| p.to_string_lossy().to_string()).collect(),
+                moved: self
+                    .moved
+                    .iter()
+                    .map(|(p, files)| {
+                        let files = files.iter().map(|(from, to)| (from.to_string_lossy().to_string(), to.to_string_lossy().to_string())).collect();
+                        (p.to_string_lossy().to_string(), files)
+                    })
+                    .collect(),
+            };
+            let working_path = std::path::PathBuf::from(working_path);
+            let file_path = working_path.join(PROGRESS_FILE);
+            let temporary_path = file_path.with_extension("json.tmp");
+            let backup_path = file_path.with_extension("json.bak");
+            let backup = self.settings.backup_progress;
+            // Written aside and renamed over, so a crash never leaves half a progress file
+            let written = serde_json::to_string_pretty(&progress)
+                .map_err(std::io::Error::other)
+                .and_then(|json| std::fs::write(&temporary_path, json))
+                .and_then(|()| {
+                    if backup && file_path.exists() {
+                        std::fs::rename(&file_path, &backup_path)?;
+                    }
+                    std::fs::rename(&temporary_path, &file_path)
+                });
+            match written {
+                Ok(()) => journal::clear(&working_path),
+                Err(e) => {
+                    log::warn!("Could not save progress to {}: {}", file_path.display(), e);
+                    return; // Keep the journal, it is all there is
+                },
+            }
+        }
+        self.unsaved_since = None;
+        self.journal_length = 0;
+    }
+
+    /// Reads the progress file, or the backup of the save before when it is unreadable,
+    /// e.g. cut short by a full disk.
+    fn read_progress(&mut self, file_path: &std::path::Path) -> Option<ProgressData> {
+        let read = |path: &std::path::Path| -> std::io::Result<ProgressData> {
+            let json = std::fs::read_to_string(path)?;
+            serde_json::from_str(&json).map_err(std::io::Error::other)
+        };
+        let error = match read(file_path) {
+            Ok(progress) => return Some(progress),
+            Err(e) => e,
+        };
+        let message = match read(&file_path.with_extension("json.bak")) {
+            Ok(progress) => {
+                let message = format!("{} could not be read ({}), so the backup of the save before it was loaded", PROGRESS_FILE, error);
+                log::warn!("{}", message);
+                self.status_message = Some(message);
+                return Some(progress);
+            },
+            Err(_) if error.kind() == std::io::ErrorKind::NotFound => return None, // A new folder
+            Err(_) => format!("{} could not be read and there is no usable backup: {}", PROGRESS_FILE, error),
+        };
+        log::warn!("{}", message);
+        self.status_message = Some(message);
+        None
+    }
+
+    /// Returns true for a progress file from before decisions were kept per image, whose
+    /// queue new images should not be added to.
+    fn load_progress(&mut self, working_path: &std::path::Path) -> bool {
+        let file_path = working_path.join(PROGRESS_FILE);
+        if let Some(progress) = self.read_progress(&file_path) {
+            self.kept_images = progress.kept_images.iter().map(std::path::PathBuf::from).collect();
+            self.image_counter = progress.image_counter;
+            self.image_paths = progress.remaining_queue.iter().map(std::path::PathBuf::from).collect();
+            self.labels = progress.labels.into_iter().map(|(p, label)| (std::path::PathBuf::from(p), label)).collect();
+            self.ratings = progress.ratings.into_iter().map(|(p, rating)| (std::path::PathBuf::from(p), rating)).collect();
+            self.tags = progress.tags.into_iter().map(|(p, tags)| (std::path::PathBuf::from(p), tags)).collect();
+            self.tag_names = progress.tag_names;
+            self.bucket_assignments = progress.buckets.into_iter().map(|(p, bucket)| (std::path::PathBuf::from(p), bucket)).collect();
+            self.skipped_count = progress.skipped_count;
+            self.shuffle_seed = progress.shuffle_seed;
+            self.time_spent = progress.time_spent;
+            let mut used_tags: Vec<String> = self.tags.values().flatten().filter(|tag| !self.tag_names.contains(tag)).cloned().collect();
+            used_tags.sort();
+            used_tags.dedup();
+            self.tag_names.extend(used_tags);
+            self.decisions = progress.decisions.into_iter().map(|(p, decision)| (std::path::PathBuf::from(p), decision)).collect();
+            self.discarded_images = if progress.discarded_images.is_empty() {
+                // Written before the paths were; the decisions still know them
+                let mut discarded: Vec<_> = self.decisions.iter()
+                    .filter(|(_, decision)| **decision == Decision::Discard)
+                    .map(|(p, _)| p.clone())
+                    .collect();
+                discarded.sort();
+                discarded
+            } else {
+                progress.discarded_images.iter().map(std::path::PathBuf::from).collect()
+            };
+            self.moved = progress
+                .moved
+                .into_iter()
+                .map(|(p, files)| {
+                    // A move saved while under way that never got to a file left it where it was
+                    let files: Vec<_> = files
+                        .into_iter()
+                        .map(|(from, to)| (std::path::PathBuf::from(from), std::path::PathBuf::from(to)))
+                        .filter(|(from, to)| to.exists() || !from.exists())
+                        .collect();
+                    (std::path::PathBuf::from(p), files)
+                })
+                .filter(|(_, files)| !files.is_empty())
+                .collect();
+            // The queue already holds their images; the scan fills in the tree for counts and resets
+            for root in progress.extra_roots.iter().map(std::path::PathBuf::from).filter(|root| root.is_dir()) {
+                self.attach_root(&root);
+            }
+            return self.decisions.is_empty() && (!self.kept_images.is_empty() || progress.discarded_count > 0);
+        }
+        false
+    }
+}
+
+impl eframe::App for MyApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll_copy(ctx);
+        self.poll_scan(ctx);
+        self.poll_moves(ctx);
+        self.autosave(ctx);
+        let title = self.window_title();
+        if title != self.window_title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.window_title = title;
+        }
+        self.track_window(ctx);
+        if self.working_path.is_some() && !self.image_paths.is_empty() {
+            self.time_spent += (ctx.input(|i| i.unstable_dt) as f64).min(MAX_COUNTED_PAUSE);
+        }
+        self.show_settings_window(ctx);
+        about::show_about_window(ctx, &mut self.show_about);
+        self.show_tag_window(ctx);
+        self.show_sequence_gap_window(ctx);
+        self.show_quota_window(ctx);
+        self.update_quota();
+        self.show_blur_window(ctx);
+        self.show_faces_window(ctx);
+        self.update_face_search();
+        self.show_discard_confirmation(ctx);
+        self.show_recovery_prompt(ctx);
+        self.show_reset_confirmation(ctx);
+        self.show_export_confirmation(ctx);
+        self.show_toast_area(ctx);
+        self.paint_decision_flash(ctx);
+        self.show_folder_sidebar(ctx);
+        self.show_info_sidebar(ctx);
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Select working folder").clicked()
+                    && let Some(path) = rfd::FileDialog::new().pick_folder()
+                {
+                    self.open_working_folder(path, ctx);
+                }
+                if ui
+                    .button("📄 Open list")
+                    .on_hover_text("Review the images listed in a text file, one path per line, instead of a folder")
+                    .clicked()
+                    && let Some(file) = rfd::FileDialog::new().add_filter("Text", &["txt", "csv", "lst"]).pick_file()
+                {
+                    self.open_image_list(file, ctx);
+                }
+                if ui
+                    .add_enabled(self.working_path.is_some() && self.scan.is_none(), egui::Button::new("➕ Add folder"))
+                    .on_hover_text("Review another folder together with the current one")
+                    .clicked()
+                    && let Some(path) = rfd::FileDialog::new().pick_folder()
+                {
+                    self.add_root_folder(path, ctx);
+                }
+                if ui
+                    .add_enabled(self.working_path.is_some() && self.scan.is_none(), egui::Button::new("📋 Import selection"))
+                    .on_hover_text("Keep the images listed in a text file, one file name or relative path per line")
+                    .clicked()
+                    && let Some(file) = rfd::FileDialog::new().add_filter("Text", &["txt", "csv", "lst"]).pick_file()
+                {
+                    self.import_selection(file);
+                }
+                if ui
+                    .add_enabled(
+                        self.working_path.is_some() && self.scan.is_none() && self.list_file.is_none(),
+                        egui::Button::new("🔍 Rescan for new files"),
+                    )
+                    .on_disabled_hover_text("Open the list again to pick up changes to it")
+                    .clicked()
+                {
+                    self.rescan_for_new_files(ctx);
+                }
+                if ui
+                    .add_enabled(self.image_paths.len() > 1, egui::Button::new("⇅ Reverse queue"))
+                    .on_hover_text("Review the undecided images in the opposite order")
+                    .clicked()
+                {
+                    self.reverse_queue();
+                }
+                if ui
+                    .add_enabled(self.image_paths.len() > 1, egui::Button::new("🔀 Shuffle queue"))
+                    .on_hover_text("Review the undecided images in random order, so earlier ones do not set the bar for later ones")
+                    .clicked()
+                {
+                    self.shuffle_queue(self.settings.shuffle_seed);
+                }
+                if ui.add_enabled(self.copy_job.is_none(), egui::Button::new("📁 Copy Kept Images")).clicked() {
+                    self.run_copy(self.decided_images(), ctx);
+                }
+                if ui.selectable_label(self.settings.show_folder_tree, "📂 Folders").clicked() {
+                    self.settings.show_folder_tree = !self.settings.show_folder_tree;
+                    self.settings.save();
+                }
+                if ui
+                    .selectable_label(self.settings.show_info_pane, "ℹ Info")
+                    .on_hover_text("Details of the current image in a panel beside it; drag its edge to resize it")
+                    .clicked()
+                {
+                    self.settings.show_info_pane = !self.settings.show_info_pane;
+                    self.settings.save();
+                }
+                if ui
+                    .selectable_label(self.show_grid, "▦ Grid")
+                    .on_hover_text("Thumbnails of the queue; select several and sort them with one key")
+                    .clicked()
+                {
+                    self.show_grid = !self.show_grid;
+                    self.grid_selection.clear();
+                }
+                if ui.selectable_label(self.show_quota, "⚖ Quota").on_hover_text("Keep the best share of each folder").clicked() {
+                    self.show_quota = !self.show_quota;
+                }
+                if ui.selectable_label(self.show_blur_pass, "🔅 Blurry").on_hover_text("Discard everything below a sharpness threshold").clicked() {
+                    self.show_blur_pass = !self.show_blur_pass;
+                }
+                if ui.selectable_label(self.show_faces, "👤 Faces").on_hover_text("Keep the images a person is in").clicked() {
+                    self.show_faces = !self.show_faces;
+                }
+                if ui.selectable_label(self.show_tags, "🏷 Tags").clicked() {
+                    self.show_tags = !self.show_tags;
+                }
+                if ui
+                    .add_enabled(self.images.is_some() && self.scan.is_none(), egui::Button::new("🔢 Gaps"))
+                    .on_hover_text("Look for frame numbers missing from the file names in each folder, e.g. IMG_0042 between IMG_0041 and IMG_0043")
+                    .clicked()
+                {
+                    self.check_sequence_gaps();
+                }
+                if ui.button("⚙ Settings").clicked() {
+                    self.show_settings = !self.show_settings;
+                }
+                if ui.button("ℹ About").clicked() {
+                    self.show_about = !self.show_about;
                 }
             });
 
@@ -277,182 +4005,345 @@ impl eframe::App for MyApp {
                     ui.label("Picked folder:");
                     ui.monospace(picked_path.to_string_lossy().as_ref());
                 });
+                for root in &self.extra_roots {
+                    ui.horizontal(|ui| {
+                        ui.label("Also reviewing:");
+                        ui.monospace(root.to_string_lossy().as_ref());
+                    });
+                }
+                if let Some(message) = &self.status_message {
+                    ui.label(message);
+                }
+                self.show_copy_report(ui);
+                self.show_selection_report(ui);
+                self.show_raw_only_report(ui);
+                self.show_provisional_order(ui);
+                self.show_break_nudge(ui);
+                self.show_folder_bucket_offer(ui, ctx);
                 // Display information about found images
                 if let Some(images_node) = &self.images {
                     let total_images = images_node.count_images();
-                    ui.label(format!("Total images found: {} (Current queue: {})", total_images, self.image_paths.len()));
+                    ui.horizontal(|ui| {
+                        if let Some(running) = &self.scan {
+                            ui.spinner();
+                            ui.label(if running.shuffle_when_done { "Scanning; the shuffled review starts once all images are found…" } else { "Scanning…" });
+                        }
+                        ui.label(format!("Total images found: {} (Current queue: {})", total_images, self.image_paths.len()));
+                    });
+                    self.show_queue_filter(ui);
                     // Show 'All images processed!' block if queue is empty
-                    if self.image_paths.is_empty() {
-                        self.texture = None; // Ensure no image is displayed
-                        ui.label("🎉 All images processed!");
-                        ui.horizontal(|ui| {
-                            ui.label(format!("Kept: {}", self.kept_images.len()));
-                            ui.label(format!("Discarded: {}", self.discarded_count));
-                        });
-                        ui.add_space(10.0);
-                        ui.horizontal(|ui| {
-                            if ui.button("📁 Copy Kept Images").clicked() {
-                                match self.copy_kept_images() {
-                                    Ok(()) => {
-                                        if let Some(working_path) = &self.working_path {
-                                            let output_folder = std::path::PathBuf::from(working_path).join("kept_images");
-                                            ui.label(format!("✅ {} images copied to: {}", self.kept_images.len(), output_folder.display()));
-                                        }
-                                    },
-                                    Err(e) => {
-                                        ui.label(format!("❌ Error copying images: {}", e));
-                                    }
-                                }
-                            }
-                            if ui.button("🔄 Reset").clicked() {
-                                if let (Some(images_node), Some(working_path)) = (&self.images, &self.working_path) {
-                                    let path = std::path::PathBuf::from(working_path);
-                                    self.image_paths = images_node.get_images_depth_first_current_priority(&path);
-                                }
-                                self.kept_images.clear();
-                                self.discarded_count = 0;
-                                self.image_counter = 0;
-                            }
-                        });
+                    if self.image_paths.is_empty() && self.scan.is_none() && !self.queue_filter.is_active() {
+                        self.show_completion_screen(ui);
                     }
                 }
             }
 
             // Image viewer section
-            if !self.image_paths.is_empty() {
+            if self.show_grid && !self.image_paths.is_empty() {
+                self.update_preload(ctx);
+                self.show_queue_grid(ui);
+            } else if !self.image_paths.is_empty() {
                 
                 // Handle keyboard input
-                let mut should_advance = false;
-                let mut keep_image = false;
+                let mut sort_into = None; // Index of the bucket to sort the current image into
+                let mut resort_into = None; // Index of the bucket to move the last decided image to
+                let mut skip_image = false;
+                let mut next_folder = false;
+                let mut skip_rest_of_folder = false;
+                let mut label_key = None;
+                let mut rating_key = None;
+                let mut toggle_enhanced = false;
+                let mut pin_reference = false;
+                let mut toggle_compare = false;
+                let mut reveal_current = false;
+                let mut copy_path = false;
+                let mut next_guide = false;
+                let mut next_fit_mode = false;
+                let mut face_zoom = false;
+                let mut keep_sharpest = false;
                 
+                // Keys typed into a text field (tags, export folders) are text, not decisions,
+                // and the confirmation dialog has the keyboard while it is open
+                let hotkeys_enabled = self.pending_discard.is_none() && self.pending_recovery.is_none() && !self.pending_reset && self.pending_export.is_none() && !ctx.wants_keyboard_input();
                 ctx.input(|i| {
-                    if i.key_pressed(egui::Key::ArrowRight) {
-                        // Keep current image and move to next
-                        if !self.image_paths.is_empty() {
-                            should_advance = true;
-                            keep_image = true;
+                    if !hotkeys_enabled {
+                        return;
+                    }
+                    // Sort the current image into the bucket of the key and move to the next;
+                    // with Shift, sort the last decided one again instead
+                    let bucket_key = self
+                        .settings
+                        .buckets
+                        .iter()
+                        .position(|bucket| bucket.key().is_some_and(|key| i.key_pressed(key)));
+                    if i.modifiers.shift {
+                        resort_into = bucket_key;
+                    } else {
+                        sort_into = bucket_key;
+                    }
+                    if i.key_pressed(egui::Key::ArrowDown) {
+                        // Defer current image to the end of the queue
+                        skip_image = true;
+                    }
+                    // Page Down goes on to the next folder, with Shift discarding the rest of this one
+                    if i.key_pressed(egui::Key::PageDown) {
+                        if i.modifiers.shift {
+                            skip_rest_of_folder = true;
+                        } else {
+                            next_folder = true;
                         }
                     }
-                    if i.key_pressed(egui::Key::ArrowLeft) {
-                        // Discard current image and move to next
-                        if !self.image_paths.is_empty() {
-                            should_advance = true;
-                            keep_image = false;
+                    for event in &i.events {
+                        if let egui::Event::Key { key, pressed: true, repeat: false, modifiers, .. } = event
+                            && modifiers.is_none()
+                        {
+                            label_key = label_key.or(ColorLabel::from_key(*key));
+                            rating_key = rating_key.or(metadata::rating_from_key(*key));
+                            toggle_enhanced |= *key == egui::Key::E;
+                            pin_reference |= *key == egui::Key::P;
+                            toggle_compare |= *key == egui::Key::R;
+                            reveal_current |= *key == egui::Key::F;
+                            next_guide |= *key == egui::Key::G;
+                            next_fit_mode |= *key == egui::Key::Z;
+                            face_zoom |= *key == egui::Key::H;
+                            keep_sharpest |= *key == egui::Key::B;
                         }
+                        // Ctrl/Cmd+C arrives as a copy event rather than a key press
+                        copy_path |= matches!(event, egui::Event::Copy);
                     }
                 });
 
+                // Pressing the key of the label an image already has clears it
+                if let (Some(label), Some(current)) = (label_key, self.image_paths.first().cloned()) {
+                    let label = if self.labels.get(&current) == Some(&label) { None } else { Some(label) };
+                    self.set_label(&current, label);
+                }
+                // Same for ratings: pressing the current star count again removes the rating
+                if let (Some(rating), Some(current)) = (rating_key, self.image_paths.first().cloned()) {
+                    let rating = if self.ratings.get(&current) == Some(&rating) { None } else { Some(rating) };
+                    self.set_rating(&current, rating);
+                }
+
+
 
+                self.update_preload(ctx);
+                self.update_burst();
+                let current_gps = self.image_paths.first().cloned().and_then(|path| self.exif_info(&path)?.gps);
+                let capture_badge = self
+                    .image_paths
+                    .first()
+                    .cloned()
+                    .filter(|_| self.settings.show_capture_badge)
+                    .and_then(|path| self.exif_info(&path)?.capture_settings.summary());
 
                 // Current image display
                 if !self.image_paths.is_empty() {
-                    let current_image_path = &self.image_paths[0];
+                    let current_image_path = self.image_paths[0].clone();
+                    self.zoom.follow(&current_image_path, self.settings.keep_fit_mode);
+                    self.show_enhanced ^= toggle_enhanced;
+                    if pin_reference {
+                        self.toggle_reference();
+                    }
+                    if next_guide {
+                        self.guide = self.guide.next();
+                    }
+                    if next_fit_mode {
+                        self.cycle_fit_mode();
+                    }
+                    self.update_face_detection(ctx);
+                    if face_zoom {
+                        self.toggle_face_zoom(ctx);
+                    }
+                    self.show_reference ^= toggle_compare && self.reference.is_some();
                     
-                    // Progress bar - calculate based on total processed vs original total
-                    let total_processed = self.kept_images.len() + self.discarded_count;
-                    let original_total = total_processed + self.image_paths.len();
-                    let progress = if original_total > 0 { 
-                        total_processed as f32 / original_total as f32 
+                    // Progress bar - images with a final decision over all distinct images found
+                    let (decided, total) = self.progress();
+                    let progress = if total > 0 { 
+                        decided as f32 / total as f32 
                     } else { 
                         0.0 
                     };
-                    ui.add(egui::ProgressBar::new(progress).text(format!("{} / {}", total_processed, original_total)));
+                    ui.add(egui::ProgressBar::new(progress).text(format!("{} / {}", decided, total)));
                     
                     ui.horizontal(|ui| {
                         ui.label("📷 Current image:");
                         ui.monospace(current_image_path.file_name().unwrap_or_default().to_string_lossy());
+                        if let Some(rating) = self.ratings.get(&current_image_path) {
+                            ui.label(metadata::stars(*rating));
+                        }
+                        if let Some(label) = self.labels.get(&current_image_path) {
+                            metadata::label_dot(ui, *label);
+                        }
+                        ui.toggle_value(&mut self.show_enhanced, "✨ Auto-enhance (E)")
+                            .on_hover_text("Preview auto levels and white balance next to the original. Nothing is written to disk.");
+                        let is_reference = self.reference.as_ref() == Some(&current_image_path);
+                        if ui
+                            .selectable_label(is_reference, "📌 Pin as reference (P)")
+                            .on_hover_text("Compare every following image against this one")
+                            .clicked()
+                        {
+                            self.toggle_reference();
+                        }
+                        ui.add_enabled_ui(self.reference.is_some(), |ui| {
+                            ui.toggle_value(&mut self.show_reference, "Compare (R)");
+                        });
+                        if ui
+                            .toggle_value(&mut self.settings.show_capture_badge, "📷 Settings badge")
+                            .on_hover_text("Focal length, aperture, shutter speed and ISO in a corner of the image")
+                            .changed()
+                        {
+                            self.settings.save();
+                        }
+                        if ui
+                            .toggle_value(&mut self.settings.show_palette, "🎨 Palette")
+                            .on_hover_text("The main colors of the image in a corner of it, and along the bottom of every thumbnail in the grid")
+                            .changed()
+                        {
+                            self.settings.save();
+                        }
+                        let finding_faces = self.face_detection.as_ref().is_some_and(|detection| detection.path == current_image_path);
+                        if ui
+                            .add_enabled(faces::FACES_AVAILABLE && !finding_faces, egui::Button::new("👤 Face (H)"))
+                            .on_hover_text("Zoom to the largest face, e.g. to check the eyes; press again to see all of the image")
+                            .on_disabled_hover_text(if faces::FACES_AVAILABLE { "Looking for faces…" } else { "This build was made without the faces feature" })
+                            .clicked()
+                        {
+                            self.toggle_face_zoom(ctx);
+                        }
+                        if ui
+                            .button(format!("🔍 {} (Z)", self.zoom.mode.name()))
+                            .on_hover_text("Cycle between fitting the image in, filling the viewer and actual pixels")
+                            .clicked()
+                        {
+                            self.cycle_fit_mode();
+                        }
+                        if ui
+                            .toggle_value(&mut self.settings.keep_fit_mode, "📌")
+                            .on_hover_text("Keep the fit mode for the following images instead of going back to Fit")
+                            .changed()
+                        {
+                            self.settings.save();
+                        }
+                        if ui
+                            .toggle_value(&mut self.settings.show_minimap, "🗺 Minimap")
+                            .on_hover_text("While zoomed in, show where the part in view is. Scroll over the image to zoom, drag to pan, double-click to see all of it.")
+                            .changed()
+                        {
+                            self.settings.save();
+                        }
+                        if self.texture.as_ref().is_some_and(|texture| is_panorama(texture.size()))
+                            && ui
+                                .toggle_value(&mut self.settings.scroll_panoramas, "↔ Scroll panorama")
+                                .on_hover_text("Show the panorama at full height and scroll along it, instead of shrinking it to fit")
+                                .changed()
+                        {
+                            self.settings.save();
+                        }
+                        if ui.button(format!("📐 {} (G)", self.guide.name())).on_hover_text("Cycle composition guides").clicked() {
+                            self.guide = self.guide.next();
+                        }
+                        if ui.button("📂 Show in folder (F)").on_hover_text("Open the file manager with this file selected").clicked() {
+                            reveal_current = true;
+                        }
+                        if ui.button("📋 Copy path (Ctrl+C)").clicked() {
+                            copy_path = true;
+                        }
                     });
 
+                    self.show_tag_picker(ui, &current_image_path);
+                    self.show_bulk_metadata(ui, &current_image_path);
+
+                    if let Some(gps) = current_gps.filter(|_| !self.settings.show_info_pane) {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("📍 {}", gps));
+                            if ui.button("🗺 Open map").clicked()
+                                && let Err(e) = open::that(gps.map_url())
+                            {
+                                log::warn!("Could not open map for {}: {}", gps, e);
+                            }
+                        });
+                    }
+
                     // Show statistics
                     ui.horizontal(|ui| {
                         ui.label(format!("✅ Kept: {}", self.kept_images.len()));
                         ui.separator();
-                        ui.label(format!("❌ Discarded: {}", self.discarded_count));
+                        ui.label(format!("❌ Discarded: {}", self.discarded_images.len()));
                         ui.separator();
                         ui.label(format!("📁 Remaining: {}", self.image_paths.len()));
                     });
 
+                    if reveal_current {
+                        reveal::reveal_file(&pages::file_of(&current_image_path));
+                    }
+                    if copy_path {
+                        let path = std::path::absolute(&current_image_path).unwrap_or_else(|_| current_image_path.clone());
+                        ctx.copy_text(path.display().to_string());
+                        self.show_toast(format!("📋 Copied {}", path.display()));
+                    }
+
+                    if let Some(path) = self.show_decision_trail(ui) {
+                        self.stretch.record_undo(std::time::Instant::now());
+                        self.undecide(&path);
+                        ctx.request_repaint();
+                    }
+                    if let Some(path) = self.show_kept_strip(ui) {
+                        self.unkeep(&path);
+                        ctx.request_repaint();
+                    }
+                    let mut burst_choice = self.show_burst_panel(ui);
+                    if keep_sharpest && self.burst.as_ref().is_some_and(|burst| burst.preselected) {
+                        burst_choice = Some(BurstChoice::KeepSharpest);
+                    }
+
                     ui.separator();
 
-                    // Get image bytes (load on demand)
-                    let current_image_path_clone = current_image_path.clone();
+                    // Turn the decoded image into a texture once the pool has delivered it
                     let bytes_uri = format!("bytes://{}/{}", self.image_counter, current_image_path.display());
-                    let mut create_texture = false;
-                    if self.texture.is_none() {
-                        create_texture = true;
-                    }
-                    if create_texture {
-                        let extension = current_image_path_clone.extension().and_then(|e| e.to_str()).map(|s| s.to_lowercase());
-                        let image_bytes = match std::fs::read(&current_image_path_clone) {
-                            Ok(bytes) => Some(bytes),
-                            Err(_) => None
-                        };
-                        if let Some(bytes) = &image_bytes {
-                            let color_image = if let Some(ext) = &extension {
-                                if ext == "jpg" || ext == "jpeg" {
-                                    // Use jpeg-decoder for JPEGs
-                                    let mut decoder = jpeg_decoder::Decoder::new(std::io::Cursor::new(bytes));
-                                    match decoder.decode() {
-                                        Ok(decoded) => {
-                                            if let Some(info) = decoder.info() {
-                                                let width = info.width as usize;
-                                                let height = info.height as usize;
-                                                let pixels: Vec<egui::Color32> = decoded
-                                                    .chunks(3)
-                                                    .map(|chunk| egui::Color32::from_rgb(chunk[0], chunk[1], chunk[2]))
-                                                    .collect();
-                                                egui::ColorImage {
-                                                    size: [width, height],
-                                                    source_size: egui::Vec2::new(width as f32, height as f32),
-                                                    pixels,
-                                                }
-                                            } else {
-                                                egui::ColorImage {
-                                                    size: [1, 1],
-                                                    source_size: egui::Vec2::new(1.0, 1.0),
-                                                    pixels: vec![egui::Color32::BLACK],
-                                                }
-                                            }
-                                        },
-                                        Err(_) => egui::ColorImage {
-                                            size: [1, 1],
-                                            source_size: egui::Vec2::new(1.0, 1.0),
-                                            pixels: vec![egui::Color32::BLACK],
-                                        },
-                                    }
-                                } else {
-                                    // Use image crate for other formats
-                                    match image::load_from_memory(bytes) {
-                                        Ok(img) => {
-                                            let rgba = img.to_rgba8();
-                                            let size = [rgba.width() as usize, rgba.height() as usize];
-                                            let pixels = rgba.into_raw();
-                                            egui::ColorImage::from_rgba_unmultiplied(size, &pixels)
-                                        },
-                                        Err(_) => egui::ColorImage {
-                                            size: [1, 1],
-                                            source_size: egui::Vec2::new(1.0, 1.0),
-                                            pixels: vec![egui::Color32::BLACK],
-                                        },
-                                    }
-                                }
-                            } else {
-                                egui::ColorImage {
-                                    size: [1, 1],
-                                    source_size: egui::Vec2::new(1.0, 1.0),
-                                    pixels: vec![egui::Color32::BLACK],
-                                }
-                            };
-                            self.texture = Some(ctx.load_texture(bytes_uri.clone(), color_image, egui::TextureOptions::default()));
-                        } else {
-                            self.texture = None;
-                        }
+                    if self.texture.is_none()
+                        && let Some(color_image) = self.preloaded.remove(&current_image_path)
+                    {
+                        let color_image = std::sync::Arc::new(color_image);
+                        self.texture = Some(ctx.load_texture(bytes_uri.clone(), color_image.clone(), self.settings.texture_filter.texture_options()));
+                        self.current_image = Some(color_image);
+                        self.enhanced_texture = None;
+                    }
+                    if self.show_enhanced
+                        && self.texture.is_some()
+                        && self.enhanced_texture.is_none()
+                        && let Some(color_image) = &self.current_image
+                    {
+                        let enhanced = enhance::auto_enhance(color_image);
+                        self.enhanced_texture = Some(ctx.load_texture(format!("{}#enhanced", bytes_uri), enhanced, self.settings.texture_filter.texture_options()));
+                    }
+                    if self.settings.show_palette
+                        && self.texture.is_some()
+                        && !self.palettes.contains_key(&current_image_path)
+                        && let Some(color_image) = &self.current_image
+                    {
+                        self.palettes.insert(current_image_path.clone(), palette::dominant_colors(color_image, palette::PALETTE_SIZE));
+                    }
+                    let swatches = self.palettes.get(&current_image_path).filter(|_| self.settings.show_palette).cloned();
+                    if self.reference_texture.is_none()
+                        && let Some(reference) = &self.reference
+                        && let Some(color_image) = self.preloaded.remove(reference)
+                    {
+                        let uri = format!("bytes://reference/{}", reference.display());
+                        self.reference_texture = Some(ctx.load_texture(uri, color_image, self.settings.texture_filter.texture_options()));
                     }
 
-                    // Button click state (also used for keyboard input)
-                    // let mut should_advance = false;  // Already declared above for keyboard
-                    // let mut keep_image = false;      // Already declared above for keyboard
-                    
+                    // Keeping buckets go left of Skip, discarding ones right of it
+                    let (keeping, discarding): (Vec<_>, Vec<_>) = self
+                        .settings
+                        .buckets
+                        .iter()
+                        .enumerate()
+                        .map(|(index, bucket)| {
+                            let icon = if bucket.keeps { "👍" } else { "👎" };
+                            (index, bucket.keeps, format!("{} {}", icon, bucket.name))
+                        })
+                        .partition(|(_, keeps, _)| *keeps);
+
                     // Use bottom-up layout to reserve space for buttons first
                     ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
                         // First place the buttons at the bottom
@@ -460,20 +4351,40 @@ impl eframe::App for MyApp {
                             egui::Vec2::new(ui.available_width(), 70.0),
                             egui::Layout::left_to_right(egui::Align::Center),
                             |ui| {
-                                let total_button_width = 150.0 + 30.0 + 150.0; // button + space + button
+                                let bucket_count = (keeping.len() + discarding.len()) as f32;
+                                let total_button_width = bucket_count * (150.0 + 30.0) + 100.0; // buttons + spaces
                                 let left_space = (ui.available_width() - total_button_width) / 2.0;
                                 ui.add_space(left_space);
                                 
-                                if ui.add_sized([150.0, 60.0], egui::Button::new("👍 Keep")).clicked() {
-                                    should_advance = true;
-                                    keep_image = true;
+                                for (index, _, label) in &keeping {
+                                    if ui.add_sized([150.0, 60.0], egui::Button::new(label)).clicked() {
+                                        sort_into = Some(*index);
+                                    }
+                                    ui.add_space(30.0); // Space between buttons
                                 }
                                 
-                                ui.add_space(30.0); // Space between buttons
+                                let skip = ui
+                                    .add_sized([100.0, 60.0], egui::Button::new("⏭ Skip"))
+                                    .on_hover_text("Right-click to go on to the next folder");
+                                if skip.clicked() {
+                                    skip_image = true;
+                                }
+                                skip.context_menu(|ui| {
+                                    if ui.button("⏩ Next folder (Page Down)").clicked() {
+                                        next_folder = true;
+                                        ui.close();
+                                    }
+                                    if ui.button("⏬ Discard the rest of this folder (Shift+Page Down)").clicked() {
+                                        skip_rest_of_folder = true;
+                                        ui.close();
+                                    }
+                                });
                                 
-                                if ui.add_sized([150.0, 60.0], egui::Button::new("👎 Discard")).clicked() {
-                                    should_advance = true;
-                                    keep_image = false;
+                                for (index, _, label) in &discarding {
+                                    ui.add_space(30.0);
+                                    if ui.add_sized([150.0, 60.0], egui::Button::new(label)).clicked() {
+                                        sort_into = Some(*index);
+                                    }
                                 }
                             },
                         );
@@ -482,14 +4393,60 @@ impl eframe::App for MyApp {
                         
                         // Now use all remaining space for the image
                         ui.vertical_centered(|ui| {
-                            if let Some(texture) = &self.texture {
-                                ui.add(
+                            // Reference and enhanced copies go next to the image, each in its own column
+                            let side_panes: Vec<(&egui::TextureHandle, &str)> = [
+                                (self.show_reference, self.reference_texture.as_ref(), "📌 Reference"),
+                                (self.show_enhanced, self.enhanced_texture.as_ref(), "Auto-enhanced"),
+                            ]
+                            .into_iter()
+                            .filter_map(|(shown, texture, caption)| texture.filter(|_| shown).map(|texture| (texture, caption)))
+                            .collect();
+                            if let Some(texture) = self.texture.as_ref().filter(|_| !side_panes.is_empty()) {
+                                let panes: Vec<(&egui::TextureHandle, &str)> = std::iter::once((texture, "Current")).chain(side_panes).collect();
+                                ui.columns(panes.len(), |columns| {
+                                    for (index, (column, (texture, caption))) in columns.iter_mut().zip(panes).enumerate() {
+                                        column.vertical_centered(|ui| {
+                                            ui.label(caption);
+                                            let response = ui.add(egui::Image::new(texture).fit_to_exact_size(ui.available_size()));
+                                            if index == 0 {
+                                                self.guide.paint(ui.painter(), response.rect);
+                                                paint_capture_badge(ui, response.rect, capture_badge.as_deref());
+                                                palette::paint_swatches(ui, response.rect, swatches.as_deref());
+                                            }
+                                        });
+                                    }
+                                });
+                            } else if let Some(texture) = self.texture.as_ref().filter(|texture| self.settings.scroll_panoramas && is_panorama(texture.size())) {
+                                // Shrunk to fit, a panorama is a thin strip; at full height it is worth scrolling through
+                                let [width, height] = texture.size();
+                                let display_height = ui.available_height() - 2.0 * ui.spacing().scroll.bar_width;
+                                let display_size = egui::vec2(display_height * width as f32 / height as f32, display_height);
+                                egui::ScrollArea::horizontal()
+                                    .id_salt(("panorama", &current_image_path))
+                                    .show(ui, |ui| {
+                                        let response = ui.add(egui::Image::new(texture).fit_to_exact_size(display_size));
+                                        self.guide.paint(ui.painter(), response.rect);
+                                        paint_capture_badge(ui, response.rect, capture_badge.as_deref());
+                                        palette::paint_swatches(ui, response.rect, swatches.as_deref());
+                                    });
+                            } else if let Some(texture) = &self.texture {
+                                let available = egui::Vec2::new(ui.available_width() - 20.0, ui.available_height());
+                                let size = self.zoom.layout(available, texture.size(), ctx.pixels_per_point());
+                                let response = ui.add(
                                     egui::Image::new(texture)
-                                        .fit_to_exact_size(egui::Vec2::new(
-                                            ui.available_width() - 20.0,
-                                            ui.available_height()
-                                        ))
+                                        .uv(self.zoom.uv_rect())
+                                        .fit_to_exact_size(size)
+                                        .sense(egui::Sense::click_and_drag())
                                 );
+                                self.zoom.handle_input(ui, &response);
+                                self.guide.paint(ui.painter(), response.rect);
+                                paint_capture_badge(ui, response.rect, capture_badge.as_deref());
+                                palette::paint_swatches(ui, response.rect, swatches.as_deref());
+                                if self.settings.show_minimap {
+                                    self.zoom.paint_minimap(ui, response.rect, texture);
+                                }
+                            } else if let Some(error) = self.decode_errors.get(&current_image_path) {
+                                ui.label(format!("⚠ This image could not be shown: {}", error));
                             } else {
                                 ui.horizontal(|ui| {
                                     ui.spinner();
@@ -500,55 +4457,952 @@ impl eframe::App for MyApp {
                     });
                     
                     // Handle the action after the UI
-                    if should_advance {
-                        if keep_image {
-                            self.kept_images.push(current_image_path_clone.clone());
-                        } else {
-                            self.discarded_count += 1;
+                    let discards = sort_into.and_then(|index| self.settings.buckets.get(index)).is_some_and(|bucket| !bucket.keeps);
+                    if let Some(bucket) = sort_into
+                        && discards
+                        && (self.settings.review_discards || self.needs_discard_confirmation(&current_image_path))
+                    {
+                        self.pending_discard = Some((current_image_path.clone(), bucket));
+                        self.review_kept = self.last_kept_in_folder(&current_image_path);
+                    } else if let Some(bucket) = sort_into {
+                        self.decide_current(bucket);
+                        ctx.request_repaint();
+                    } else if let Some(bucket) = resort_into {
+                        self.resort_last_decided(bucket);
+                        ctx.request_repaint();
+                    } else if skip_image {
+                        self.skip_current();
+                        ctx.request_repaint();
+                    } else if next_folder {
+                        self.next_folder();
+                        ctx.request_repaint();
+                    } else if skip_rest_of_folder {
+                        self.skip_rest_of_folder();
+                        ctx.request_repaint();
+                    } else if let Some(choice) = burst_choice {
+                        match choice {
+                            BurstChoice::Apply => self.apply_burst(),
+                            BurstChoice::KeepSharpest => self.keep_sharpest_of_burst(),
+                            BurstChoice::ReviewIndividually => self.dismiss_burst(),
                         }
-                        self.image_paths.remove(0);
-                        // Drop the previous texture
-                        self.texture = None;
-                        self.image_counter += 1;
-                        self.save_progress(); // Save progress after each action
                         ctx.request_repaint();
                     }
 
                 } else {
                     // All images processed
-                    self.texture = None; // Ensure no image is displayed
-                    ui.label("🎉 All images processed!");
-                    ui.horizontal(|ui| {
-                        ui.label(format!("Kept: {}", self.kept_images.len()));
-                        ui.label(format!("Discarded: {}", self.discarded_count));
-                    });
-                    ui.add_space(10.0);
-                    ui.horizontal(|ui| {
-                        if ui.button("📁 Copy Kept Images").clicked() {
-                            match self.copy_kept_images() {
-                                Ok(()) => {
-                                    if let Some(working_path) = &self.working_path {
-                                        let output_folder = std::path::PathBuf::from(working_path).join("kept_images");
-                                        ui.label(format!("✅ {} images copied to: {}", self.kept_images.len(), output_folder.display()));
-                                    }
-                                },
-                                Err(e) => {
-                                    ui.label(format!("❌ Error copying images: {}", e));
-                                }
-                            }
-                        }
-                        if ui.button("🔄 Reset").clicked() {
-                            if let (Some(images_node), Some(working_path)) = (&self.images, &self.working_path) {
-                                let path = std::path::PathBuf::from(working_path);
-                                self.image_paths = images_node.get_images_depth_first_current_priority(&path);
-                            }
-                            self.kept_images.clear();
-                            self.discarded_count = 0;
-                            self.image_counter = 0;
-                        }
-                    });
+                    self.show_completion_screen(ui);
                 }
             }
         });
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.finish_moves(None);
+        if let Some((job, _)) = &mut self.copy_job {
+            job.wait(); // Rather than leave a half-written copy behind
+        }
+        if self.unsaved_since.is_some() {
+            self.save_progress();
+        }
+        if self.settings.startup_window == settings::StartupWindow::Remember
+            && let Some((size, maximized)) = self.window
+        {
+            self.settings.window_size = size.into();
+            self.settings.window_maximized = maximized;
+            self.settings.save();
+        }
+        if let Some(mut pool) = self.decode_pool.take() {
+            pool.shutdown();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TempDir, FIXTURE};
+
+    // Indexes of the default buckets
+    const KEEP: usize = 0;
+    const DISCARD: usize = 1;
+
+    fn app_with_images(names: &[&str]) -> MyApp {
+        let root_node = FileSysNode {
+            images: names.iter().map(OsString::from).collect(),
+            ..FileSysNode::default()
+        };
+        MyApp {
+            image_paths: root_node.get_images_depth_first_current_priority(std::path::Path::new("shoot")),
+            images: Some(Box::new(root_node)),
+            ..MyApp::default()
+        }
+    }
+
+    /// An app with `root` as its working folder, reviewing `names` in it.
+    fn app_in(root: &std::path::Path, names: &[&str]) -> MyApp {
+        MyApp {
+            working_path: Some(root.as_os_str().to_os_string()),
+            image_paths: names.iter().map(|name| root.join(name)).collect(),
+            ..MyApp::default()
+        }
+    }
+
+    fn front(app: &MyApp) -> &str {
+        app.image_paths[0].file_name().unwrap().to_str().unwrap()
+    }
+
+    /// Polls the scan under way until it is done.
+    fn finish_scan(app: &mut MyApp, ctx: &egui::Context) {
+        let started = std::time::Instant::now();
+        while app.scan.is_some() {
+            assert!(started.elapsed() < std::time::Duration::from_secs(10), "the scan ends");
+            app.poll_scan(ctx);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+    }
+
+    /// Waits for the copy under way and takes in its report.
+    fn finish_copy(app: &mut MyApp, ctx: &egui::Context) {
+        if let Some((job, _)) = &mut app.copy_job {
+            job.wait();
+        }
+        app.poll_copy(ctx);
+    }
+
+    #[test]
+    fn skipping_does_not_count_as_progress() {
+        let mut app = app_with_images(&["a.jpg", "b.jpg", "c.jpg"]);
+        app.skip_current();
+        app.skip_current();
+        assert_eq!(front(&app), "c.jpg");
+        assert_eq!(app.image_paths.len(), 3);
+        assert_eq!(app.progress(), (0, 3));
+    }
+
+    #[test]
+    fn skip_then_decide_reaches_total_exactly_once_all_are_decided() {
+        let mut app = app_with_images(&["a.jpg", "b.jpg", "c.jpg"]);
+        let mut history = vec![app.progress()];
+
+        app.skip_current(); // b, c, a
+        history.push(app.progress());
+        app.decide_current(KEEP); // keep b
+        history.push(app.progress());
+        app.skip_current(); // a, c
+        history.push(app.progress());
+        app.decide_current(DISCARD); // discard a
+        history.push(app.progress());
+        app.skip_current(); // single image left, stays put
+        assert_eq!(front(&app), "c.jpg");
+        history.push(app.progress());
+        app.decide_current(KEEP); // keep c
+
+        assert!(history.windows(2).all(|pair| pair[0].0 <= pair[1].0), "progress went backwards");
+        assert!(history.iter().all(|&(decided, total)| decided < total));
+        assert_eq!(app.progress(), (3, 3));
+        assert!(app.image_paths.is_empty());
+        assert_eq!(app.kept_images.len(), 2);
+        assert_eq!(app.discarded_images.len(), 1);
+        assert_eq!(app.decisions[&std::path::PathBuf::from("shoot/a.jpg")], Decision::Discard);
+    }
+
+    #[test]
+    fn repeated_skips_of_the_same_image_never_double_count() {
+        let mut app = app_with_images(&["a.jpg", "b.jpg"]);
+        for _ in 0..5 {
+            app.skip_current();
+        }
+        app.decide_current(DISCARD);
+        assert_eq!(app.progress(), (1, 2));
+        app.decide_current(KEEP);
+        assert_eq!(app.progress(), (2, 2));
+    }
+
+    #[test]
+    fn reset_clears_decisions() {
+        let mut app = app_with_images(&["a.jpg", "b.jpg"]);
+        app.decide_current(KEEP);
+        app.working_path = Some(OsString::from("shoot"));
+        app.reset_progress();
+        assert_eq!(app.progress(), (0, 2));
+        assert_eq!(app.image_paths.len(), 2);
+    }
+
+    #[test]
+    fn reset_keeps_images_that_cannot_be_moved_back() {
+        let root = TempDir::new("reset_move");
+        std::fs::create_dir_all(root.join("kept_images")).unwrap();
+        std::fs::write(root.join("kept_images/a.jpg"), b"moved").unwrap();
+        std::fs::write(root.join("a.jpg"), b"in the way").unwrap(); // Moving back never overwrites
+        let mut app = app_in(&root, &["a.jpg", "b.jpg"]);
+        app.decide_current(KEEP);
+        app.moved.insert(root.join("a.jpg"), vec![(root.join("a.jpg"), root.join("kept_images/a.jpg"))]);
+        app.reset_progress();
+        app.finish_moves(None);
+        assert!(app.moved.contains_key(&root.join("a.jpg")));
+        assert_eq!(app.kept_images, vec![root.join("a.jpg")]);
+        assert_eq!(app.image_paths, vec![root.join("b.jpg")]);
+        assert!(app.status_message.is_some_and(|message| message.contains("could not be moved back")));
+    }
+
+    #[test]
+    fn folder_tallies_match_global_stats() {
+        let mut app = app_with_images(&["a.jpg", "b.jpg"]);
+        if let Some(root) = app.images.as_mut() {
+            root.children.push(FileSysNode {
+                name: OsString::from("day2"),
+                images: vec![OsString::from("c.jpg"), OsString::from("d.jpg")],
+                ..FileSysNode::default()
+            });
+        }
+        app.image_paths = app.images.as_ref().unwrap().get_images_depth_first_current_priority(std::path::Path::new("shoot"));
+        app.decide_current(KEEP);
+        app.skip_current();
+        app.decide_current(DISCARD);
+        app.decide_current(KEEP);
+
+        let tally = folders::tally(app.images.as_ref().unwrap(), std::path::Path::new("shoot"), &app.decisions);
+        assert_eq!(tally.kept, app.kept_images.len());
+        assert_eq!(tally.discarded, app.discarded_images.len());
+        assert_eq!(tally.remaining, app.image_paths.len());
+        assert_eq!(tally.children[0].total(), 2);
+        assert_eq!(tally.total(), 4);
+    }
+
+    #[test]
+    fn redeciding_moves_images_between_kept_and_discarded() {
+        let mut app = app_with_images(&["a.jpg", "b.jpg"]);
+        app.decide_current(KEEP);
+        app.decide_current(KEEP);
+        app.redecide(std::path::Path::new("shoot/a.jpg"), false);
+        assert_eq!(app.kept_images, vec![std::path::PathBuf::from("shoot/b.jpg")]);
+        assert_eq!(app.discarded_images.len(), 1);
+        app.redecide(std::path::Path::new("shoot/a.jpg"), false);
+        assert_eq!(app.discarded_images.len(), 1);
+        app.redecide(std::path::Path::new("shoot/a.jpg"), true);
+        assert_eq!((app.kept_images.len(), app.discarded_images.len()), (2, 0));
+        assert_eq!(app.progress(), (2, 2));
+    }
+
+    #[test]
+    fn window_title_shows_progress_and_result() {
+        let mut app = app_with_images(&["a.jpg", "b.jpg", "c.jpg", "d.jpg"]);
+        assert_eq!(app.window_title(), "Image sifter");
+        app.working_path = Some(OsString::from("shoot"));
+        app.decide_current(KEEP);
+        assert_eq!(app.window_title(), "Image sifter — 1 / 4 (25%)");
+        for _ in 0..3 {
+            app.decide_current(DISCARD);
+        }
+        assert_eq!(app.window_title(), "Image sifter — Done — 1 kept");
+        assert_eq!(with_thousands_separators(1_234_567), "1,234,567");
+        assert_eq!(with_thousands_separators(999), "999");
+    }
+
+    #[test]
+    fn auto_kept_images_skip_the_queue() {
+        let mut app = app_with_images(&[]);
+        app.working_path = Some(OsString::from("shoot"));
+        app.settings.auto_keep_rules = vec!["FINAL".to_string()];
+        let found = ["shoot/a.jpg", "shoot/b_final.jpg"].map(std::path::PathBuf::from).to_vec();
+        assert_eq!(app.enqueue_new_images(found), 1);
+        assert_eq!(app.image_paths, vec![std::path::PathBuf::from("shoot/a.jpg")]);
+        assert_eq!(app.kept_images, vec![std::path::PathBuf::from("shoot/b_final.jpg")]);
+        assert_eq!(app.decisions.len(), 1);
+    }
+
+    #[test]
+    fn unkeeping_returns_the_image_to_the_front_of_the_queue() {
+        let mut app = app_with_images(&["a.jpg", "b.jpg", "c.jpg"]);
+        app.decide_current(KEEP);
+        app.decide_current(DISCARD);
+        app.unkeep(std::path::Path::new("shoot/a.jpg"));
+        assert!(app.kept_images.is_empty());
+        assert_eq!(front(&app), "a.jpg");
+        assert_eq!(app.progress(), (1, 3));
+        app.unkeep(std::path::Path::new("shoot/b.jpg")); // Discarded, not kept: nothing to undo
+        assert_eq!(app.image_paths.len(), 2);
+    }
+
+    #[test]
+    fn images_are_copied_by_bucket() {
+        let mut app = app_with_images(&["a.jpg", "b.jpg", "c.jpg", "d.jpg"]);
+        app.settings.buckets.push(settings::Bucket {
+            name: "Portfolio".to_string(),
+            key: "Q".to_string(),
+            output_folder: "portfolio".to_string(),
+            keeps: true,
+        });
+        app.sort_image(std::path::Path::new("shoot/a.jpg"), 2);
+        app.sort_image(std::path::Path::new("shoot/b.jpg"), 0);
+        app.sort_image(std::path::Path::new("shoot/c.jpg"), 1);
+        app.redecide(std::path::Path::new("shoot/c.jpg"), true);
+        let folder_of = |app: &MyApp, name: &str| app.bucket_of(&std::path::Path::new("shoot").join(name)).map(|bucket| bucket.output_folder.clone());
+        assert_eq!(folder_of(&app, "a.jpg").as_deref(), Some("portfolio"));
+        assert_eq!(folder_of(&app, "b.jpg").as_deref(), Some("kept_images"));
+        assert_eq!(folder_of(&app, "c.jpg").as_deref(), Some("kept_images")); // Re-decided into the default keep bucket
+        assert_eq!(folder_of(&app, "d.jpg"), None);
+        assert_eq!(app.progress(), (3, 4));
+    }
+
+    #[test]
+    fn journal_replay_redoes_actions_on_top_of_the_snapshot() {
+        let mut app = app_with_images(&["a.jpg", "b.jpg", "c.jpg"]);
+        let path = |name: &str| std::path::Path::new("shoot").join(name);
+        app.replay_journal(vec![
+            JournalEntry::Skip { path: path("a.jpg") },
+            JournalEntry::Decide { path: path("b.jpg"), keep: true, bucket: Some("Keep".to_string()) },
+            JournalEntry::Rate { path: path("c.jpg"), rating: Some(5) },
+            JournalEntry::Tag { path: path("c.jpg"), tags: HashSet::from(["ceremony".to_string()]) },
+            JournalEntry::Decide { path: path("c.jpg"), keep: false, bucket: None },
+            JournalEntry::Decide { path: path("new.jpg"), keep: false, bucket: None }, // Not scanned yet
+            JournalEntry::Decide { path: path("b.jpg"), keep: false, bucket: None },
+        ]);
+        assert_eq!(front(&app), "a.jpg");
+        assert_eq!(app.skipped_count, 1);
+        assert_eq!(app.ratings[&path("c.jpg")], 5);
+        assert_eq!(app.images_tagged("ceremony"), [path("c.jpg")]);
+        assert_eq!(app.tag_names, ["ceremony"]);
+        assert!(app.kept_images.is_empty());
+        assert_eq!(app.discarded_images.len(), 3);
+        assert_eq!(app.decisions[&path("b.jpg")], Decision::Discard);
+        assert_eq!(app.decisions[&path("new.jpg")], Decision::Discard);
+    }
+
+    #[test]
+    fn moves_that_fail_in_the_background_are_taken_back_out_of_the_record() {
+        let root = TempDir::new("failed_move");
+        std::fs::create_dir_all(root.join("kept_images")).unwrap();
+        std::fs::write(root.join("a.jpg"), b"a").unwrap();
+        std::fs::write(root.join("kept_images/a.jpg"), b"in the way").unwrap(); // Moves never overwrite
+        let mut app = app_in(&root, &["a.jpg", "b.jpg"]);
+        app.settings.move_as_you_go = true;
+        app.decide_current(KEEP);
+        let recorded_right_away = app.moved.contains_key(&root.join("a.jpg"));
+        app.finish_moves(None);
+        let still_there = root.join("a.jpg").is_file();
+        assert!(recorded_right_away);
+        assert!(still_there);
+        assert!(app.moved.is_empty());
+        assert!(app.status_message.is_some_and(|message| message.contains("Could not move")));
+    }
+
+    #[test]
+    fn dropping_the_journal_keeps_track_of_moved_files() {
+        let root = TempDir::new("drop_journal");
+        std::fs::create_dir_all(root.join("kept_images")).unwrap();
+        std::fs::write(root.join("kept_images/a.jpg"), b"moved before the crash").unwrap();
+        let mut app = app_in(&root, &["a.jpg", "b.jpg"]);
+        app.drop_journal(vec![
+            JournalEntry::Decide { path: root.join("a.jpg"), keep: true, bucket: None },
+            JournalEntry::Move { path: root.join("a.jpg"), files: vec![(root.join("a.jpg"), root.join("kept_images/a.jpg"))] },
+            JournalEntry::Decide { path: root.join("b.jpg"), keep: false, bucket: None },
+        ]);
+        let mut reloaded = MyApp::default();
+        reloaded.load_progress(&root);
+        assert_eq!(app.decisions.get(&root.join("a.jpg")), Some(&Decision::Keep));
+        assert_eq!(app.decisions.get(&root.join("b.jpg")), None);
+        assert_eq!(app.image_paths, vec![root.join("b.jpg")]);
+        assert!(reloaded.moved.contains_key(&root.join("a.jpg")));
+    }
+
+    #[test]
+    fn grid_selection_sorts_every_selected_image_at_once() {
+        let mut app = app_with_images(&["a.jpg", "b.jpg", "c.jpg", "d.jpg", "e.jpg"]);
+        let ctrl = egui::Modifiers::COMMAND;
+        let shift = egui::Modifiers::SHIFT;
+        app.grid_selection.click(1, &app.image_paths, egui::Modifiers::NONE);
+        app.grid_selection.click(3, &app.image_paths, shift); // b..d
+        app.grid_selection.click(2, &app.image_paths, ctrl); // Drops c
+        app.grid_selection.click(4, &app.image_paths, ctrl); // Adds e
+        app.ratings.insert(std::path::PathBuf::from("shoot/d.jpg"), 5);
+        let queue = app.image_paths.clone();
+        app.sort_selection(&queue, DISCARD);
+        assert_eq!(app.discarded_images.len(), 2); // b and e; d is rated and needs a confirmation
+        assert_eq!(app.grid_selection.selected, HashSet::from([std::path::PathBuf::from("shoot/d.jpg")]));
+        assert_eq!(app.image_paths.len(), 3);
+    }
+
+    #[test]
+    fn keeping_the_sharpest_frame_ignores_flipped_checkboxes() {
+        let mut app = app_with_images(&["a.jpg", "b.jpg", "c.jpg", "d.jpg"]);
+        let frames: Vec<std::path::PathBuf> = app.image_paths[..3].to_vec();
+        for (frame, score) in frames.iter().zip([10.0, 30.0, 20.0]) {
+            app.sharpness.insert(frame.clone(), score);
+        }
+        let mut burst = BurstGroup::new(frames);
+        burst.preselect_sharpest(&[10.0, 30.0, 20.0]);
+        burst.keep = vec![true, true, true]; // Flipped by hand
+        app.burst = Some(burst);
+        app.keep_sharpest_of_burst();
+        assert_eq!(app.kept_images, vec![std::path::PathBuf::from("shoot/b.jpg")]);
+        assert_eq!(app.discarded_images.len(), 2);
+        assert_eq!(front(&app), "d.jpg");
+    }
+
+    #[test]
+    fn copied_discards_keep_their_folders_unless_flattened() {
+        let root = TempDir::new("discard_copy");
+        std::fs::create_dir_all(root.join("day1")).unwrap();
+        std::fs::write(root.join("day1/a.jpg"), b"").unwrap();
+        let mut app = app_in(&root, &["day1/a.jpg"]);
+        app.settings.buckets[DISCARD].output_folder = "discarded_images".to_string();
+        app.settings.export_split.enabled = true; // Only for keepers
+        app.decide_current(DISCARD);
+        let mirrored = app.plan_copy(&app.decided_images()).run(|| {}).unwrap();
+        app.settings.mirror_discard_folders = false;
+        app.settings.buckets[DISCARD].output_folder = "flat".to_string();
+        let flat = app.plan_copy(&app.decided_images()).run(|| {}).unwrap();
+        let mirrored_exists = root.join("discarded_images/day1/a.jpg").is_file();
+        let flat_exists = root.join("flat/day1_a.jpg").is_file();
+        assert_eq!((mirrored.copied, flat.copied), (1, 1));
+        assert!(mirrored_exists);
+        assert!(flat_exists);
+    }
+
+    #[test]
+    fn quota_keeps_the_sharpest_share_of_each_folder() {
+        let mut app = app_with_images(&[]);
+        let images: Vec<std::path::PathBuf> = ["day1/a.jpg", "day1/b.jpg", "day1/c.jpg", "day1/d.jpg", "day2/e.jpg"]
+            .iter()
+            .map(|name| std::path::Path::new("shoot").join(name))
+            .collect();
+        app.image_paths = images.clone();
+        for (image, score) in images.iter().zip([5.0, 50.0, 40.0, 1.0, 0.5]) {
+            app.sharpness.insert(image.clone(), score);
+        }
+        app.settings.quota_percent = 50;
+        app.apply_quota(&images);
+        let kept: HashSet<&str> = app.kept_images.iter().map(|image| image.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(kept, HashSet::from(["b.jpg", "c.jpg", "e.jpg"])); // A folder always keeps one
+        assert_eq!(app.discarded_images.len(), 2);
+        app.undo_quota();
+        assert_eq!(app.image_paths, images);
+        assert!(app.decisions.is_empty() && app.kept_images.is_empty() && app.discarded_images.is_empty());
+    }
+
+    #[test]
+    fn grid_can_sort_decided_images_again() {
+        let mut app = app_with_images(&["a.jpg", "b.jpg", "c.jpg"]);
+        app.working_path = Some(OsString::from("shoot"));
+        app.decide_current(KEEP);
+        assert_eq!(app.grid_images().len(), 2);
+        app.grid_show_decided = true;
+        let images = app.grid_images();
+        assert_eq!(images.len(), 3);
+        app.grid_selection.select_all(&images);
+        app.sort_selection(&images, DISCARD);
+        assert!(app.kept_images.is_empty());
+        assert_eq!(app.discarded_images.len(), 3);
+        assert_eq!(app.bucket_of(std::path::Path::new("shoot/a.jpg")).map(|bucket| bucket.name.as_str()), Some("Discard"));
+    }
+
+    #[test]
+    fn copies_run_in_the_background_and_report_when_done() {
+        let root = TempDir::new("copy_job");
+        std::fs::write(root.join("a.jpg"), b"a").unwrap();
+        let mut app = app_in(&root, &["a.jpg"]);
+        app.decide_current(KEEP);
+        let ctx = egui::Context::default();
+        app.run_copy(app.decided_images(), &ctx);
+        let running = app.copy_job.as_ref().map(|(job, _)| job.total);
+        finish_copy(&mut app, &ctx);
+        let copied = root.join("kept_images/a.jpg").is_file();
+        assert_eq!(running, Some(1));
+        assert!(app.copy_job.is_none());
+        assert!(matches!(&app.copy_report, Some(Ok(report)) if report.copied == 1));
+        assert!(copied);
+    }
+
+    #[test]
+    fn watermarked_copies_of_keepers_are_reencoded() {
+        let root = TempDir::new("watermark");
+        std::fs::copy(FIXTURE, root.join("a.jpg")).unwrap();
+        image::RgbImage::new(4, 4).save(root.join("b.png")).unwrap();
+        let mut app = app_in(&root, &["a.jpg", "b.png"]);
+        app.settings.watermark.enabled = true;
+        app.settings.watermark.opacity = 1.0;
+        app.settings.watermark.size_percent = 20.0;
+        app.decide_current(KEEP);
+        app.decide_current(KEEP);
+        let report = app.plan_copy(&app.decided_images()).run(|| {}).unwrap();
+        let original = image::open(root.join("a.jpg")).unwrap().to_rgb8();
+        let copy = image::open(root.join("kept_images/a.jpg")).unwrap().to_rgb8();
+        assert_eq!((report.copied, report.unwatermarked), (2, 1));
+        assert_eq!(original.dimensions(), copy.dimensions());
+        let changed = original.pixels().zip(copy.pixels()).filter(|(a, b)| a.0.iter().zip(b.0).any(|(x, y)| x.abs_diff(y) > 64)).count();
+        assert!(changed > 0);
+    }
+
+    #[test]
+    fn discarded_paths_survive_saving_and_loading() {
+        let root = TempDir::new("discarded_paths");
+        let mut app = app_in(&root, &["a.jpg", "b.jpg", "c.jpg"]);
+        app.decide_current(DISCARD);
+        app.decide_current(KEEP);
+        app.decide_current(DISCARD);
+        app.save_progress();
+        let mut loaded = MyApp::default();
+        let has_old_progress_file = loaded.load_progress(&root);
+        assert!(!has_old_progress_file);
+        assert_eq!(loaded.discarded_images, vec![root.join("a.jpg"), root.join("c.jpg")]);
+        assert_eq!(loaded.kept_images, vec![root.join("b.jpg")]);
+    }
+
+    #[test]
+    fn reversing_the_queue_leaves_decided_images_alone() {
+        let mut app = app_with_images(&["a.jpg", "b.jpg", "c.jpg", "d.jpg"]);
+        app.decide_current(KEEP);
+        app.reverse_queue();
+        assert_eq!(front(&app), "d.jpg");
+        assert_eq!(app.image_paths.len(), 3);
+        app.decide_current(DISCARD);
+        app.reverse_queue();
+        assert_eq!(front(&app), "b.jpg");
+        assert_eq!((app.kept_images.len(), app.discarded_images.len(), app.image_paths.len()), (1, 1, 2));
+    }
+
+    #[test]
+    fn unreadable_progress_falls_back_to_the_previous_save() {
+        let root = TempDir::new("backup");
+        let mut app = app_in(&root, &["a.jpg", "b.jpg"]);
+        app.decide_current(KEEP);
+        app.save_progress();
+        app.save_progress(); // The first save becomes the backup
+        std::fs::write(root.join(PROGRESS_FILE), "{\"kept_images\": [").unwrap();
+        let mut loaded = MyApp::default();
+        loaded.load_progress(&root);
+        assert_eq!(loaded.kept_images, vec![root.join("a.jpg")]);
+        assert!(loaded.status_message.is_some_and(|message| message.contains("backup")));
+    }
+
+    #[test]
+    fn face_search_results_can_be_taken_back() {
+        let mut app = app_with_images(&["a.jpg", "b.jpg", "c.jpg", "d.jpg"]);
+        app.ratings.insert(std::path::PathBuf::from("shoot/d.jpg"), PROTECTED_RATING);
+        let path = |name: &str| std::path::Path::new("shoot").join(name);
+        app.apply_face_search(&[path("a.jpg"), path("c.jpg")], &[path("b.jpg"), path("d.jpg")]);
+        assert_eq!(app.kept_images, vec![path("a.jpg"), path("c.jpg")]);
+        assert_eq!(app.discarded_images, vec![path("b.jpg")]);
+        assert_eq!(front(&app), "d.jpg"); // Protected, so it waits for the user
+        app.undo_face_search();
+        let names: Vec<_> = app.image_paths.iter().map(|image| image.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, ["a.jpg", "b.jpg", "c.jpg", "d.jpg"]);
+    }
+
+    #[test]
+    fn decisions_made_while_filtered_stay_when_the_filter_is_cleared() {
+        let mut app = app_with_images(&["IMG_1.jpg", "DSC_2.jpg", "IMG_3.jpg", "DSC_4.jpg"]);
+        app.queue_filter.text = "dsc".to_string();
+        app.queue_filter.apply(&mut app.image_paths);
+        assert_eq!(front(&app), "DSC_2.jpg");
+        app.decide_current(KEEP);
+        app.decide_current(DISCARD);
+        assert!(app.image_paths.is_empty());
+        app.queue_filter.text.clear();
+        app.queue_filter.apply(&mut app.image_paths);
+        let names: Vec<_> = app.image_paths.iter().map(|image| image.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, ["IMG_1.jpg", "IMG_3.jpg"]);
+        assert_eq!((app.kept_images.len(), app.discarded_images.len()), (1, 1));
+    }
+
+    #[test]
+    fn blur_pass_discards_only_images_below_the_threshold() {
+        let mut app = app_with_images(&["a.jpg", "b.jpg", "c.jpg"]);
+        let path = |name: &str| std::path::Path::new("shoot").join(name);
+        app.sharpness.extend([(path("a.jpg"), 12.0), (path("b.jpg"), 450.0), (path("c.jpg"), 80.0)]);
+        app.settings.blur_threshold = 100.0;
+        let images = app.image_paths.clone();
+        app.apply_blur_pass(&images);
+        assert_eq!(app.discarded_images, vec![path("a.jpg"), path("c.jpg")]);
+        assert_eq!(front(&app), "b.jpg");
+        app.undo_blur_pass();
+        assert_eq!(front(&app), "a.jpg");
+        assert_eq!(app.image_paths.len(), 3);
+    }
+
+    #[test]
+    fn undecodable_images_are_copied_into_quarantine() {
+        let root = TempDir::new("quarantine");
+        std::fs::write(root.join("a.jpg"), b"").unwrap();
+        std::fs::write(root.join("b.jpg"), b"not a jpeg").unwrap();
+        let mut app = app_in(&root, &["a.jpg", "b.jpg"]);
+        app.settings.quarantine_decode_errors = true;
+        app.decode_errors.insert(root.join("b.jpg"), "broken JPEG".to_string());
+        app.decide_current(KEEP);
+        app.decide_current(KEEP);
+        let report = app.plan_copy(&app.decided_images()).run(|| {}).unwrap();
+        let kept = (root.join("kept_images/a.jpg").is_file(), root.join("kept_images/b.jpg").is_file());
+        let quarantined = root.join("decode_errors/b.jpg").is_file();
+        let log = std::fs::read_to_string(root.join("decode_errors").join(export::DECODE_ERROR_LOG)).unwrap_or_default();
+        assert_eq!((report.copied, report.quarantined), (1, 1));
+        assert_eq!(kept, (true, false));
+        assert!(quarantined);
+        assert_eq!(log, "b.jpg: broken JPEG\n");
+    }
+
+    #[test]
+    fn moving_as_you_go_takes_sidecars_along_and_undoing_brings_them_back() {
+        let root = TempDir::new("move");
+        for name in ["a.jpg", "a.CR3", "b.jpg"] {
+            std::fs::write(root.join(name), name).unwrap();
+        }
+        let mut app = app_in(&root, &["a.jpg", "b.jpg"]);
+        app.settings.move_as_you_go = true;
+        app.decide_current(KEEP);
+        app.finish_moves(None);
+        let moved = ["a.jpg", "a.CR3"].map(|name| (root.join(name).exists(), root.join("kept_images").join(name).exists()));
+        let report = app.plan_copy(&app.decided_images()).run(|| {}).unwrap();
+        app.undecide(&root.join("a.jpg"));
+        let decided_until_back = app.decisions.contains_key(&root.join("a.jpg"));
+        app.finish_moves(None);
+        let back = ["a.jpg", "a.CR3"].map(|name| (root.join(name).exists(), root.join("kept_images").join(name).exists()));
+        assert_eq!(moved, [(false, true), (false, true)]);
+        assert_eq!(report.copied, 0); // Already where the copy would have put it
+        assert!(decided_until_back);
+        assert_eq!(back, [(true, false), (true, false)]);
+        assert!(app.moved.is_empty());
+        assert_eq!(front(&app), "a.jpg");
+    }
+
+    #[test]
+    fn decisions_flash_in_the_color_of_their_kind() {
+        let mut app = app_with_images(&["a.jpg", "b.jpg"]);
+        app.decide_current(KEEP);
+        assert_eq!(app.flash.map(|(keep, _)| app.settings.flash_color(keep)), Some(egui::Color32::from_rgb(80, 180, 60)));
+        app.decide_current(DISCARD);
+        assert_eq!(app.flash.map(|(keep, _)| keep), Some(false));
+    }
+
+    #[test]
+    fn listed_images_are_reviewed_without_scanning_their_folders() {
+        let root = TempDir::new("list");
+        std::fs::create_dir_all(root.join("shoot/day1")).unwrap();
+        std::fs::create_dir_all(root.join("lists")).unwrap();
+        for name in ["shoot/day1/a.jpg", "shoot/b.jpg", "shoot/unlisted.jpg"] {
+            std::fs::write(root.join(name), b"").unwrap();
+        }
+        let list = root.join("lists/candidates.txt");
+        let absolute_a = root.join("shoot/day1/a.jpg");
+        std::fs::write(&list, format!("# From the other tool\n{}\n../shoot/b.jpg\n../shoot/gone.jpg\n{}\n", absolute_a.display(), absolute_a.display())).unwrap();
+        let mut app = MyApp::default();
+        app.open_image_list(list, &egui::Context::default());
+        let working_path = app.working_path.clone().map(std::path::PathBuf::from);
+        let queue = app.image_paths.clone();
+        let count = app.images.as_ref().map(|tree| tree.count_images());
+        let status = app.status_message.clone().unwrap_or_default();
+        let output = app.output_relative_path(&root.join("shoot/day1/a.jpg"));
+        assert_eq!(working_path, Some(root.join("shoot")));
+        assert_eq!(queue, vec![root.join("shoot/day1/a.jpg"), root.join("shoot/b.jpg")]);
+        assert_eq!(count, Some(2));
+        assert!(status.contains("1 listed files not found"), "{}", status);
+        assert_eq!(output, Some(std::path::PathBuf::from("day1/a.jpg")));
+    }
+
+    #[test]
+    fn lone_images_are_auto_kept() {
+        let mut app = app_with_images(&[]);
+        app.working_path = Some(OsString::from("shoot"));
+        app.settings.auto_keep_lone_images = true;
+        let mut tree = FileSysNode::default();
+        tree.insert_folder(&[OsString::from("day1")], vec![OsString::from("a.jpg"), OsString::from("b.jpg")]);
+        tree.insert_folder(&[OsString::from("day2")], vec![OsString::from("final.jpg")]);
+        tree.insert_folder(&[OsString::from("day2"), OsString::from("raw")], vec![OsString::from("c.jpg"), OsString::from("d.jpg")]);
+        let found = tree.get_images_depth_first_current_priority(std::path::Path::new("shoot"));
+        app.images = Some(Box::new(tree));
+        assert_eq!(app.enqueue_new_images(found), 1);
+        assert_eq!(app.kept_images, vec![std::path::PathBuf::from("shoot/day2/final.jpg")]);
+        assert_eq!(app.image_paths.len(), 4);
+        app.unkeep(std::path::Path::new("shoot/day2/final.jpg"));
+        assert_eq!(front(&app), "final.jpg");
+    }
+
+    #[test]
+    fn bulk_edits_reach_the_whole_folder_and_can_be_taken_back() {
+        let mut app = app_with_images(&["a.jpg", "b.jpg", "c.jpg"]);
+        app.working_path = Some(OsString::from("shoot"));
+        app.decide_current(KEEP);
+        app.set_rating(std::path::Path::new("shoot/b.jpg"), Some(2));
+        let members = app.folder_images(std::path::Path::new("shoot/b.jpg"));
+        assert_eq!(members.len(), 3, "decided images belong to the folder too");
+
+        app.apply_bulk_edit(&members, BulkEdit::Rate(Some(4)));
+        app.apply_bulk_edit(&members, BulkEdit::AddTag("ceremony".to_string()));
+        assert!(members.iter().all(|image| app.ratings.get(image) == Some(&4)));
+        assert!(members.iter().all(|image| app.tags[image].contains("ceremony")));
+
+        app.undo_bulk_edit(); // Only the last edit: the tag
+        assert!(app.tags.is_empty());
+        assert_eq!(app.ratings.get(std::path::Path::new("shoot/c.jpg")), Some(&4));
+        app.apply_bulk_edit(&members, BulkEdit::Rate(None));
+        app.undo_bulk_edit();
+        assert!(members.iter().all(|image| app.ratings.get(image) == Some(&4)));
+    }
+
+    #[test]
+    fn the_last_decided_image_can_be_moved_to_another_bucket() {
+        let mut app = app_with_images(&["a.jpg", "b.jpg"]);
+        app.settings.buckets.push(settings::Bucket {
+            name: "Maybe".to_string(),
+            key: "M".to_string(),
+            output_folder: "maybe".to_string(),
+            keeps: true,
+        });
+        app.decide_current(2);
+        app.resort_last_decided(KEEP);
+        let a = std::path::Path::new("shoot/a.jpg");
+        assert_eq!(app.bucket_of(a).map(|bucket| bucket.name.as_str()), Some("Keep"));
+        app.resort_last_decided(DISCARD);
+        assert_eq!(app.decisions.get(a), Some(&Decision::Discard));
+        assert!(app.kept_images.is_empty());
+        assert_eq!(app.discarded_images, vec![a.to_path_buf()]);
+        assert_eq!(front(&app), "b.jpg", "the queue stays where it is");
+    }
+
+    #[test]
+    fn the_decision_trail_keeps_the_last_few_decisions() {
+        let names: Vec<String> = (0..DECISION_TRAIL_LENGTH + 2).map(|i| format!("{}.jpg", i)).collect();
+        let mut app = app_with_images(&names.iter().map(String::as_str).collect::<Vec<_>>());
+        for i in 0..names.len() {
+            app.decide_current(if i % 2 == 0 { KEEP } else { DISCARD });
+        }
+        assert_eq!(app.trail.len(), DECISION_TRAIL_LENGTH);
+        assert_eq!(app.trail[0], std::path::Path::new("shoot").join("2.jpg"), "the oldest decisions drop off");
+        let last = app.trail.last().cloned().unwrap();
+        app.undecide(&last);
+        assert_eq!(app.image_paths, vec![last.clone()]);
+        app.decide_current(KEEP);
+        assert_eq!(app.trail.iter().filter(|path| **path == last).count(), 1, "decided twice, shown once");
+    }
+
+    #[test]
+    fn appearance_settings_reach_the_context() {
+        let ctx = egui::Context::default();
+        let mut app = MyApp::default();
+        app.settings.theme = settings::Theme::Light;
+        app.settings.ui_scale = 1.5;
+        app.apply_appearance(&ctx);
+        let _ = ctx.run(egui::RawInput::default(), |_| {}); // A new size takes effect with the next frame
+        assert_eq!(ctx.options(|options| options.theme_preference), egui::ThemePreference::Light);
+        assert_eq!(ctx.zoom_factor(), 1.5);
+
+        app.settings.ui_scale = 10.0;
+        app.apply_appearance(&ctx);
+        let _ = ctx.run(egui::RawInput::default(), |_| {});
+        assert_eq!(ctx.zoom_factor(), MAX_UI_SCALE, "kept usable whatever the settings file says");
+    }
+
+    #[test]
+    fn folder_navigation_moves_past_the_rest_of_the_folder() {
+        let mut app = MyApp {
+            image_paths: ["day1/a.jpg", "day1/b.jpg", "day1/c.jpg", "day2/d.jpg", "day2/e.jpg"]
+                .iter()
+                .map(|name| std::path::Path::new("shoot").join(name))
+                .collect(),
+            ..MyApp::default()
+        };
+        app.next_folder();
+        let names: Vec<&str> = app.image_paths.iter().map(|path| path.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, ["d.jpg", "e.jpg", "a.jpg", "b.jpg", "c.jpg"], "the folder is deferred in its own order");
+        assert_eq!(app.skipped_count, 3);
+
+        app.next_folder();
+        app.ratings.insert(std::path::Path::new("shoot/day1/b.jpg").to_path_buf(), 5);
+        app.skip_rest_of_folder();
+        assert_eq!(app.discarded_images.len(), 2, "a and c are discarded");
+        assert!(!app.decisions.contains_key(std::path::Path::new("shoot/day1/b.jpg")), "the five-star one is left to look at");
+        let names: Vec<&str> = app.image_paths.iter().map(|path| path.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, ["d.jpg", "e.jpg", "b.jpg"]);
+    }
+
+    #[test]
+    fn shuffling_with_a_seed_gives_the_same_order_every_time() {
+        let names: Vec<String> = (0..20).map(|i| format!("IMG_{:04}.jpg", i)).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let mut first = app_with_images(&names);
+        let mut second = app_with_images(&names);
+        first.shuffle_queue(Some(42));
+        second.shuffle_queue(Some(42));
+        assert_eq!(first.image_paths, second.image_paths);
+        assert_ne!(first.image_paths, app_with_images(&names).image_paths, "twenty images hardly come out in order");
+        assert_eq!(first.shuffle_seed, Some(42));
+
+        let shown = first.image_paths[0].clone();
+        first.decide_current(KEEP);
+        assert_eq!(first.kept_images, vec![shown], "the decision is for the image that was shown");
+    }
+
+    #[test]
+    fn rescans_and_added_folders_are_walked_in_the_background() {
+        let root = TempDir::new("rescan");
+        let (photos, other) = (root.join("photos"), root.join("other"));
+        std::fs::create_dir_all(photos.join("day2")).unwrap();
+        std::fs::create_dir_all(&other).unwrap();
+        for image in [photos.join("a.jpg"), photos.join("b.jpg"), other.join("c.jpg")] {
+            std::fs::copy(FIXTURE, image).unwrap();
+        }
+        let ctx = egui::Context::default();
+        let mut app = MyApp::default();
+        app.open_working_folder(photos.clone(), &ctx);
+        finish_scan(&mut app, &ctx);
+        let opened = app.image_paths.clone();
+
+        std::fs::copy(FIXTURE, photos.join("day2/d.jpg")).unwrap();
+        app.decide_current(KEEP);
+        app.rescan_for_new_files(&ctx);
+        let rescanning = app.scan.is_some();
+        finish_scan(&mut app, &ctx);
+        let rescanned = (app.image_paths.clone(), app.status_message.clone());
+
+        app.add_root_folder(other.clone(), &ctx);
+        finish_scan(&mut app, &ctx);
+        let total = app.images.as_ref().map(|tree| tree.count_images());
+
+        assert_eq!(opened, vec![photos.join("a.jpg"), photos.join("b.jpg")]);
+        assert!(rescanning, "the rescan runs on the scan thread");
+        assert_eq!(rescanned, (vec![photos.join("b.jpg"), photos.join("day2/d.jpg")], Some("Added 1 new image".to_string())));
+        assert_eq!(app.image_paths.last(), Some(&other.join("c.jpg")));
+        assert_eq!(app.status_message, Some(format!("Added 1 images from {}", other.display())));
+        assert_eq!(total, Some(4));
+        assert_eq!(app.extra_roots, vec![other]);
+    }
+
+    #[test]
+    fn shuffled_sessions_are_queued_once_the_scan_ends_and_summed_up_in_folder_order() {
+        let root = TempDir::new("shuffled_scan");
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut app = app_in(&root, &[]);
+        app.settings.shuffle_seed = Some(7);
+        app.scan = Some(RunningScan {
+            receiver,
+            root: root.to_path_buf(),
+            later_roots: Vec::new(),
+            kind: ScanKind::Session,
+            queued: HashSet::new(),
+            queue_new_images: true,
+            reverse_when_done: false,
+            shuffle_when_done: true,
+            held_back: Vec::new(),
+            found: 0,
+            auto_kept: 0,
+        });
+        let folder = |relative_path: &[&str], count: usize| ScannedFolder {
+            relative_path: relative_path.iter().map(OsString::from).collect(),
+            images: (0..count).map(|i| OsString::from(format!("IMG_{:04}.jpg", i))).collect(),
+            sidecars: Vec::new(),
+            raw_only: 0,
+            provisional_order: false,
+        };
+        let ctx = egui::Context::default();
+        sender.send(folder(&[], 20)).unwrap();
+        app.poll_scan(&ctx);
+        let queued_while_scanning = app.image_paths.len();
+        sender.send(folder(&["day2"], 2)).unwrap();
+        drop(sender);
+        app.poll_scan(&ctx);
+        let first = app.image_paths[0].clone();
+        app.decide_current(KEEP);
+        let tree = app.images.as_deref().unwrap();
+        let summary = summary::SessionSummary::new(tree, &root, &app.decisions, 0, app.shuffle_seed, std::time::Duration::ZERO);
+
+        assert_eq!(queued_while_scanning, 0, "the first folder is not reviewed first");
+        assert!(app.scan.is_none());
+        assert_eq!(app.image_paths.len(), 21);
+        assert_eq!(app.shuffle_seed, Some(7));
+        let in_folder_order = tree.get_images_depth_first_current_priority(&root);
+        assert_ne!(first, in_folder_order[0], "twenty-two images hardly come out in order");
+        let position = in_folder_order.iter().position(|image| *image == first).unwrap();
+        assert_eq!(summary.images[position], (first.strip_prefix(&root).unwrap().to_path_buf(), Some(Decision::Keep)));
+        assert!(summary.to_text().contains(&format!("  {:>5}  kept       {}", position + 1, summary.images[position].0.display())));
+    }
+
+    #[test]
+    fn zooming_to_a_face_fills_the_view_with_it_and_back_out() {
+        let mut app = app_with_images(&["portrait.jpg"]);
+        let current = app.image_paths[0].clone();
+        app.zoom.follow(&current, false);
+        app.zoom.layout(egui::vec2(1000.0, 1000.0), [1000, 1000], 1.0);
+        let face = egui::Rect::from_center_size(egui::pos2(0.3, 0.25), egui::vec2(0.05, 0.05));
+        app.face_boxes.insert(current, vec![face]);
+
+        let ctx = egui::Context::default();
+        app.toggle_face_zoom(&ctx);
+        let view = app.zoom.uv_rect();
+        assert!((view.center() - face.center()).length() < 1e-4, "centered on the face");
+        assert!((view.width() - face.width() * (1.0 + 2.0 * FACE_ZOOM_MARGIN)).abs() < 1e-4, "with a margin around it");
+
+        app.zoom.layout(egui::vec2(1000.0, 1000.0), [1000, 1000], 1.0);
+        app.toggle_face_zoom(&ctx);
+        app.zoom.layout(egui::vec2(1000.0, 1000.0), [1000, 1000], 1.0);
+        assert_eq!(app.zoom.uv_rect(), egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1.0, 1.0)));
+    }
+
+    #[test]
+    fn pages_are_looked_up_on_disk_through_their_file() {
+        let root = TempDir::new("page_file");
+        let scan = root.join("scan.tif");
+        let mut encoder = tiff::encoder::TiffEncoder::new(std::fs::File::create(&scan).unwrap()).unwrap();
+        for shade in [10u8, 240] {
+            encoder.write_image::<tiff::encoder::colortype::RGB8>(4, 3, &[shade; 4 * 3 * 3]).unwrap();
+        }
+        drop(encoder);
+        let rated = XmpFields { rating: Some(3), label: None };
+        metadata::write_xmp_sidecar(&scan, &rated).unwrap();
+        std::fs::write(root.join("scan.CR3"), b"not a sidecar of the pages").unwrap();
+        let file_size = std::fs::metadata(&scan).unwrap().len();
+
+        let settings = Settings::default();
+        let tree = scan::scan_folder(&root, &ScanOptions::from_settings(&settings));
+        let page = pages::page_path(&scan, 2);
+        let decisions = HashMap::from([(pages::page_path(&scan, 1), Decision::Keep), (page.clone(), Decision::Keep)]);
+        let summary = summary::SessionSummary::new(&tree, &root, &decisions, 0, None, std::time::Duration::ZERO);
+        let read = metadata::read_xmp_sidecar(&page);
+        let raw = export::raw_sidecar(&page);
+        let is_jpeg = decode::has_jpeg_signature(&page);
+
+        assert_eq!(read, Some(rated), "the sidecar of the file");
+        assert_eq!(summary.kept_bytes, file_size, "the file once, not once per page");
+        assert_eq!(raw, None);
+        assert!(!is_jpeg);
+    }
+
+    #[test]
+    fn same_named_files_from_elsewhere_count_as_conflicts() {
+        let root = TempDir::new("same_name");
+        std::fs::create_dir_all(root.join("kept_images")).unwrap();
+        std::fs::copy(FIXTURE, root.join("IMG_0001.jpg")).unwrap();
+        std::fs::write(root.join("IMG_0002.jpg"), b"this card").unwrap();
+        std::fs::copy(root.join("IMG_0001.jpg"), root.join("kept_images/IMG_0001.jpg")).unwrap(); // Copied earlier
+        std::fs::write(root.join("kept_images/IMG_0002.jpg"), b"another card").unwrap();
+        std::fs::write(root.join("kept_images/IMG_0002.xmp"), b"").unwrap();
+        let mut app = app_in(&root, &["IMG_0001.jpg", "IMG_0002.jpg"]);
+        app.decide_current(KEEP);
+        app.decide_current(KEEP);
+        let conflicts = app.output_conflicts(&app.decided_images());
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].files, vec![root.join("kept_images/IMG_0002.jpg"), root.join("kept_images/IMG_0002.xmp")]);
+    }
+
+    #[test]
+    fn copying_into_a_folder_with_other_results_waits_for_a_choice() {
+        let root = TempDir::new("output_conflict");
+        std::fs::create_dir_all(root.join("kept_images/day1")).unwrap();
+        std::fs::copy(FIXTURE, root.join("a.jpg")).unwrap();
+        std::fs::write(root.join("kept_images/day1/old.jpg"), b"earlier sort").unwrap();
+        let mut app = app_in(&root, &["a.jpg"]);
+        app.decide_current(KEEP);
+        let ctx = egui::Context::default();
+        app.run_copy(app.decided_images(), &ctx);
+        let held_back = !root.join("kept_images/a.jpg").exists();
+        let conflict = app.pending_export.as_ref().map(|pending| (pending.conflicts[0].0.files.clone(), pending.conflicts[0].0.bytes));
+        let pending = app.pending_export.take().unwrap();
+        app.replace_and_copy(pending, &ctx);
+        finish_copy(&mut app, &ctx);
+        let copied = root.join("kept_images/a.jpg").is_file();
+        let moved_aside = root.join("kept_images_previous/day1/old.jpg").is_file();
+        let emptied = !root.join("kept_images/day1").exists();
+        assert!(held_back);
+        assert_eq!(conflict, Some((vec![root.join("kept_images/day1/old.jpg")], 12)));
+        assert!(copied && moved_aside && emptied);
+    }
 }
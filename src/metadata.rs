@@ -0,0 +1,197 @@
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+use serde::{Serialize, Deserialize};
+
+/// Lightroom/Bridge style color labels.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ColorLabel {
+    Red,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+}
+
+impl ColorLabel {
+    pub const ALL: [ColorLabel; 5] = [Self::Red, Self::Yellow, Self::Green, Self::Blue, Self::Purple];
+
+    /// Value written to `xmp:Label`; these match Lightroom's default label set names.
+    pub fn xmp_name(self) -> &'static str {
+        match self {
+            Self::Red => "Red",
+            Self::Yellow => "Yellow",
+            Self::Green => "Green",
+            Self::Blue => "Blue",
+            Self::Purple => "Purple",
+        }
+    }
+
+    pub fn color(self) -> egui::Color32 {
+        match self {
+            Self::Red => egui::Color32::from_rgb(220, 50, 47),
+            Self::Yellow => egui::Color32::from_rgb(230, 200, 40),
+            Self::Green => egui::Color32::from_rgb(80, 180, 60),
+            Self::Blue => egui::Color32::from_rgb(50, 120, 220),
+            Self::Purple => egui::Color32::from_rgb(150, 80, 200),
+        }
+    }
+
+    /// Keys 6-9 follow Lightroom (red, yellow, green, blue); 0 picks purple.
+    pub fn from_key(key: egui::Key) -> Option<Self> {
+        match key {
+            egui::Key::Num6 => Some(Self::Red),
+            egui::Key::Num7 => Some(Self::Yellow),
+            egui::Key::Num8 => Some(Self::Green),
+            egui::Key::Num9 => Some(Self::Blue),
+            egui::Key::Num0 => Some(Self::Purple),
+            _ => None,
+        }
+    }
+}
+
+/// Draws a small filled circle in the label's color.
+pub fn label_dot(ui: &mut egui::Ui, label: ColorLabel) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(egui::Vec2::splat(12.0), egui::Sense::hover());
+    ui.painter().circle_filled(rect.center(), 5.0, label.color());
+    response.on_hover_text(label.xmp_name())
+}
+
+/// The fields this app knows how to put into, and read back from, an XMP sidecar.
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct XmpFields {
+    pub rating: Option<u8>,
+    pub label: Option<ColorLabel>,
+}
+
+impl XmpFields {
+    pub fn is_empty(&self) -> bool {
+        self.rating.is_none() && self.label.is_none()
+    }
+
+    /// Takes in an `xmp:Rating` or `xmp:Label` value; anything else is ignored. Unrated (0)
+    /// and rejected (-1) images have no rating here.
+    fn set(&mut self, name: &[u8], value: &str) {
+        match name {
+            b"xmp:Rating" => {
+                self.rating = value.trim().parse::<i32>().ok().filter(|rating| (1..=5).contains(rating)).map(|rating| rating as u8);
+            },
+            b"xmp:Label" => {
+                self.label = ColorLabel::ALL.into_iter().find(|label| label.xmp_name().eq_ignore_ascii_case(value.trim()));
+            },
+            _ => {},
+        }
+    }
+}
+
+/// Star rating for the number keys 1-5.
+pub fn rating_from_key(key: egui::Key) -> Option<u8> {
+    match key {
+        egui::Key::Num1 => Some(1),
+        egui::Key::Num2 => Some(2),
+        egui::Key::Num3 => Some(3),
+        egui::Key::Num4 => Some(4),
+        egui::Key::Num5 => Some(5),
+        _ => None,
+    }
+}
+
+pub fn stars(rating: u8) -> String {
+    format!("{}{}", "★".repeat(rating as usize), "☆".repeat(5usize.saturating_sub(rating as usize)))
+}
+
+/// Sidecars share the image's stem (`IMG_0001.xmp`), which is where Lightroom and
+/// Bridge look for them next to both the JPEG and its RAW.
+pub fn sidecar_path(image_path: &Path) -> PathBuf {
+    image_path.with_extension("xmp")
+}
+
+pub fn write_xmp_sidecar(image_path: &Path, fields: &XmpFields) -> std::io::Result<()> {
+    let mut attributes = String::new();
+    if let Some(rating) = fields.rating {
+        attributes.push_str(&format!("\n    xmp:Rating=\"{}\"", rating));
+    }
+    if let Some(label) = fields.label {
+        attributes.push_str(&format!("\n    xmp:Label=\"{}\"", label.xmp_name()));
+    }
+    let packet = format!(
+        r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"{}/>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>
+"#,
+        attributes
+    );
+    std::fs::write(sidecar_path(image_path), packet)
+}
+
+/// Rating and label from the sidecar of `image_path`, as left by Lightroom, Bridge or this
+/// app. None when there is no sidecar or it holds neither.
+pub fn read_xmp_sidecar(image_path: &Path) -> Option<XmpFields> {
+    let xml = std::fs::read_to_string(sidecar_path(&crate::pages::file_of(image_path))).ok()?;
+    let fields = parse_xmp(&xml);
+    (!fields.is_empty()).then_some(fields)
+}
+
+/// Both ways XMP puts properties: as attributes of `rdf:Description`, which is what
+/// Lightroom writes, and as elements inside it.
+pub fn parse_xmp(xml: &str) -> XmpFields {
+    use quick_xml::events::Event;
+
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut fields = XmpFields::default();
+    let mut element = Vec::new(); // Name of the element whose text comes next
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(start)) | Ok(Event::Empty(start)) => {
+                for attribute in start.attributes().flatten() {
+                    if let Ok(value) = attribute.unescape_value() {
+                        fields.set(attribute.key.as_ref(), &value);
+                    }
+                }
+                element = start.name().as_ref().to_vec();
+            },
+            Ok(Event::Text(text)) => {
+                if let Ok(text) = text.unescape() {
+                    fields.set(&element, &text);
+                }
+            },
+            Ok(Event::End(_)) => element.clear(),
+            Ok(Event::Eof) => break,
+            Err(e) => {
+                log::warn!("Stopped reading an XMP sidecar at an error: {}", e);
+                break;
+            },
+            Ok(_) => {},
+        }
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn ratings_and_labels_are_read_back_from_xmp_sidecars() {
+        let lightroom = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+            <rdf:Description rdf:about="" xmlns:xmp="http://ns.adobe.com/xap/1.0/" xmp:Rating="4" xmp:Label="Green"/>
+            </rdf:RDF></x:xmpmeta>"#;
+        assert_eq!(parse_xmp(lightroom), XmpFields { rating: Some(4), label: Some(ColorLabel::Green) });
+        let as_elements = r#"<rdf:Description><xmp:Rating>-1</xmp:Rating><xmp:Label>purple</xmp:Label></rdf:Description>"#;
+        assert_eq!(parse_xmp(as_elements), XmpFields { rating: None, label: Some(ColorLabel::Purple) });
+
+        let root = TempDir::new("xmp_read");
+        let written = XmpFields { rating: Some(2), label: Some(ColorLabel::Red) };
+        write_xmp_sidecar(&root.join("a.jpg"), &written).unwrap();
+        let read = read_xmp_sidecar(&root.join("a.jpg"));
+        let missing = read_xmp_sidecar(&root.join("b.jpg"));
+        assert_eq!(read, Some(written));
+        assert_eq!(missing, None);
+    }
+}
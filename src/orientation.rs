@@ -0,0 +1,225 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+};
+
+use eframe::egui;
+
+/// Whether this build can turn JPEGs, which it only does without re-encoding them (through
+/// libjpeg-turbo).
+pub const LOSSLESS: bool = cfg!(feature = "turbojpeg");
+
+const ORIENTATION_TAG: u16 = 0x0112;
+
+/// Where the EXIF orientation of a JPEG is stored: the offset of its value and whether the
+/// EXIF block is little-endian. None when the file has no orientation tag.
+fn orientation_field(jpeg: &[u8]) -> Option<(usize, bool)> {
+    let u16_at = |offset: usize, little_endian: bool| -> Option<u16> {
+        let bytes = [*jpeg.get(offset)?, *jpeg.get(offset + 1)?];
+        Some(if little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+    };
+    if jpeg.get(..2)? != [0xFF, 0xD8] {
+        return None;
+    }
+    let mut position = 2;
+    loop {
+        let marker = *jpeg.get(position + 1)?;
+        if jpeg[position] != 0xFF || marker == 0xDA || marker == 0xD9 {
+            return None; // Lost, or the image data starts: metadata only comes before it
+        }
+        let length = usize::from(u16_at(position + 2, false)?);
+        let data = position + 4;
+        if marker == 0xE1 && jpeg.get(data..data + 6) == Some(b"Exif\0\0") {
+            let tiff = data + 6;
+            let little_endian = match jpeg.get(tiff..tiff + 2)? {
+                b"II" => true,
+                b"MM" => false,
+                _ => return None,
+            };
+            let offset_bytes: [u8; 4] = jpeg.get(tiff + 4..tiff + 8)?.try_into().ok()?;
+            let first_ifd = if little_endian { u32::from_le_bytes(offset_bytes) } else { u32::from_be_bytes(offset_bytes) };
+            let ifd = tiff + first_ifd as usize;
+            for entry in 0..usize::from(u16_at(ifd, little_endian)?) {
+                let entry = ifd + 2 + 12 * entry;
+                if u16_at(entry, little_endian)? == ORIENTATION_TAG {
+                    return Some((entry + 8, little_endian));
+                }
+            }
+            return None;
+        }
+        position += 2 + length;
+    }
+}
+
+/// The EXIF orientation of a JPEG, 1 to 8; 1 is upright.
+pub fn read_orientation(jpeg: &[u8]) -> Option<u16> {
+    let (offset, little_endian) = orientation_field(jpeg)?;
+    let bytes = [jpeg[offset], jpeg[offset + 1]];
+    Some(if little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+}
+
+/// Overwrites the EXIF orientation in place; returns false when there is none to overwrite.
+pub fn write_orientation(jpeg: &mut [u8], orientation: u16) -> bool {
+    let Some((offset, little_endian)) = orientation_field(jpeg) else {
+        return false;
+    };
+    let bytes = if little_endian { orientation.to_le_bytes() } else { orientation.to_be_bytes() };
+    jpeg[offset..offset + 2].copy_from_slice(&bytes);
+    true
+}
+
+/// Turns the JPEG at `path` the way its EXIF orientation says and marks it upright, keeping
+/// its timestamps. Returns false for JPEGs that were upright already.
+pub fn straighten(path: &Path) -> Result<bool, Box<dyn std::error::Error>> {
+    let jpeg = std::fs::read(path)?;
+    let Some(orientation) = read_orientation(&jpeg).filter(|orientation| (2..=8).contains(orientation)) else {
+        return Ok(false);
+    };
+    let mut turned = turn(&jpeg, orientation)?;
+    write_orientation(&mut turned, 1);
+
+    let metadata = std::fs::metadata(path)?;
+    let temporary_path = path.with_extension("straightening.tmp");
+    std::fs::write(&temporary_path, &turned)
+        .and_then(|()| std::fs::rename(&temporary_path, path))
+        .inspect_err(|_| {
+            let _ = std::fs::remove_file(&temporary_path);
+        })?;
+    filetime::set_file_times(
+        path,
+        filetime::FileTime::from_last_access_time(&metadata),
+        filetime::FileTime::from_last_modification_time(&metadata),
+    )?;
+    Ok(true)
+}
+
+#[cfg(feature = "turbojpeg")]
+fn turn(jpeg: &[u8], orientation: u16) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let op = match orientation {
+        2 => turbojpeg::TransformOp::Hflip,
+        3 => turbojpeg::TransformOp::Rot180,
+        4 => turbojpeg::TransformOp::Vflip,
+        5 => turbojpeg::TransformOp::Transpose,
+        6 => turbojpeg::TransformOp::Rot90,
+        7 => turbojpeg::TransformOp::Transverse,
+        8 => turbojpeg::TransformOp::Rot270,
+        _ => turbojpeg::TransformOp::None,
+    };
+    // A lossless turn cannot move a partial block to the other edge; rather than trimming the
+    // edge off, such images are left as they are
+    let transform = turbojpeg::Transform { op, perfect: true, ..turbojpeg::Transform::default() };
+    Ok(turbojpeg::transform(&transform, jpeg)?.to_vec()) // Markers, EXIF included, are copied over
+}
+
+#[cfg(not(feature = "turbojpeg"))]
+fn turn(_jpeg: &[u8], _orientation: u16) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    Err("this build cannot turn JPEGs without re-encoding them".into())
+}
+
+/// Straightens a batch of exported JPEGs on its own thread.
+pub struct OrientationPass {
+    receiver: Receiver<(PathBuf, Result<bool, String>)>,
+    pub total: usize,
+    pub checked: usize,
+    pub turned: usize,
+    pub failed: Vec<(PathBuf, String)>,
+    pub finished: bool,
+}
+
+impl OrientationPass {
+    pub fn start(files: Vec<PathBuf>, ctx: egui::Context) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let total = files.len();
+        std::thread::Builder::new()
+            .name("orientation".to_string())
+            .spawn(move || {
+                for file in files {
+                    let result = straighten(&file).map_err(|e| e.to_string());
+                    if sender.send((file, result)).is_err() {
+                        break;
+                    }
+                    ctx.request_repaint();
+                }
+                ctx.request_repaint(); // Let the UI notice the closed channel
+            })
+            .expect("failed to spawn orientation thread");
+        Self { receiver, total, checked: 0, turned: 0, failed: Vec::new(), finished: false }
+    }
+
+    /// Collects what the thread has done since the last call.
+    pub fn poll(&mut self) {
+        loop {
+            match self.receiver.try_recv() {
+                Ok((file, result)) => {
+                    self.checked += 1;
+                    match result {
+                        Ok(true) => self.turned += 1,
+                        Ok(false) => {},
+                        Err(e) => {
+                            log::warn!("Could not straighten {}: {}", file.display(), e);
+                            self.failed.push((file, e));
+                        },
+                    }
+                },
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    break;
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{two_color_jpeg, TempDir};
+
+    #[test]
+    fn the_exif_orientation_is_read_and_overwritten_in_place() {
+        let mut jpeg = two_color_jpeg(32, 16, true);
+        assert_eq!(read_orientation(&jpeg), Some(6));
+        assert!(write_orientation(&mut jpeg, 1));
+        assert_eq!(read_orientation(&jpeg), Some(1));
+        let mut upright = two_color_jpeg(32, 16, false);
+        assert_eq!(read_orientation(&upright), None);
+        assert!(!write_orientation(&mut upright, 1));
+    }
+
+    #[cfg(not(feature = "turbojpeg"))]
+    #[test]
+    fn builds_without_libjpeg_turbo_leave_jpegs_alone() {
+        let root = TempDir::new("orientation");
+        let path = root.join("sideways.jpg");
+        let jpeg = two_color_jpeg(32, 16, true);
+        std::fs::write(&path, &jpeg).unwrap();
+        let result = straighten(&path);
+        let after = std::fs::read(&path).unwrap();
+        assert!(result.is_err());
+        assert_eq!(after, jpeg);
+    }
+
+    #[cfg(feature = "turbojpeg")]
+    #[test]
+    fn sideways_jpegs_are_turned_upright_and_marked_as_such() {
+        let root = TempDir::new("orientation");
+        std::fs::write(root.join("sideways.jpg"), two_color_jpeg(32, 16, true)).unwrap();
+        std::fs::write(root.join("upright.jpg"), two_color_jpeg(32, 16, false)).unwrap();
+        std::fs::write(root.join("partial_blocks.jpg"), two_color_jpeg(36, 20, true)).unwrap();
+
+        let turned = straighten(&root.join("sideways.jpg")).unwrap();
+        let untouched = straighten(&root.join("upright.jpg")).unwrap();
+        let uneven = straighten(&root.join("partial_blocks.jpg"));
+        let result = std::fs::read(root.join("sideways.jpg")).unwrap();
+        let straightened = image::load_from_memory(&result).unwrap().to_rgb8();
+        let uneven_after = std::fs::read(root.join("partial_blocks.jpg")).unwrap();
+        assert!(turned);
+        assert!(!untouched);
+        assert_eq!(read_orientation(&result), Some(1));
+        assert_eq!(straightened.dimensions(), (16, 32));
+        assert!(straightened.get_pixel(8, 4)[0] > 200, "the left half should now be on top");
+        assert!(uneven.is_err(), "turning would have cropped the edge");
+        assert_eq!(uneven_after, two_color_jpeg(36, 20, true));
+    }
+}
@@ -0,0 +1,89 @@
+use std::time::{Duration, Instant};
+
+const BREAK_GAP: Duration = Duration::from_secs(5 * 60); // A pause this long counts as a break
+
+/// Decisions made since the last break, to suggest another before tiredness turns into
+/// mistakes. Un-keeping an image counts as an undo, a rough measure of accuracy.
+#[derive(Default)]
+pub struct Stretch {
+    started: Option<Instant>,
+    last_action: Option<Instant>,
+    pub decisions: usize,
+    pub undos: usize,
+    nudged: Option<(Instant, usize)>, // When the last nudge was dismissed, and the decisions up to then
+}
+
+impl Stretch {
+    pub fn record_decision(&mut self, now: Instant) {
+        self.touch(now);
+        self.decisions += 1;
+    }
+
+    pub fn record_undo(&mut self, now: Instant) {
+        self.touch(now);
+        self.undos += 1;
+    }
+
+    /// A long enough pause since the last action starts a new stretch.
+    fn touch(&mut self, now: Instant) {
+        if self.last_action.is_none_or(|last| now.duration_since(last) >= BREAK_GAP) {
+            *self = Self {
+                started: Some(now),
+                ..Self::default()
+            };
+        }
+        self.last_action = Some(now);
+    }
+
+    pub fn duration(&self, now: Instant) -> Duration {
+        self.started.map_or(Duration::ZERO, |started| now.duration_since(started))
+    }
+
+    pub fn undo_rate(&self) -> f64 {
+        if self.decisions == 0 { 0.0 } else { self.undos as f64 / self.decisions as f64 }
+    }
+
+    /// Whether `max_duration` or `max_decisions` has passed, counted from the start of
+    /// the stretch or the last dismissed nudge. A stretch ended by a break is never due.
+    pub fn is_due(&self, now: Instant, max_duration: Duration, max_decisions: usize) -> bool {
+        let (Some(started), Some(last_action)) = (self.started, self.last_action) else {
+            return false;
+        };
+        if now.duration_since(last_action) >= BREAK_GAP {
+            return false;
+        }
+        let (since, decisions_before) = self.nudged.unwrap_or((started, 0));
+        now.duration_since(since) >= max_duration || self.decisions - decisions_before >= max_decisions
+    }
+
+    pub fn dismiss(&mut self, now: Instant) {
+        self.nudged = Some((now, self.decisions));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breaks_are_suggested_after_long_stretches_only() {
+        let start = std::time::Instant::now();
+        let minutes = |n: u64| start + std::time::Duration::from_secs(n * 60);
+        let max_duration = std::time::Duration::from_secs(30 * 60);
+        let mut stretch = Stretch::default();
+        for minute in 0..20 {
+            stretch.record_decision(minutes(minute));
+        }
+        assert!(!stretch.is_due(minutes(20), max_duration, 100));
+        stretch.record_decision(minutes(40)); // Twenty minutes away was a break
+        assert_eq!(stretch.decisions, 1);
+        for minute in 41..75 {
+            stretch.record_decision(minutes(minute));
+        }
+        stretch.record_undo(minutes(75));
+        assert!(stretch.is_due(minutes(75), max_duration, 100));
+        assert!((stretch.undo_rate() - 1.0 / 35.0).abs() < 1e-9);
+        stretch.dismiss(minutes(75));
+        assert!(!stretch.is_due(minutes(76), max_duration, 100));
+    }
+}
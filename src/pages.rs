@@ -0,0 +1,337 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+    time::SystemTime,
+};
+
+use image::{DynamicImage, ImageBuffer};
+use tiff::{
+    decoder::{Decoder, DecodingResult, Limits},
+    tags::Tag,
+    ColorType,
+};
+
+/// Pages of a multi-page TIFF are queued under their file's path with this and the page
+/// number appended, e.g. `scan.tif#page2`, so they can be decided on like files of their own.
+const PAGE_MARKER: &str = "#page";
+
+/// The modified time and size of a file, to tell when it changed.
+type Stamp = (SystemTime, u64);
+
+/// The pages found in each TIFF, with the stamp of the file they were found in. Every page of
+/// a file is decoded on its own, and looking for them means reading every directory.
+type PageCache = HashMap<PathBuf, (Stamp, Vec<Page>)>;
+
+static PAGE_CACHE: LazyLock<Mutex<PageCache>> = LazyLock::new(Default::default);
+
+/// A full-size page: the number of its image directory and where that starts in the file.
+#[derive(Clone, Copy, Debug)]
+struct Page {
+    directory: usize,
+    offset: u64,
+}
+
+pub fn is_tiff(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|ext| matches!(ext.to_lowercase().as_str(), "tif" | "tiff"))
+}
+
+/// The path a page of `file` is queued under; pages count from 1.
+pub fn page_path(file: &Path, page: usize) -> PathBuf {
+    let mut name = file.file_name().unwrap_or_default().to_os_string();
+    name.push(format!("{}{}", PAGE_MARKER, page));
+    file.with_file_name(name)
+}
+
+/// The TIFF file and page number of a page queued on its own, or None for a plain file.
+pub fn split_page_path(path: &Path) -> Option<(PathBuf, usize)> {
+    let name = path.file_name()?.to_str()?;
+    let (file_name, page) = name.rsplit_once(PAGE_MARKER)?;
+    let page = page.parse().ok().filter(|page| *page >= 1)?;
+    let file = path.with_file_name(file_name);
+    is_tiff(&file).then_some((file, page))
+}
+
+pub fn is_page(path: &Path) -> bool {
+    split_page_path(path).is_some()
+}
+
+/// The file on disk behind a queue entry: the TIFF for a page, the path itself otherwise.
+pub fn file_of(path: &Path) -> PathBuf {
+    split_page_path(path).map_or_else(|| path.to_path_buf(), |(file, _)| file)
+}
+
+/// What a scan queues for the TIFF `name` in `folder`: one entry per page if it has several,
+/// or the file itself.
+pub fn queue_names(folder: &Path, name: OsString) -> Vec<OsString> {
+    let file = folder.join(&name);
+    match pages_of(&file) {
+        Ok(pages) if pages.len() > 1 => (1..=pages.len())
+            .map(|page| page_path(&file, page).file_name().unwrap_or_default().to_os_string())
+            .collect(),
+        Ok(_) => vec![name],
+        Err(e) => {
+            log::warn!("Could not count the pages of {}: {}", file.display(), e);
+            vec![name]
+        },
+    }
+}
+
+/// The full-size pages of `file`, in order, from the cache while the file is unchanged.
+fn pages_of(file: &Path) -> Result<Vec<Page>, String> {
+    let metadata = std::fs::metadata(file).map_err(|e| e.to_string())?;
+    let stamp = (metadata.modified().map_err(|e| e.to_string())?, metadata.len());
+    let cache = PAGE_CACHE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some((cached_stamp, pages)) = cache.get(file)
+        && *cached_stamp == stamp
+    {
+        return Ok(pages.clone());
+    }
+    drop(cache); // Other files' pages can be looked up meanwhile
+    let pages = page_directories(file)?;
+    PAGE_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(file.to_path_buf(), (stamp, pages.clone()));
+    Ok(pages)
+}
+
+/// The image directories of `file` holding full-size pages, in order. Reduced-resolution
+/// previews some scanners store next to every page are left out.
+fn page_directories(file: &Path) -> Result<Vec<Page>, String> {
+    let offsets = directory_offsets(file)?;
+    let mut decoder = Decoder::new(BufReader::new(std::fs::File::open(file).map_err(|e| e.to_string())?)).map_err(|e| e.to_string())?;
+    let mut pages = Vec::new();
+    for directory in 0.. {
+        let subfile_type = decoder.find_tag_unsigned::<u32>(Tag::NewSubfileType).map_err(|e| e.to_string())?.unwrap_or(0);
+        if subfile_type & 1 == 0 {
+            let offset = *offsets.get(directory).ok_or("the image directories do not add up")?;
+            pages.push(Page { directory, offset });
+        }
+        if !decoder.more_images() {
+            break;
+        }
+        decoder.next_image().map_err(|e| e.to_string())?;
+    }
+    Ok(pages)
+}
+
+/// How a TIFF lays out its numbers, read from the first 16 bytes of the file.
+struct Header {
+    little_endian: bool,
+    big_tiff: bool, // 8-byte offsets, for files past 4 GB
+    first_directory: u64,
+}
+
+impl Header {
+    fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let little_endian = match bytes.get(..2) {
+            Some(b"II") => true,
+            Some(b"MM") => false,
+            _ => return Err("not a TIFF".to_string()),
+        };
+        let header = Self { little_endian, big_tiff: false, first_directory: 0 };
+        match header.number(bytes.get(2..4).unwrap_or_default()) {
+            42 => Ok(Self { first_directory: header.number(bytes.get(4..8).unwrap_or_default()), ..header }),
+            43 => Ok(Self { big_tiff: true, first_directory: header.number(bytes.get(8..16).unwrap_or_default()), ..header }),
+            _ => Err("not a TIFF".to_string()),
+        }
+    }
+
+    fn number(&self, bytes: &[u8]) -> u64 {
+        let fold = |number: u64, byte: &u8| number << 8 | u64::from(*byte);
+        if self.little_endian { bytes.iter().rev().fold(0, fold) } else { bytes.iter().fold(0, fold) }
+    }
+
+    fn read_number(&self, reader: &mut impl Read, size: usize) -> std::io::Result<u64> {
+        let mut bytes = [0; 8];
+        reader.read_exact(&mut bytes[..size])?;
+        Ok(self.number(&bytes[..size]))
+    }
+
+    /// Where in the header the first directory's offset is kept.
+    fn first_directory_field(&self) -> std::ops::Range<usize> {
+        if self.big_tiff { 8..16 } else { 4..8 }
+    }
+}
+
+/// Where each image directory of `file` starts, following the chain from the header.
+fn directory_offsets(file: &Path) -> Result<Vec<u64>, String> {
+    let mut reader = BufReader::new(std::fs::File::open(file).map_err(|e| e.to_string())?);
+    let mut bytes = [0; 16];
+    reader.read_exact(&mut bytes).map_err(|e| e.to_string())?;
+    let header = Header::parse(&bytes)?;
+    let (count_size, entry_size, offset_size) = if header.big_tiff { (8, 20, 8) } else { (2, 12, 4) };
+    let mut offsets = Vec::new();
+    let mut offset = header.first_directory;
+    while offset != 0 && !offsets.contains(&offset) {
+        // A directory is its entry count, the entries, and the offset of the next one
+        offsets.push(offset);
+        reader.seek(SeekFrom::Start(offset)).map_err(|e| e.to_string())?;
+        let entries = header.read_number(&mut reader, count_size).map_err(|e| e.to_string())?;
+        reader.seek(SeekFrom::Current((entries * entry_size) as i64)).map_err(|e| e.to_string())?;
+        offset = header.read_number(&mut reader, offset_size).map_err(|e| e.to_string())?;
+    }
+    Ok(offsets)
+}
+
+/// Decodes one page of a multi-page TIFF. Pixel formats and compressions the tiff decoder does
+/// not take, such as bilevel fax scans or CMYK prints, are left to the image crate.
+pub fn read_page(file: &Path, page: usize) -> Result<DynamicImage, String> {
+    let pages = pages_of(file)?;
+    let page = page
+        .checked_sub(1)
+        .and_then(|index| pages.get(index).copied())
+        .ok_or_else(|| format!("{} has no page {}", file.display(), page))?;
+    read_directory(file, page.directory).or_else(|e| {
+        log::debug!("Decoding page {} of {} with the image crate: {}", page.directory, file.display(), e);
+        read_with_image_crate(file, page.offset).map_err(|fallback| format!("{}; the image crate: {}", e, fallback))
+    })
+}
+
+fn read_directory(file: &Path, directory: usize) -> Result<DynamicImage, String> {
+    let mut decoder = Decoder::new(BufReader::new(std::fs::File::open(file).map_err(|e| e.to_string())?))
+        .map_err(|e| e.to_string())?
+        .with_limits(Limits::unlimited());
+    decoder.seek_to_image(directory).map_err(|e| e.to_string())?;
+    let (width, height) = decoder.dimensions().map_err(|e| e.to_string())?;
+    let color_type = decoder.colortype().map_err(|e| e.to_string())?;
+    let image = match (color_type, decoder.read_image().map_err(|e| e.to_string())?) {
+        (ColorType::Gray(8), DecodingResult::U8(data)) => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLuma8),
+        (ColorType::Gray(16), DecodingResult::U16(data)) => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLuma16),
+        (ColorType::GrayA(8), DecodingResult::U8(data)) => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLumaA8),
+        (ColorType::GrayA(16), DecodingResult::U16(data)) => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLumaA16),
+        (ColorType::RGB(8), DecodingResult::U8(data)) => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgb8),
+        (ColorType::RGB(16), DecodingResult::U16(data)) => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgb16),
+        (ColorType::RGBA(8), DecodingResult::U8(data)) => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgba8),
+        (ColorType::RGBA(16), DecodingResult::U16(data)) => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgba16),
+        (color_type, _) => return Err(format!("unsupported pixel format {:?}", color_type)),
+    };
+    image.ok_or_else(|| "the page holds fewer pixels than it claims".to_string())
+}
+
+/// Decodes the directory at `offset` with the image crate. It only ever reads a file's first
+/// directory, so it is handed the file with its header pointing at the page instead.
+fn read_with_image_crate(file: &Path, offset: u64) -> Result<DynamicImage, String> {
+    let mut bytes = std::fs::read(file).map_err(|e| e.to_string())?;
+    let header = Header::parse(&bytes)?;
+    let field = header.first_directory_field();
+    let width = field.len();
+    let offset = if header.little_endian { offset.to_le_bytes()[..width].to_vec() } else { offset.to_be_bytes()[8 - width..].to_vec() };
+    bytes[field].copy_from_slice(&offset);
+    image::load_from_memory_with_format(&bytes, image::ImageFormat::Tiff).map_err(|e| e.to_string())
+}
+
+/// Where a kept page goes: its own TIFF named after the file and page, e.g. `scan_page2.tif`.
+pub fn export_path(destination: &Path) -> PathBuf {
+    match split_page_path(destination) {
+        Some((file, page)) => {
+            let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+            let extension = file.extension().unwrap_or_default().to_string_lossy();
+            file.with_file_name(format!("{}_page{}.{}", stem, page, extension))
+        },
+        None => destination.to_path_buf(),
+    }
+}
+
+/// Writes the page queued as `page_path` out as a TIFF of its own at `destination`.
+pub fn write_page(page_path: &Path, destination: &Path) -> Result<(), String> {
+    let (file, page) = split_page_path(page_path).ok_or_else(|| format!("{} is not a page", page_path.display()))?;
+    read_page(&file, page)?
+        .save_with_format(destination, image::ImageFormat::Tiff)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    /// A TIFF of 8x2 black-and-white pages, one byte per row and uncompressed, the way image
+    /// encoders never write them but fax software does.
+    fn bilevel_tiff(pages: &[[u8; 2]]) -> Vec<u8> {
+        let mut bytes = b"II*\0\0\0\0\0".to_vec();
+        let mut link = 4; // Where the offset of the next directory goes
+        for rows in pages {
+            let strip = bytes.len() as u32;
+            bytes.extend(rows);
+            let directory = bytes.len() as u32;
+            bytes[link..link + 4].copy_from_slice(&directory.to_le_bytes());
+            let entries: [(u16, u16, u32); 8] = [(256, 3, 8), (257, 3, 2), (258, 3, 1), (259, 3, 1), (262, 3, 1), (273, 4, strip), (278, 3, 2), (279, 4, 2)];
+            bytes.extend((entries.len() as u16).to_le_bytes());
+            for (tag, kind, value) in entries {
+                bytes.extend(tag.to_le_bytes());
+                bytes.extend(kind.to_le_bytes());
+                bytes.extend(1u32.to_le_bytes());
+                bytes.extend(value.to_le_bytes());
+            }
+            link = bytes.len();
+            bytes.extend(0u32.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn bilevel_and_cmyk_pages_are_decoded_through_the_image_crate() {
+        let root = TempDir::new("page_formats");
+        let fax = root.join("fax.tif");
+        std::fs::write(&fax, bilevel_tiff(&[[0; 2], [0b1111_0000, 0b0000_1111]])).unwrap();
+        let print = root.join("print.tif");
+        let mut encoder = tiff::encoder::TiffEncoder::new(std::fs::File::create(&print).unwrap()).unwrap();
+        for cmyk in [[0, 0, 0, 255], [255, 0, 0, 0]] {
+            encoder.write_image::<tiff::encoder::colortype::CMYK8>(2, 2, &cmyk.repeat(4)).unwrap();
+        }
+        drop(encoder);
+
+        let fax_page = read_page(&fax, 2).map(|page| page.to_luma8());
+        let print_page = read_page(&print, 2).map(|page| page.to_rgb8());
+
+        let fax_page = fax_page.expect("the bilevel page decodes");
+        assert_eq!(fax_page.dimensions(), (8, 2));
+        assert_eq!([fax_page.get_pixel(0, 0).0[0], fax_page.get_pixel(7, 0).0[0]], [255, 0], "the second page, not the first");
+        assert_eq!([fax_page.get_pixel(0, 1).0[0], fax_page.get_pixel(7, 1).0[0]], [0, 255]);
+        assert_eq!(print_page.expect("the CMYK page decodes").get_pixel(1, 1), &image::Rgb([0, 255, 255]));
+    }
+
+    #[test]
+    fn the_pages_of_a_file_are_found_again_once_it_changes() {
+        let root = TempDir::new("page_cache");
+        let fax = root.join("fax.tif");
+        std::fs::write(&fax, bilevel_tiff(&[[0; 2], [255; 2]])).unwrap();
+        let before = pages_of(&fax).map(|pages| pages.len());
+        let cached = pages_of(&fax).map(|pages| pages.len());
+        std::fs::write(&fax, bilevel_tiff(&[[0; 2], [255; 2], [0; 2]])).unwrap();
+        let after = pages_of(&fax).map(|pages| pages.len());
+        assert_eq!((before, cached, after), (Ok(2), Ok(2), Ok(3)));
+    }
+
+    #[test]
+    fn multi_page_tiffs_are_reviewed_and_exported_page_by_page() {
+        let root = TempDir::new("pages");
+        let scan = root.join("scan.tif");
+        let mut encoder = tiff::encoder::TiffEncoder::new(std::fs::File::create(&scan).unwrap()).unwrap();
+        for shade in [10u8, 120, 240] {
+            encoder.write_image::<tiff::encoder::colortype::RGB8>(4, 3, &[shade; 4 * 3 * 3]).unwrap();
+        }
+        drop(encoder);
+
+        let names = queue_names(&root, OsString::from("scan.tif"));
+        let second = root.join(&names[1]);
+        let options = crate::decode::DecodeOptions { max_dimension: None, jpeg_backend: crate::decode::JpegBackend::default() };
+        let decoded = crate::decode::decode_image(&second, &options);
+        let exported = export_path(&root.join("kept").join(&names[1]));
+        std::fs::create_dir_all(root.join("kept")).unwrap();
+        let written = write_page(&second, &exported).map(|()| image::open(&exported).map(|image| image.to_rgb8()));
+
+        assert_eq!(names, ["scan.tif#page1", "scan.tif#page2", "scan.tif#page3"].map(OsString::from));
+        let decoded = decoded.expect("the page decodes");
+        assert_eq!(decoded.size, [4, 3]);
+        assert_eq!(decoded.pixels[0], egui::Color32::from_rgb(120, 120, 120), "the second page, not the first");
+        assert_eq!(exported.file_name().unwrap(), "scan_page2.tif");
+        assert_eq!(written.unwrap().unwrap().get_pixel(0, 0), &image::Rgb([120, 120, 120]));
+    }
+}
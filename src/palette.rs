@@ -0,0 +1,115 @@
+use eframe::egui;
+
+pub const PALETTE_SIZE: usize = 5;
+const MAX_SAMPLES: usize = 20_000; // Pixels looked at per image; the palette does not need every one
+const SWATCH_SIZE: f32 = 18.0;
+
+/// One of the main colors of an image and how much of it is that color.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Swatch {
+    pub color: egui::Color32,
+    pub share: f32, // 0 to 1
+}
+
+/// Up to `count` main colors of `image`, most common first, by a variant of median cut:
+/// box by box, the pixels are split at the middle of the channel they vary most in, and
+/// every box is averaged into one color. Splitting by value rather than at the median
+/// keeps pixels of one color together, so a large area does not turn up twice.
+pub fn dominant_colors(image: &egui::ColorImage, count: usize) -> Vec<Swatch> {
+    let step = image.pixels.len().div_ceil(MAX_SAMPLES).max(1);
+    let samples: Vec<[u8; 3]> = image
+        .pixels
+        .iter()
+        .step_by(step)
+        .filter(|pixel| pixel.a() > 0)
+        .map(|pixel| [pixel.r(), pixel.g(), pixel.b()])
+        .collect();
+    if samples.is_empty() {
+        return Vec::new();
+    }
+    let total = samples.len() as f32;
+
+    let mut boxes = vec![samples];
+    while boxes.len() < count {
+        // The box spanning the widest range of one channel is split next
+        let Some((index, channel, range)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, pixels)| pixels.len() > 1)
+            .map(|(index, pixels)| {
+                let (channel, range) = (0..3).map(|channel| (channel, channel_range(pixels, channel))).max_by_key(|(_, range)| *range).unwrap_or((0, 0));
+                (index, channel, range)
+            })
+            .filter(|(_, _, range)| *range > 0)
+            .max_by_key(|(_, _, range)| *range)
+        else {
+            break; // Every box is a single color
+        };
+        let pixels = boxes.swap_remove(index);
+        let low = pixels.iter().map(|pixel| pixel[channel]).min().unwrap_or(0);
+        let middle = low + range / 2;
+        let (lower, upper): (Vec<_>, Vec<_>) = pixels.into_iter().partition(|pixel| pixel[channel] <= middle);
+        boxes.push(lower);
+        boxes.push(upper);
+    }
+
+    let mut swatches: Vec<Swatch> = boxes
+        .iter()
+        .map(|pixels| {
+            let sums = pixels.iter().fold([0u64; 3], |sums, pixel| [0, 1, 2].map(|channel| sums[channel] + u64::from(pixel[channel])));
+            let [r, g, b] = sums.map(|sum| (sum / pixels.len() as u64) as u8);
+            Swatch { color: egui::Color32::from_rgb(r, g, b), share: pixels.len() as f32 / total }
+        })
+        .collect();
+    swatches.sort_by(|a, b| b.share.total_cmp(&a.share));
+    swatches
+}
+
+fn channel_range(pixels: &[[u8; 3]], channel: usize) -> u8 {
+    let (low, high) = pixels.iter().fold((u8::MAX, u8::MIN), |(low, high), pixel| (low.min(pixel[channel]), high.max(pixel[channel])));
+    high.saturating_sub(low)
+}
+
+/// Draws the palette as a row of squares into the bottom right corner of the visible part
+/// of `image_rect`.
+pub fn paint_swatches(ui: &egui::Ui, image_rect: egui::Rect, swatches: Option<&[Swatch]>) {
+    let Some(swatches) = swatches.filter(|swatches| !swatches.is_empty()) else {
+        return;
+    };
+    let visible = image_rect.intersect(ui.clip_rect());
+    let size = egui::vec2(SWATCH_SIZE * swatches.len() as f32, SWATCH_SIZE);
+    let row = egui::Rect::from_min_size(visible.right_bottom() - egui::vec2(8.0, 8.0) - size, size);
+    ui.painter().rect_filled(row.expand(2.0), 4.0, egui::Color32::from_black_alpha(160));
+    for (index, swatch) in swatches.iter().enumerate() {
+        let square = egui::Rect::from_min_size(row.min + egui::vec2(SWATCH_SIZE * index as f32, 0.0), egui::Vec2::splat(SWATCH_SIZE));
+        ui.painter().rect_filled(square.shrink(1.0), 2.0, swatch.color);
+    }
+}
+
+/// Draws the palette as a band along the bottom of `rect`, every color as wide as its share.
+pub fn paint_band(painter: &egui::Painter, rect: egui::Rect, swatches: &[Swatch]) {
+    let mut left = rect.left();
+    for swatch in swatches {
+        let right = left + rect.width() * swatch.share;
+        painter.rect_filled(egui::Rect::from_x_y_ranges(left..=right, rect.y_range()), 0.0, swatch.color);
+        left = right;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palettes_find_the_main_colors_by_share() {
+        let (red, blue) = (egui::Color32::from_rgb(220, 20, 20), egui::Color32::from_rgb(20, 20, 220));
+        // A quarter red, the rest blue
+        let pixels = (0..100 * 100).map(|index| if index % 100 < 25 { red } else { blue }).collect();
+        let image = egui::ColorImage { size: [100, 100], source_size: egui::vec2(100.0, 100.0), pixels };
+        let swatches = dominant_colors(&image, PALETTE_SIZE);
+        assert_eq!(swatches.len(), 2, "no more colors than the image has");
+        assert_eq!(swatches[0].color, blue, "most common first");
+        assert_eq!(swatches[1].color, red);
+        assert!((swatches[1].share - 0.25).abs() < 0.01);
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Narrows the queue down to the images whose path contains a search term. The others are
+/// set aside undecided and go back to their places when the filter is cleared.
+#[derive(Default)]
+pub struct QueueFilter {
+    pub text: String, // As typed into the search box
+    applied: String, // Lowercased term the queue is narrowed down by; empty when unfiltered
+    hidden: Vec<PathBuf>, // Queued images that do not match
+    positions: HashMap<PathBuf, usize>, // Queue order when the filter was applied, for putting it back
+}
+
+impl QueueFilter {
+    pub fn is_active(&self) -> bool {
+        !self.applied.is_empty()
+    }
+
+    pub fn hidden(&self) -> &[PathBuf] {
+        &self.hidden
+    }
+
+    fn matches(term: &str, path: &Path) -> bool {
+        path.to_string_lossy().to_lowercase().contains(term)
+    }
+
+    /// Narrows `queue` down by the current text, after putting back what an earlier term set aside.
+    pub fn apply(&mut self, queue: &mut Vec<PathBuf>) {
+        self.clear(queue);
+        let term = self.text.trim().to_lowercase();
+        if term.is_empty() {
+            return;
+        }
+        self.positions = queue.iter().enumerate().map(|(index, image)| (image.clone(), index)).collect();
+        let (shown, hidden) = std::mem::take(queue).into_iter().partition(|image| Self::matches(&term, image));
+        *queue = shown;
+        self.hidden = hidden;
+        self.applied = term;
+    }
+
+    /// Puts the images set aside back into `queue`, where they were. Images queued while
+    /// filtering that were not queued before, e.g. taken-back decisions, come first.
+    pub fn clear(&mut self, queue: &mut Vec<PathBuf>) {
+        *queue = self.full_queue(queue);
+        self.hidden.clear();
+        self.positions.clear();
+        self.applied.clear();
+    }
+
+    /// The queue as it would be without the filter.
+    pub fn full_queue(&self, queue: &[PathBuf]) -> Vec<PathBuf> {
+        let mut full: Vec<PathBuf> = queue.iter().chain(&self.hidden).cloned().collect();
+        if self.is_active() {
+            full.sort_by_key(|image| self.positions.get(image).map_or(0, |position| position + 1)); // Stable
+        }
+        full
+    }
+
+    /// Sets a newly found image aside unless it matches; returns it if it belongs in the queue.
+    pub fn admit(&mut self, image: PathBuf) -> Option<PathBuf> {
+        if !self.is_active() {
+            return Some(image);
+        }
+        let position = self.positions.len();
+        self.positions.insert(image.clone(), position); // After everything queued so far
+        if Self::matches(&self.applied, &image) {
+            Some(image)
+        } else {
+            self.hidden.push(image);
+            None
+        }
+    }
+
+    /// Forgets the filter along with the images set aside, for when the queue is rebuilt.
+    pub fn reset(&mut self) {
+        self.text.clear();
+        self.applied.clear();
+        self.hidden.clear();
+        self.positions.clear();
+    }
+}
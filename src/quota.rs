@@ -0,0 +1,26 @@
+use std::{collections::HashMap, path::PathBuf};
+
+/// Splits `images` folder by folder into the best `fraction` by score and the rest.
+/// Every folder keeps at least one image; images without a score count as worst.
+pub fn split_by_quota(images: &[PathBuf], scores: &HashMap<PathBuf, f64>, fraction: f64) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let mut folders: Vec<Vec<&PathBuf>> = Vec::new();
+    let mut folder_indexes: HashMap<Option<&std::path::Path>, usize> = HashMap::new();
+    for image in images {
+        let index = *folder_indexes.entry(image.parent()).or_insert_with(|| {
+            folders.push(Vec::new());
+            folders.len() - 1
+        });
+        folders[index].push(image);
+    }
+
+    let mut keep = Vec::new();
+    let mut discard = Vec::new();
+    for mut folder_images in folders {
+        let score = |image: &PathBuf| scores.get(image).copied().unwrap_or(f64::NEG_INFINITY);
+        folder_images.sort_by(|a, b| score(b).total_cmp(&score(a))); // Stable, so ties keep queue order
+        let keep_count = ((folder_images.len() as f64 * fraction).ceil() as usize).clamp(1, folder_images.len());
+        keep.extend(folder_images[..keep_count].iter().map(|image| (*image).clone()));
+        discard.extend(folder_images[keep_count..].iter().map(|image| (*image).clone()));
+    }
+    (keep, discard)
+}
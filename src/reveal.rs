@@ -0,0 +1,74 @@
+use std::{path::Path, process::Command};
+
+/// Opens the OS file manager at the folder containing `path` with the file selected.
+/// Where selecting is not supported, the folder is just opened.
+pub fn reveal_file(path: &Path) {
+    if let Err(e) = select_in_file_manager(path) {
+        log::info!("Could not select {} in the file manager ({}), opening its folder instead", path.display(), e);
+        let Some(folder) = path.parent() else {
+            return;
+        };
+        if let Err(e) = open::that(folder) {
+            log::warn!("Could not open {}: {}", folder.display(), e);
+        }
+    }
+}
+
+#[cfg(windows)]
+fn select_in_file_manager(path: &Path) -> std::io::Result<()> {
+    // Explorer exits with 1 even when it worked, so only a failed spawn counts
+    let mut argument = std::ffi::OsString::from("/select,");
+    argument.push(path);
+    Command::new("explorer").arg(argument).spawn().map(|_| ())
+}
+
+#[cfg(target_os = "macos")]
+fn select_in_file_manager(path: &Path) -> std::io::Result<()> {
+    check(Command::new("open").arg("-R").arg(path).status()?)
+}
+
+/// Nautilus, Dolphin, Nemo and most other Linux file managers implement the
+/// freedesktop FileManager1 D-Bus interface for exactly this.
+#[cfg(not(any(windows, target_os = "macos")))]
+fn select_in_file_manager(path: &Path) -> std::io::Result<()> {
+    let path = std::fs::canonicalize(path)?;
+    let status = Command::new("dbus-send")
+        .args([
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.FileManager1",
+            "--type=method_call",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .arg(format!("array:string:{}", file_uri(&path)))
+        .arg("string:")
+        .stdout(std::process::Stdio::null())
+        .status()?;
+    check(status)
+}
+
+#[cfg(not(windows))]
+fn check(status: std::process::ExitStatus) -> std::io::Result<()> {
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!("file manager exited with {}", status)))
+    }
+}
+
+/// `file://` URI for an absolute path, percent-encoding everything but unreserved characters.
+#[cfg(not(any(windows, target_os = "macos")))]
+fn file_uri(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut uri = String::from("file://");
+    for &byte in path.as_os_str().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
@@ -0,0 +1,69 @@
+use std::path::Path;
+
+/// Whether any rule matches `relative_path`, the image's path below the folder it was found in.
+pub fn matches_any(rules: &[String], relative_path: &Path) -> bool {
+    let relative_path = relative_path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+        .to_lowercase();
+    rules.iter().any(|rule| matches(rule, &relative_path))
+}
+
+/// Rules with `*` or `?` are globs over the whole relative path (`*` also crosses folders);
+/// anything else only needs to appear somewhere in it. Case never matters.
+fn matches(rule: &str, relative_path: &str) -> bool {
+    let rule = rule.trim().replace('\\', "/").to_lowercase();
+    if rule.is_empty() {
+        return false;
+    }
+    if rule.contains(['*', '?']) {
+        glob_matches(&rule.chars().collect::<Vec<_>>(), &relative_path.chars().collect::<Vec<_>>())
+    } else {
+        relative_path.contains(&rule)
+    }
+}
+
+/// Iterative wildcard matching that backtracks only to the most recent `*`.
+fn glob_matches(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut last_star: Option<(usize, usize)> = None; // Pattern index after the star, text index it matched up to
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                last_star = Some((p + 1, t));
+                p += 1;
+            },
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            },
+            _ => match last_star {
+                Some((after_star, matched_to)) => {
+                    p = after_star;
+                    t = matched_to + 1;
+                    last_star = Some((after_star, matched_to + 1));
+                },
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_keep_rules_match_names_and_globs() {
+        let rules = vec!["final".to_string(), "selects/*.jpg".to_string()];
+        assert!(matches_any(&rules, std::path::Path::new("day1/IMG_1_FINAL.jpg")));
+        assert!(matches_any(&rules, std::path::Path::new("selects/IMG_2.JPG")));
+        assert!(!matches_any(&rules, std::path::Path::new("day1/selects/IMG_3.jpg")));
+        assert!(!matches_any(&rules, std::path::Path::new("day1/IMG_4.jpg")));
+        assert!(matches_any(&["*/selects/*".to_string()], std::path::Path::new("day1/selects/IMG_3.jpg")));
+        assert!(!matches_any(&["  ".to_string()], std::path::Path::new("day1/IMG_4.jpg")));
+    }
+}
@@ -0,0 +1,492 @@
+use std::{
+    ffi::OsString,
+    fs::DirEntry,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+};
+
+use eframe::egui;
+
+use crate::{
+    exif_info::ExifInfo,
+    metadata::{self, XmpFields},
+    settings::{ImageOrder, RawOnlyFolders, Settings},
+};
+
+const RAW_EXTENSIONS: &[&str] = &["cr3", "cr2", "nef", "nrw", "arw", "dng", "raf", "orf", "rw2", "pef"];
+
+/// Files and folders that operating systems and NAS boxes drop into photo folders.
+const SYSTEM_FILE_NAMES: &[&str] = &[
+    "Thumbs.db",
+    "ehthumbs.db",
+    "desktop.ini",
+    "$RECYCLE.BIN",
+    "System Volume Information",
+    "@eaDir", // Synology thumbnail folders
+    "Icon\r",  // macOS custom folder icon
+];
+
+#[derive(Default)]
+pub struct FileSysNode {
+    pub images: Vec<OsString>,
+    pub children: Vec<FileSysNode>,
+    pub name: OsString,
+}
+
+#[derive(Clone, Default)]
+pub struct ScanOptions {
+    pub skip_hidden: bool,
+    pub fast: bool, // Trust the directory listing and file extensions instead of reading every entry's metadata or contents
+    pub excluded_folders: Vec<String>, // Lowercase paths below the scan root with `/` separators
+    pub sniff_extensionless: bool, // Read the start of files without an extension to find JPEGs among them
+    pub order: ImageOrder,
+    pub read_xmp: bool, // Pick up ratings and labels from existing sidecars
+    pub raw_only: RawOnlyFolders,
+    pub tiff_pages: bool, // Queue TIFFs too, with multi-page ones split into their pages
+}
+
+impl ScanOptions {
+    /// The configured exclusions plus every bucket's output folder, so copies made by an
+    /// earlier session are never queued again.
+    pub fn from_settings(settings: &Settings) -> Self {
+        let output_folders = settings.buckets.iter().map(|bucket| &bucket.output_folder).chain([&settings.quarantine_folder]);
+        Self {
+            skip_hidden: settings.skip_hidden_files,
+            fast: settings.fast_scan,
+            sniff_extensionless: settings.sniff_extensionless,
+            order: settings.image_order,
+            read_xmp: settings.read_xmp_sidecars,
+            raw_only: settings.raw_only_folders,
+            tiff_pages: settings.review_tiff_pages,
+            excluded_folders: settings
+                .excluded_folders
+                .iter()
+                .chain(output_folders)
+                .map(|folder| folder.trim().replace('\\', "/").trim_matches('/').to_lowercase())
+                .filter(|folder| !folder.is_empty())
+                .collect(),
+        }
+    }
+
+    fn is_excluded(&self, relative_path: &[OsString], name: &OsString) -> bool {
+        if self.excluded_folders.is_empty() {
+            return false;
+        }
+        let path = relative_path
+            .iter()
+            .chain(std::iter::once(name))
+            .map(|component| component.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+            .to_lowercase();
+        self.excluded_folders.contains(&path)
+    }
+}
+
+/// One folder's images, sent by a background scan as soon as the folder has been read.
+pub struct ScannedFolder {
+    pub relative_path: Vec<OsString>, // Folder names from the scan root down; empty for the root itself
+    pub images: Vec<OsString>,
+    pub sidecars: Vec<(OsString, XmpFields)>, // Images whose XMP sidecar has a rating or label
+    pub raw_only: usize, // RAW files in a folder without JPEGs; among `images` when reviewed by their previews
+    pub provisional_order: bool, // In name order, as a fast scan does not read the dates or sizes to sort by
+}
+
+/// Builds the tree of images below `path` the way the background scan does, but in one go.
+/// Unreadable folders and entries are skipped.
+#[cfg(test)]
+pub fn scan_folder(path: &Path, options: &ScanOptions) -> FileSysNode {
+    let (sender, receiver) = mpsc::channel();
+    stream_folder(path, &mut Vec::new(), options, &sender, &egui::Context::default());
+    drop(sender);
+    let mut root_node = FileSysNode {
+        name: path.as_os_str().to_os_string(),
+        ..FileSysNode::default()
+    };
+    for folder in receiver {
+        root_node.insert_folder(&folder.relative_path, folder.images);
+    }
+    root_node
+}
+
+/// Walks `path` on a background thread and sends every folder as soon as it has been read,
+/// in the same order `get_images_depth_first_current_priority` lists them. The channel
+/// closes when the walk is done; dropping the receiver stops the walk early.
+pub fn spawn_scan(path: PathBuf, options: ScanOptions, ctx: egui::Context) -> Receiver<ScannedFolder> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("scan".to_string())
+        .spawn(move || {
+            stream_folder(&path, &mut Vec::new(), &options, &sender, &ctx);
+            ctx.request_repaint(); // Let the UI notice the closed channel
+        })
+        .expect("failed to spawn scan thread");
+    receiver
+}
+
+/// Returns false once nobody is listening anymore.
+fn stream_folder(
+    dir_path: &Path,
+    relative_path: &mut Vec<OsString>,
+    options: &ScanOptions,
+    sender: &Sender<ScannedFolder>,
+    ctx: &egui::Context,
+) -> bool {
+    let (images, folders, raw_only) = read_folder(dir_path, relative_path, options);
+    let sidecars = if options.read_xmp {
+        images
+            .iter()
+            .filter_map(|image| Some((image.clone(), metadata::read_xmp_sidecar(&dir_path.join(image))?)))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let provisional_order = options.fast && options.order != ImageOrder::Name && !images.is_empty();
+    let folder = ScannedFolder {
+        relative_path: relative_path.clone(),
+        images,
+        sidecars,
+        raw_only,
+        provisional_order,
+    };
+    if sender.send(folder).is_err() {
+        return false;
+    }
+    ctx.request_repaint();
+    for name in folders {
+        let child_path = dir_path.join(&name);
+        relative_path.push(name);
+        let listening = stream_folder(&child_path, relative_path, options, sender, ctx);
+        relative_path.pop();
+        if !listening {
+            return false;
+        }
+    }
+    true
+}
+
+/// The supported images and the subfolders directly inside `dir_path`, which is at
+/// `relative_path` below the scan root, and how many RAW files it holds if it has no JPEGs.
+/// Images come in the configured order (by name in a fast scan), folders by name, so the
+/// queue does not depend on the order the OS lists them in.
+fn read_folder(dir_path: &Path, relative_path: &[OsString], options: &ScanOptions) -> (Vec<OsString>, Vec<OsString>, usize) {
+    let mut images = Vec::new();
+    let mut raws = Vec::new();
+    let mut folders = Vec::new();
+    let Ok(entries) = dir_path.read_dir() else {
+        return (images, folders, 0);
+    };
+    for entry in entries.flatten() {
+        let is_dir = if options.fast {
+            // Most platforms report the entry type with the listing, so this needs no extra I/O.
+            // Anything else about the file, down to its pages, is only found out once it is displayed.
+            if options.skip_hidden && has_hidden_name(&entry) {
+                continue;
+            }
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            file_type.is_dir()
+        } else {
+            let Ok(metadata) = entry.metadata() else {
+                continue; // Silently ignore metadata errors
+            };
+            if options.skip_hidden && is_hidden_or_system(&entry, &metadata) {
+                continue;
+            }
+            metadata.is_dir()
+        };
+        if is_dir {
+            let name = entry.file_name();
+            if !options.is_excluded(relative_path, &name) {
+                folders.push(name);
+            }
+        } else {
+            let path = entry.path();
+            let is_image = match path.extension() {
+                Some(_) => is_supported_image(&path),
+                None => options.sniff_extensionless && !options.fast && crate::decode::has_jpeg_signature(&path),
+            };
+            if is_image || (options.tiff_pages && options.fast && crate::pages::is_tiff(&path)) {
+                images.push(entry.file_name()); // A fast scan queues TIFFs whole, as counting their pages reads them
+            } else if options.tiff_pages && crate::pages::is_tiff(&path) {
+                images.extend(crate::pages::queue_names(dir_path, entry.file_name()));
+            } else if is_raw(&path) {
+                raws.push(entry.file_name());
+            }
+        }
+    }
+    // RAW files next to JPEGs are their sidecars; alone, they are the shoot
+    let raw_only = if images.is_empty() { raws.len() } else { 0 };
+    if raw_only > 0 && options.raw_only == RawOnlyFolders::Preview {
+        images = raws;
+    }
+    sort_images(&mut images, dir_path, if options.fast { ImageOrder::Name } else { options.order });
+    folders.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+    (images, folders, raw_only)
+}
+
+fn sort_images(images: &mut [OsString], dir_path: &Path, order: ImageOrder) {
+    images.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+    match order {
+        ImageOrder::Name => {},
+        // Stable sorts, so ties stay in name order
+        ImageOrder::Date => images.sort_by_cached_key(|name| {
+            let path = crate::pages::file_of(&dir_path.join(name)); // Pages of a file share its date
+            let captured_at = ExifInfo::read(&path).and_then(|exif| exif.captured_at);
+            let date = captured_at.or_else(|| {
+                let modified = std::fs::metadata(&path).and_then(|metadata| metadata.modified()).ok()?;
+                Some(modified.duration_since(std::time::UNIX_EPOCH).ok()?.as_secs_f64())
+            });
+            (date.is_none(), date.map(|seconds| (seconds * 1000.0) as i64)) // Undated images last
+        }),
+        ImageOrder::Size => images.sort_by_cached_key(|name| {
+            std::fs::metadata(crate::pages::file_of(&dir_path.join(name))).map_or(0, |metadata| metadata.len())
+        }),
+    }
+}
+
+/// Compares names case-insensitively, with runs of digits compared by their value.
+pub fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return std::cmp::Ordering::Equal,
+            (None, Some(_)) => return std::cmp::Ordering::Less,
+            (Some(_), None) => return std::cmp::Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let take_number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                        digits.push(digit);
+                    }
+                    digits
+                };
+                let (x, y) = (take_number(&mut a), take_number(&mut b));
+                let (x_value, y_value) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let ordering = x_value.len().cmp(&y_value.len()).then_with(|| x_value.cmp(y_value)).then_with(|| x.len().cmp(&y.len()));
+                if ordering.is_ne() {
+                    return ordering;
+                }
+            },
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering.is_ne() {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            },
+        }
+    }
+}
+
+/// Camera RAW formats that carry a full-size JPEG preview.
+pub fn is_raw(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|ext| RAW_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+fn is_supported_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|ext| matches!(ext.to_lowercase().as_str(), "jpg" | "jpeg"))
+}
+
+/// Dotfiles (including `._` AppleDouble files), well-known system files and
+/// anything the platform marks as hidden.
+fn is_hidden_or_system(entry: &DirEntry, metadata: &std::fs::Metadata) -> bool {
+    has_hidden_name(entry) || has_hidden_attribute(metadata)
+}
+
+fn has_hidden_name(entry: &DirEntry) -> bool {
+    let name = entry.file_name();
+    let name = name.to_string_lossy();
+    name.starts_with('.') || SYSTEM_FILE_NAMES.iter().any(|system| name.eq_ignore_ascii_case(system))
+}
+
+#[cfg(windows)]
+fn has_hidden_attribute(metadata: &std::fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+    metadata.file_attributes() & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0
+}
+
+#[cfg(target_os = "macos")]
+fn has_hidden_attribute(metadata: &std::fs::Metadata) -> bool {
+    use std::os::macos::fs::MetadataExt;
+    const UF_HIDDEN: u32 = 0x8000;
+    metadata.st_flags() & UF_HIDDEN != 0
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn has_hidden_attribute(_metadata: &std::fs::Metadata) -> bool {
+    false // Other Unixes only know the dotfile convention
+}
+
+impl FileSysNode {
+    /// Hangs a streamed folder into the tree, creating any folders on the way that have not
+    /// arrived yet. Folders keep the order they arrive in.
+    pub fn insert_folder(&mut self, relative_path: &[OsString], images: Vec<OsString>) {
+        let mut node = self;
+        for name in relative_path {
+            let index = match node.children.iter().position(|child| child.name == *name) {
+                Some(index) => index,
+                None => {
+                    node.children.push(FileSysNode {
+                        name: name.clone(),
+                        ..FileSysNode::default()
+                    });
+                    node.children.len() - 1
+                },
+            };
+            node = &mut node.children[index];
+        }
+        node.images.extend(images);
+    }
+
+    /// The folder at `relative_path` below this one, if it has been found.
+    pub fn find_folder(&self, relative_path: &Path) -> Option<&FileSysNode> {
+        relative_path.iter().try_fold(self, |node, name| node.children.iter().find(|child| child.name == name))
+    }
+
+    pub fn count_images(&self) -> usize {
+        let mut count = self.images.len();
+        for child in &self.children {
+            count += child.count_images();
+        }
+        count
+    }
+
+    pub fn get_images_depth_first_current_priority(&self, base_path: &Path) -> Vec<PathBuf> {
+        let mut all_images = Vec::new();
+
+        // First, add all images from the current directory
+        for image in &self.images {
+            let image_path = base_path.join(image);
+            all_images.push(image_path);
+        }
+
+        // Then, recursively add images from subdirectories (depth-first)
+        for child in &self.children {
+            let child_path = base_path.join(&child.name);
+            all_images.extend(child.get_images_depth_first_current_priority(&child_path));
+        }
+
+        all_images
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TempDir, FIXTURE};
+
+    #[test]
+    fn streamed_folders_build_the_scanned_tree() {
+        let folders: [(&[&str], &[&str]); 4] = [
+            (&[], &["a.jpg"]),
+            (&["day1"], &["b.jpg", "c.jpg"]),
+            (&["day1", "raw"], &[]),
+            (&["day2"], &["d.jpg"]),
+        ];
+        let mut tree = FileSysNode::default();
+        let mut queue = Vec::new();
+        for (relative_path, images) in folders {
+            let relative_path: Vec<OsString> = relative_path.iter().map(OsString::from).collect();
+            let folder_path = relative_path.iter().fold(std::path::PathBuf::from("shoot"), |path, name| path.join(name));
+            queue.extend(images.iter().map(|image| folder_path.join(image)));
+            tree.insert_folder(&relative_path, images.iter().map(OsString::from).collect());
+        }
+        assert_eq!(tree.get_images_depth_first_current_priority(std::path::Path::new("shoot")), queue);
+        assert_eq!(tree.children.len(), 2);
+        assert_eq!(tree.children[0].children[0].name, "raw");
+    }
+
+    #[test]
+    fn scans_leave_out_earlier_output() {
+        let root = TempDir::new("scan");
+        for folder in ["day1", "kept_images/day1", "Portfolio", "day1/tagged"] {
+            std::fs::create_dir_all(root.join(folder)).unwrap();
+            std::fs::write(root.join(folder).join("a.jpg"), b"").unwrap();
+        }
+        let mut settings = Settings::default();
+        settings.buckets[1].output_folder = "portfolio/".to_string();
+        let tree = scan_folder(&root, &ScanOptions::from_settings(&settings));
+        let mut images = tree.get_images_depth_first_current_priority(std::path::Path::new(""));
+        images.sort();
+        // Exclusions are paths from the root, so a "tagged" folder deeper down is still scanned
+        assert_eq!(images, ["day1/a.jpg", "day1/tagged/a.jpg"].map(std::path::PathBuf::from));
+    }
+
+    #[test]
+    fn extensionless_jpegs_are_found_by_their_signature() {
+        let root = TempDir::new("sniff");
+        std::fs::copy(FIXTURE, root.join("export_0001")).unwrap();
+        std::fs::write(root.join("README"), b"not an image").unwrap();
+        let mut options = ScanOptions::default();
+        let found_without_sniffing = scan_folder(&root, &options).count_images();
+        options.sniff_extensionless = true;
+        let tree = scan_folder(&root, &options);
+        let decoded = crate::decode::decode_image(&root.join("export_0001"), &crate::decode::DecodeOptions::default());
+        assert_eq!(found_without_sniffing, 0);
+        assert_eq!(tree.images, vec![OsString::from("export_0001")]);
+        assert!(decoded.is_ok());
+    }
+
+    #[test]
+    fn fast_scans_read_nothing_but_the_listing() {
+        let root = TempDir::new("fast_scan");
+        std::fs::copy(FIXTURE, root.join("b.jpg")).unwrap();
+        std::fs::copy(FIXTURE, root.join("a.jpg")).unwrap();
+        std::fs::copy(FIXTURE, root.join("export_0001")).unwrap();
+        std::fs::write(root.join("scan.tif"), b"not even read").unwrap();
+        let options = ScanOptions {
+            fast: true,
+            sniff_extensionless: true,
+            tiff_pages: true,
+            order: ImageOrder::Date,
+            ..ScanOptions::default()
+        };
+        let folders: Vec<ScannedFolder> = spawn_scan(root.to_path_buf(), options, egui::Context::default()).into_iter().collect();
+        assert_eq!(folders.len(), 1);
+        assert_eq!(folders[0].images, ["a.jpg", "b.jpg", "scan.tif"].map(OsString::from));
+        assert!(folders[0].provisional_order);
+    }
+
+    #[test]
+    fn names_sort_by_the_value_of_their_numbers() {
+        let mut names = vec!["IMG_10.jpg", "img_2.jpg", "IMG_1.jpg", "IMG_02b.jpg", "IMG_2a.jpg", "DSC_9.jpg"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, vec!["DSC_9.jpg", "IMG_1.jpg", "img_2.jpg", "IMG_2a.jpg", "IMG_02b.jpg", "IMG_10.jpg"]);
+    }
+
+    #[test]
+    fn raw_only_folders_are_reviewed_by_their_largest_preview() {
+        let root = TempDir::new("raw_only");
+        std::fs::create_dir_all(root.join("jpegs")).unwrap();
+        std::fs::create_dir_all(root.join("raws")).unwrap();
+        let jpeg = |width, height| {
+            let mut encoded = Vec::new();
+            image::codecs::jpeg::JpegEncoder::new(&mut encoded).encode_image(&image::RgbImage::new(width, height)).unwrap();
+            encoded
+        };
+        // A thumbnail and a large preview between stand-ins for the header and the sensor data
+        let raw: Vec<u8> = [b"II*\0 header".to_vec(), jpeg(8, 8), vec![0x55; 64], jpeg(48, 32), vec![0xAA; 64]].concat();
+        std::fs::write(root.join("raws/IMG_1.CR2"), &raw).unwrap();
+        std::fs::write(root.join("jpegs/IMG_2.jpg"), jpeg(4, 4)).unwrap();
+        std::fs::write(root.join("jpegs/IMG_2.CR3"), &raw).unwrap();
+
+        let mut options = ScanOptions::from_settings(&Settings::default());
+        let skipped = scan_folder(&root, &options).count_images();
+        options.raw_only = crate::settings::RawOnlyFolders::Preview;
+        let reviewed = scan_folder(&root, &options).get_images_depth_first_current_priority(&root);
+        let preview = crate::decode::decode_image(&root.join("raws/IMG_1.CR2"), &crate::decode::DecodeOptions::default());
+        assert_eq!(skipped, 1);
+        assert_eq!(reviewed, vec![root.join("jpegs/IMG_2.jpg"), root.join("raws/IMG_1.CR2")]);
+        assert_eq!(preview.unwrap().size, [48, 32]);
+        assert_eq!(crate::export::raw_sidecar(&root.join("raws/IMG_1.CR2")), None);
+    }
+}
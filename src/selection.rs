@@ -0,0 +1,90 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Outcome of importing a list of keepers prepared in another tool.
+#[derive(Default)]
+pub struct SelectionReport {
+    pub source: PathBuf,
+    pub kept: usize,
+    pub not_found: Vec<String>, // Listed, but no queued image has that path or name
+    pub ambiguous: Vec<String>, // Names shared by several queued images in different folders
+}
+
+/// One file name or relative path per line; blank lines and `#` comments are skipped.
+pub fn read_selection_file(path: &Path) -> std::io::Result<Vec<String>> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Finds the queued images the entries refer to. An entry matches a relative path first,
+/// then a file name, then a file name without extension, so a list of RAW names still
+/// finds the JPEGs. Case and path separators do not matter.
+pub fn match_selection(
+    entries: &[String],
+    queue: &[PathBuf],
+    relative_path: impl Fn(&Path) -> Option<PathBuf>,
+) -> (Vec<PathBuf>, SelectionReport) {
+    let mut by_relative_path: HashMap<String, &PathBuf> = HashMap::new();
+    let mut by_name: HashMap<String, Vec<&PathBuf>> = HashMap::new();
+    let mut by_stem: HashMap<String, Vec<&PathBuf>> = HashMap::new();
+    for image in queue {
+        if let Some(relative) = relative_path(image) {
+            by_relative_path.insert(normalize(&relative.to_string_lossy()), image);
+        }
+        if let Some(name) = image.file_name() {
+            by_name.entry(normalize(&name.to_string_lossy())).or_default().push(image);
+        }
+        if let Some(stem) = image.file_stem() {
+            by_stem.entry(normalize(&stem.to_string_lossy())).or_default().push(image);
+        }
+    }
+
+    let mut matched = Vec::new();
+    let mut report = SelectionReport::default();
+    for entry in entries {
+        let key = normalize(entry);
+        let stem_key = normalize(&Path::new(&key).file_stem().unwrap_or_default().to_string_lossy());
+        let candidates = match by_relative_path.get(&key) {
+            Some(image) => vec![*image],
+            None => by_name.get(&key).or_else(|| by_stem.get(&stem_key)).cloned().unwrap_or_default(),
+        };
+        match candidates.as_slice() {
+            [] => report.not_found.push(entry.clone()),
+            [image] => matched.push((*image).clone()),
+            _ => report.ambiguous.push(entry.clone()),
+        }
+    }
+    matched.sort();
+    matched.dedup();
+    report.kept = matched.len();
+    (matched, report)
+}
+
+fn normalize(entry: &str) -> String {
+    entry.trim().trim_start_matches("./").replace('\\', "/").to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_entries_match_paths_names_and_stems() {
+        let queue: Vec<std::path::PathBuf> = ["shoot/day1/IMG_1.JPG", "shoot/day1/IMG_2.jpg", "shoot/day2/IMG_2.jpg", "shoot/day2/IMG_3.jpg"]
+            .iter()
+            .map(std::path::PathBuf::from)
+            .collect();
+        let entries: Vec<String> = ["img_1.jpg", "day2\\IMG_2.jpg", "IMG_3.CR3", "IMG_2.jpg", "IMG_9.jpg"].iter().map(|entry| entry.to_string()).collect();
+        let (matched, report) = match_selection(&entries, &queue, |image| image.strip_prefix("shoot").ok().map(std::path::Path::to_path_buf));
+        assert_eq!(matched, vec![queue[0].clone(), queue[2].clone(), queue[3].clone()]);
+        assert_eq!(report.ambiguous, vec!["IMG_2.jpg".to_string()]);
+        assert_eq!(report.not_found, vec!["IMG_9.jpg".to_string()]);
+    }
+}
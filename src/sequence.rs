@@ -0,0 +1,101 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use crate::scan::FileSysNode;
+
+/// Jumps larger than this are taken for a new sequence, e.g. a counter reset or a second
+/// camera, rather than for missing frames.
+const MAX_GAP: u64 = 500;
+
+/// Frame numbers missing between two images of the same name sequence, e.g. IMG_0042 when
+/// IMG_0041 and IMG_0043 are there.
+#[derive(PartialEq, Debug)]
+pub struct SequenceGap {
+    pub prefix: String, // Name before the number, as the image before the gap has it
+    pub width: usize, // Digits the numbers are padded to
+    pub first: u64,
+    pub last: u64,
+}
+
+impl SequenceGap {
+    pub fn missing(&self) -> u64 {
+        self.last - self.first + 1
+    }
+}
+
+impl std::fmt::Display for SequenceGap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (prefix, width) = (&self.prefix, self.width);
+        if self.first == self.last {
+            write!(f, "{}{:0width$}", prefix, self.first)
+        } else {
+            write!(f, "{}{:0width$} to {}{:0width$} ({} frames)", prefix, self.first, prefix, self.last, self.missing())
+        }
+    }
+}
+
+/// The name of an image split into the part before its trailing number, the number's digit
+/// count and the number: `IMG_0042.jpg` gives `("IMG_", 4, 42)`.
+fn split_number(name: &OsString) -> Option<(&str, usize, u64)> {
+    let stem = Path::new(name).file_stem()?.to_str()?;
+    let prefix = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+    let digits = &stem[prefix.len()..];
+    Some((prefix, digits.len(), digits.parse().ok()?))
+}
+
+/// Gaps in the numbered names of one folder's images. Names are grouped into sequences by
+/// what comes before the number, ignoring case; names without a number are left out.
+pub fn find_gaps(names: &[OsString]) -> Vec<SequenceGap> {
+    let mut numbered: Vec<(String, &str, usize, u64)> = names
+        .iter()
+        .filter_map(split_number)
+        .map(|(prefix, width, number)| (prefix.to_lowercase(), prefix, width, number))
+        .collect();
+    numbered.sort_by(|a, b| (&a.0, a.3).cmp(&(&b.0, b.3)));
+    numbered.dedup_by(|a, b| a.0 == b.0 && a.3 == b.3);
+    numbered
+        .windows(2)
+        .filter(|pair| pair[0].0 == pair[1].0 && (2..=MAX_GAP).contains(&(pair[1].3 - pair[0].3)))
+        .map(|pair| SequenceGap {
+            prefix: pair[0].1.to_string(),
+            width: pair[0].2,
+            first: pair[0].3 + 1,
+            last: pair[1].3 - 1,
+        })
+        .collect()
+}
+
+/// Every folder below `node`, which is at `path`, with gaps in its sequences.
+pub fn find_folder_gaps(node: &FileSysNode, path: &Path) -> Vec<(PathBuf, Vec<SequenceGap>)> {
+    let mut folders = Vec::new();
+    collect_gaps(node, path, &mut folders);
+    folders
+}
+
+fn collect_gaps(node: &FileSysNode, path: &Path, out: &mut Vec<(PathBuf, Vec<SequenceGap>)>) {
+    let gaps = find_gaps(&node.images);
+    if !gaps.is_empty() {
+        out.push((path.to_path_buf(), gaps));
+    }
+    for child in &node.children {
+        collect_gaps(child, &path.join(&child.name), out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gaps_in_numbered_names_are_found_per_sequence() {
+        let names: Vec<OsString> = ["IMG_0040.jpg", "img_0041.JPG", "IMG_0043.jpg", "IMG_0047.jpg", "DSC_9000.jpg", "DSC_0001.jpg", "cover.jpg"]
+            .map(OsString::from)
+            .to_vec();
+        let gaps = find_gaps(&names);
+        assert_eq!(gaps.len(), 2, "the DSC counter restarting is no gap: {:?}", gaps);
+        assert_eq!(gaps[0].to_string(), "img_0042");
+        assert_eq!(gaps[1].to_string(), "IMG_0044 to IMG_0046 (3 frames)");
+    }
+}
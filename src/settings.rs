@@ -0,0 +1,1033 @@
+use eframe::egui;
+use serde::{Serialize, Deserialize};
+
+use crate::{contact_sheet::ContactSheet, decode::JpegBackend, metadata::ColorLabel, thumbnail_cache::ThumbnailCache, watermark::Watermark};
+
+const SETTINGS_FILE: &str = "settings.json";
+const LARGE_WINDOW_SIZE: [f32; 2] = [1280.0, 860.0]; // Points, so it grows with the display scale
+
+/// User preferences that apply across working folders.
+/// Stored as JSON in the OS config directory (e.g. ~/.config/bildsak/settings.json).
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)] // Missing fields fall back to defaults so older settings files keep loading
+pub struct Settings {
+    pub decode_threads: usize, // Worker threads feeding the preload cache
+    pub jpeg_backend: JpegBackend,
+    pub decode_timeout_secs: u32, // A decode taking longer is given up on; 0 waits forever
+    pub cache_thumbnails: bool, // Keep thumbnails on disk between runs
+    pub thumbnail_cache_mb: u32, // Size the thumbnail cache is trimmed back to
+    pub skip_hidden_files: bool, // Leave dotfiles, Thumbs.db & co. out of the scan
+    pub fast_scan: bool, // Only look at names and the directory listing while scanning
+    pub sniff_extensionless: bool, // Look inside files without an extension for JPEG data
+    pub review_tiff_pages: bool, // Queue TIFF files too, every page of a multi-page one on its own
+    pub read_xmp_sidecars: bool, // Take over ratings and labels other programs left in XMP sidecars
+    pub image_order: ImageOrder, // Order of the images within each folder
+    pub raw_only_folders: RawOnlyFolders,
+    pub reverse_new_sessions: bool, // Start folders opened for the first time from the end of the queue
+    pub shuffle_new_sessions: bool, // Review folders opened for the first time in random order; wins over reversing
+    pub shuffle_seed: Option<u64>, // Gives the same order for the same images every time; None picks a new one
+    pub excluded_folders: Vec<String>, // Paths below a scanned folder that are never scanned, e.g. earlier output
+    pub texture_filter: TextureFilter,
+    pub scroll_panoramas: bool, // Show very wide images at full height in a scroll area rather than shrunk to fit
+    pub show_capture_badge: bool, // Focal length, aperture, shutter speed and ISO over the image
+    pub show_palette: bool, // Main colors of the image over it, and under every thumbnail in the grid
+    pub show_minimap: bool, // Overview of the whole image with the part in view outlined, while zoomed in
+    pub keep_fit_mode: bool, // Show the next image in the fit mode of the last one instead of fitted
+    pub show_folder_tree: bool,
+    pub startup_window: StartupWindow,
+    pub window_size: [f32; 2], // Points; the chosen size for Custom, the size at the last exit for Remember
+    pub window_maximized: bool, // Whether the window was maximized at the last exit, for Remember
+    pub show_info_pane: bool, // Details of the current image in a panel beside it instead of in rows above it
+    pub info_pane_width: f32,
+    pub theme: Theme,
+    pub ui_scale: f32, // Size of all text and controls, 1.0 being egui's own
+    pub fallback_font: String, // Font file tried for glyphs egui's fonts lack, before the bundled one; empty for the bundled one alone
+    pub confirm_protected_discards: bool, // Ask before discarding 4+ star or labelled images
+    pub confirm_reset: bool, // Ask before Reset forgets every decision
+    pub confirm_output_conflicts: bool, // Ask before exporting into folders holding files from elsewhere
+    pub review_discards: bool, // Show the last kept image from the same folder before every discard
+    pub detect_bursts: bool,
+    pub burst_max_gap_ms: u32, // Frames closer together than this form a burst
+    pub export_split: ExportSplit,
+    pub watermark: Watermark,
+    pub contact_sheet: ContactSheet,
+    pub straighten_kept_jpegs: bool, // Turn kept JPEGs by their EXIF orientation after copying, for software that ignores it
+    pub verify_copies: VerifyCopies,
+    pub mirror_discard_folders: bool, // Copies of discards keep their subfolders instead of sharing one folder
+    pub preserve_timestamps: bool, // Give copies the original's modified/created times instead of the copy time
+    pub move_as_you_go: bool, // Move every decided image into its bucket's output folder right away instead of copying at the end
+    pub quarantine_decode_errors: bool, // Copy decided images that could not be decoded aside instead of into their bucket
+    pub quarantine_folder: String, // Below the working folder
+    pub auto_keep_rules: Vec<String>, // Name/path substrings or globs of images that are kept as soon as they are found
+    pub auto_keep_lone_images: bool, // Keep images that are alone in their folder as soon as they are found
+    pub buckets: Vec<Bucket>, // What an image can be sorted into; keep/discard by default
+    pub quota_percent: u32, // Share of each folder the quota keeps
+    pub blur_threshold: f64, // Sharpness score below which the blur pass discards an image
+    pub face_tolerance: f64, // Largest distance between two face encodings taken as the same person
+    pub flash_decisions: bool, // Flash the window edge after every decision
+    pub show_decision_trail: bool, // Thumbnails of the last few decisions above the image
+    pub flash_millis: u32,
+    pub keep_flash_color: [u8; 3], // sRGB
+    pub discard_flash_color: [u8; 3],
+    pub suggest_breaks: bool,
+    pub break_after_minutes: u32, // Of culling without a pause
+    pub break_after_decisions: u32,
+    pub autosave_idle_secs: u32, // Write the progress file after this long without changes; 0 writes after every action
+    pub backup_progress: bool, // Keep the previous progress file as a .bak, loaded if the current one is unreadable
+}
+
+/// How the viewer samples the image when it is drawn larger or smaller than its pixels.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum TextureFilter {
+    #[default]
+    Linear, // Smooth
+    Nearest, // Crisp pixels, for judging fine detail
+}
+
+impl TextureFilter {
+    pub fn texture_options(self) -> egui::TextureOptions {
+        match self {
+            Self::Linear => egui::TextureOptions::LINEAR,
+            Self::Nearest => egui::TextureOptions::NEAREST,
+        }
+    }
+}
+
+/// How the images of a folder are ordered in the queue.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum ImageOrder {
+    #[default]
+    Name, // Numbers in names compare by value, so IMG_2 comes before IMG_10
+    Date, // Capture time from EXIF, else the file's modified time
+    Size,
+}
+
+impl ImageOrder {
+    pub const ALL: [Self; 3] = [Self::Name, Self::Date, Self::Size];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Name => "Name",
+            Self::Date => "Date taken",
+            Self::Size => "File size",
+        }
+    }
+}
+
+/// How copies are checked against their originals after copying, from least to most thorough.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Debug)]
+pub enum VerifyCopies {
+    #[default]
+    Off,
+    Size, // Catches copies cut short, at next to no cost
+    Contents, // Reads every copy back, which doubles the reading
+}
+
+impl VerifyCopies {
+    pub const ALL: [Self; 3] = [Self::Off, Self::Size, Self::Contents];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Size => "Size",
+            Self::Contents => "Size and contents",
+        }
+    }
+}
+
+/// Light or dark colors for the whole interface.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Theme {
+    #[default]
+    System, // Whatever the operating system is set to
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub const ALL: [Self; 3] = [Self::System, Self::Dark, Self::Light];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::System => "As the system",
+            Self::Dark => "Dark",
+            Self::Light => "Light",
+        }
+    }
+
+    pub fn preference(self) -> egui::ThemePreference {
+        match self {
+            Self::System => egui::ThemePreference::System,
+            Self::Dark => egui::ThemePreference::Dark,
+            Self::Light => egui::ThemePreference::Light,
+        }
+    }
+}
+
+/// How big the window opens.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum StartupWindow {
+    #[default]
+    Large,
+    Maximized, // Fills the monitor it opens on
+    Remember, // As it was when the app was last closed
+    Custom,
+}
+
+impl StartupWindow {
+    pub const ALL: [Self; 4] = [Self::Large, Self::Maximized, Self::Remember, Self::Custom];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Large => "Large",
+            Self::Maximized => "Maximized",
+            Self::Remember => "As last closed",
+            Self::Custom => "Custom size",
+        }
+    }
+}
+
+/// What a scan does with folders that hold RAW files but no JPEGs.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum RawOnlyFolders {
+    #[default]
+    Skip, // Leave them out without a word
+    Preview, // Review the RAW files by the JPEG preview embedded in them
+    List, // Leave them out, but list them after the scan
+}
+
+impl RawOnlyFolders {
+    pub const ALL: [Self; 3] = [Self::Skip, Self::Preview, Self::List];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Skip => "Skip",
+            Self::Preview => "Review previews",
+            Self::List => "Skip and list",
+        }
+    }
+}
+
+/// Routes kept images into subfolders of the output folder by label or star rating.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ExportSplit {
+    pub enabled: bool,
+    pub rating_folders: [String; 5], // Index 0 is one star
+    pub label_folders: Vec<(ColorLabel, String)>, // Checked before the rating; empty means "use the rating"
+    pub unrated_folder: String,
+}
+
+impl Default for ExportSplit {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rating_folders: ["other", "other", "maybe", "maybe", "selects"].map(String::from),
+            label_folders: ColorLabel::ALL.iter().map(|label| (*label, String::new())).collect(),
+            unrated_folder: "unrated".to_string(),
+        }
+    }
+}
+
+impl ExportSplit {
+    /// Subfolder of the output folder an image with this rating and label belongs in.
+    pub fn subfolder(&self, rating: Option<u8>, label: Option<ColorLabel>) -> &str {
+        let by_label = label.and_then(|label| {
+            self.label_folders
+                .iter()
+                .find(|(routed, folder)| *routed == label && !folder.trim().is_empty())
+        });
+        if let Some((_, folder)) = by_label {
+            return folder.trim();
+        }
+        match rating {
+            Some(stars @ 1..=5) => self.rating_folders[stars as usize - 1].trim(),
+            _ => self.unrated_folder.trim(),
+        }
+    }
+
+    /// Returns true when anything was edited.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = ui
+            .checkbox(&mut self.enabled, "Split kept images into subfolders")
+            .on_hover_text("Labels win over ratings; unrated images without a routed label use the default folder")
+            .changed();
+        ui.add_enabled_ui(self.enabled, |ui| {
+            egui::Grid::new("export_split").num_columns(2).show(ui, |ui| {
+                for (index, folder) in self.rating_folders.iter_mut().enumerate().rev() {
+                    ui.label("★".repeat(index + 1));
+                    changed |= ui.text_edit_singleline(folder).changed();
+                    ui.end_row();
+                }
+                for (label, folder) in &mut self.label_folders {
+                    ui.horizontal(|ui| {
+                        crate::metadata::label_dot(ui, *label);
+                        ui.label(label.xmp_name());
+                    });
+                    changed |= ui.add(egui::TextEdit::singleline(folder).hint_text("by rating")).changed();
+                    ui.end_row();
+                }
+                ui.label("Unrated");
+                changed |= ui.text_edit_singleline(&mut self.unrated_folder).changed();
+                ui.end_row();
+            });
+        });
+        changed
+    }
+}
+
+/// Keys a bucket can be bound to. Digits, B, E, F, G, H, O, P, R and Z already do something else.
+pub const BUCKET_KEYS: [egui::Key; 11] = [
+    egui::Key::ArrowRight,
+    egui::Key::ArrowLeft,
+    egui::Key::ArrowUp,
+    egui::Key::Q,
+    egui::Key::W,
+    egui::Key::A,
+    egui::Key::S,
+    egui::Key::D,
+    egui::Key::X,
+    egui::Key::C,
+    egui::Key::V,
+];
+
+/// Gives buckets saved with a key that has since been taken for something else (Z, before it
+/// cycled the fit modes) a free one instead, or none when every key is in use.
+fn rebind_taken_keys(buckets: &mut [Bucket]) {
+    for index in 0..buckets.len() {
+        if buckets[index].key().is_none_or(|key| BUCKET_KEYS.contains(&key)) {
+            continue;
+        }
+        let free = BUCKET_KEYS.iter().copied().find(|key| !buckets.iter().any(|bucket| bucket.key() == Some(*key)));
+        buckets[index].key = free.map(|key| key.name().to_string()).unwrap_or_default();
+    }
+}
+
+/// A pile images are sorted into. Every bucket either keeps or discards, which is what
+/// progress, statistics and everything else built on keep/discard go by.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Bucket {
+    pub name: String,
+    pub key: String, // egui key name, e.g. "Right" or "Q"
+    pub output_folder: String, // Below the working folder; empty means the bucket is not copied
+    pub keeps: bool,
+}
+
+impl Bucket {
+    pub fn key(&self) -> Option<egui::Key> {
+        egui::Key::from_name(&self.key)
+    }
+}
+
+/// Plain keep/discard: → copies to kept_images, ← leaves the image where it is.
+pub fn default_buckets() -> Vec<Bucket> {
+    vec![
+        Bucket {
+            name: "Keep".to_string(),
+            key: egui::Key::ArrowRight.name().to_string(),
+            output_folder: "kept_images".to_string(),
+            keeps: true,
+        },
+        Bucket {
+            name: "Discard".to_string(),
+            key: egui::Key::ArrowLeft.name().to_string(),
+            output_folder: String::new(),
+            keeps: false,
+        },
+    ]
+}
+
+// Folder names people commonly sort into by hand, compared case-insensitively
+const KEEPING_FOLDER_NAMES: [&str; 10] = ["keep", "keeps", "kept", "keepers", "selects", "picks", "best", "yes", "maybe", "hold"];
+const DISCARDING_FOLDER_NAMES: [&str; 9] = ["reject", "rejects", "rejected", "discard", "discards", "discarded", "trash", "delete", "no"];
+
+/// Buckets for the subfolders of a working folder that look like an existing manual sort,
+/// e.g. keep/, maybe/ and reject/, each copying into its own folder. Empty unless at least
+/// two such folders exist. The first keeping folder gets →, the first discarding one ←;
+/// without a discarding folder, ← discards without copying.
+pub fn buckets_from_folders(folder_names: &[String]) -> Vec<Bucket> {
+    let keeps = |name: &str| -> Option<bool> {
+        let name = name.to_lowercase();
+        if KEEPING_FOLDER_NAMES.contains(&name.as_str()) {
+            Some(true)
+        } else if DISCARDING_FOLDER_NAMES.contains(&name.as_str()) {
+            Some(false)
+        } else {
+            None
+        }
+    };
+    let mut found: Vec<(&String, bool)> = folder_names.iter().filter_map(|name| Some((name, keeps(name)?))).collect();
+    if found.len() < 2 {
+        return Vec::new();
+    }
+    // Best-known names first, so "keep" rather than "maybe" gets the arrow key
+    let rank = |name: &str| {
+        let name = name.to_lowercase();
+        KEEPING_FOLDER_NAMES.iter().chain(&DISCARDING_FOLDER_NAMES).position(|known| *known == name)
+    };
+    found.sort_by_key(|(name, keeps)| (!keeps, rank(name)));
+
+    let mut buckets: Vec<Bucket> = found
+        .into_iter()
+        .map(|(name, keeps)| {
+            let mut chars = name.chars();
+            let title = chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default();
+            Bucket { name: title, key: String::new(), output_folder: name.clone(), keeps }
+        })
+        .collect();
+    if !buckets.iter().any(|bucket| !bucket.keeps) {
+        buckets.push(default_buckets().remove(1));
+    }
+    let first_keeping = buckets.iter().position(|bucket| bucket.keeps);
+    let first_discarding = buckets.iter().position(|bucket| !bucket.keeps);
+    let mut free_keys = BUCKET_KEYS[2..].iter();
+    for (index, bucket) in buckets.iter_mut().enumerate() {
+        let key = if Some(index) == first_keeping {
+            Some(&egui::Key::ArrowRight)
+        } else if Some(index) == first_discarding {
+            Some(&egui::Key::ArrowLeft)
+        } else {
+            free_keys.next()
+        };
+        bucket.key = key.map(|key| key.name().to_string()).unwrap_or_default();
+    }
+    buckets
+}
+
+/// Returns true when anything was edited.
+pub fn buckets_ui(buckets: &mut Vec<Bucket>, ui: &mut egui::Ui) -> bool {
+    let mut changed = false;
+    let mut removed = None;
+    let can_remove = buckets.len() > 1;
+    egui::Grid::new("buckets").num_columns(5).show(ui, |ui| {
+        ui.strong("Name");
+        ui.strong("Key");
+        ui.strong("Output folder");
+        ui.strong("Counts as");
+        ui.end_row();
+        for (index, bucket) in buckets.iter_mut().enumerate() {
+            changed |= ui.add(egui::TextEdit::singleline(&mut bucket.name).desired_width(100.0)).changed();
+            egui::ComboBox::from_id_salt(("bucket_key", index))
+                .selected_text(bucket.key().map_or("?", |key| key.symbol_or_name()))
+                .show_ui(ui, |ui| {
+                    for key in BUCKET_KEYS {
+                        changed |= ui.selectable_value(&mut bucket.key, key.name().to_string(), key.symbol_or_name()).changed();
+                    }
+                });
+            changed |= ui
+                .add(egui::TextEdit::singleline(&mut bucket.output_folder).hint_text("not copied").desired_width(120.0))
+                .changed();
+            ui.horizontal(|ui| {
+                changed |= ui.radio_value(&mut bucket.keeps, true, "kept").changed();
+                changed |= ui.radio_value(&mut bucket.keeps, false, "discarded").changed();
+            });
+            if ui.add_enabled(can_remove, egui::Button::new("🗑").small()).clicked() {
+                removed = Some(index);
+            }
+            ui.end_row();
+        }
+    });
+    if let Some(index) = removed {
+        buckets.remove(index);
+        changed = true;
+    }
+    let keys: Vec<String> = buckets.iter().map(|bucket| bucket.key.clone()).collect();
+    if keys.iter().enumerate().any(|(index, key)| keys[..index].contains(key)) {
+        ui.colored_label(ui.visuals().warn_fg_color, "⚠ Several buckets share a key; the first one wins");
+    }
+    ui.horizontal(|ui| {
+        if ui.button("➕ Add bucket").clicked() {
+            let unused_key = BUCKET_KEYS.iter().find(|key| !keys.iter().any(|used| used == key.name())).unwrap_or(&BUCKET_KEYS[0]);
+            buckets.push(Bucket {
+                name: format!("Bucket {}", buckets.len() + 1),
+                key: unused_key.name().to_string(),
+                output_folder: String::new(),
+                keeps: true,
+            });
+            changed = true;
+        }
+        if ui.button("Restore keep/discard").clicked() {
+            *buckets = default_buckets();
+            changed = true;
+        }
+    });
+    changed
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            decode_threads: default_decode_threads(),
+            jpeg_backend: JpegBackend::default(),
+            decode_timeout_secs: 30,
+            cache_thumbnails: true,
+            thumbnail_cache_mb: 256,
+            skip_hidden_files: true,
+            fast_scan: false,
+            sniff_extensionless: false,
+            review_tiff_pages: false,
+            read_xmp_sidecars: true,
+            image_order: ImageOrder::default(),
+            raw_only_folders: RawOnlyFolders::default(),
+            reverse_new_sessions: false,
+            shuffle_new_sessions: false,
+            shuffle_seed: None,
+            excluded_folders: default_excluded_folders(),
+            texture_filter: TextureFilter::default(),
+            scroll_panoramas: true,
+            show_capture_badge: true,
+            show_palette: false,
+            show_minimap: true,
+            keep_fit_mode: false,
+            show_folder_tree: false,
+            startup_window: StartupWindow::default(),
+            window_size: LARGE_WINDOW_SIZE,
+            window_maximized: false,
+            show_info_pane: false,
+            info_pane_width: 260.0,
+            theme: Theme::default(),
+            ui_scale: 1.0,
+            fallback_font: String::new(),
+            confirm_protected_discards: true,
+            confirm_reset: true,
+            confirm_output_conflicts: true,
+            review_discards: false,
+            detect_bursts: true,
+            burst_max_gap_ms: 800,
+            export_split: ExportSplit::default(),
+            watermark: Watermark::default(),
+            contact_sheet: ContactSheet::default(),
+            straighten_kept_jpegs: false,
+            verify_copies: VerifyCopies::default(),
+            mirror_discard_folders: true,
+            preserve_timestamps: false,
+            move_as_you_go: false,
+            quarantine_decode_errors: false,
+            quarantine_folder: "decode_errors".to_string(),
+            auto_keep_rules: Vec::new(),
+            auto_keep_lone_images: false,
+            buckets: default_buckets(),
+            quota_percent: 20,
+            blur_threshold: 100.0,
+            face_tolerance: 0.6,
+            flash_decisions: true,
+            show_decision_trail: true,
+            flash_millis: 250,
+            keep_flash_color: [80, 180, 60],
+            discard_flash_color: [220, 50, 47],
+            suggest_breaks: false,
+            break_after_minutes: 45,
+            break_after_decisions: 600,
+            autosave_idle_secs: 5,
+            backup_progress: true,
+        }
+    }
+}
+
+/// Where copies end up by default, so earlier output is not reviewed again.
+pub fn default_excluded_folders() -> Vec<String> {
+    ["kept_images", "discarded_images", "tagged"].map(String::from).to_vec()
+}
+
+/// Editable list of strings with a remove button per entry. Returns true when anything was edited.
+pub fn string_list_ui(list: &mut Vec<String>, hint: &str, ui: &mut egui::Ui) -> bool {
+    let mut changed = false;
+    let mut removed = None;
+    for (index, entry) in list.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            changed |= ui.add(egui::TextEdit::singleline(entry).hint_text(hint)).changed();
+            if ui.small_button("🗑").clicked() {
+                removed = Some(index);
+            }
+        });
+    }
+    if let Some(index) = removed {
+        list.remove(index);
+        changed = true;
+    }
+    if ui.button("➕ Add").clicked() {
+        list.push(String::new());
+        changed = true;
+    }
+    changed
+}
+
+/// Leave one core for the UI thread, but always have at least one worker.
+pub fn default_decode_threads() -> usize {
+    max_decode_threads().saturating_sub(1).max(1)
+}
+
+pub fn max_decode_threads() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(2)
+}
+
+impl Settings {
+    fn file_path() -> Option<std::path::PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("bildsak").join(SETTINGS_FILE))
+    }
+
+    pub fn load() -> Self {
+        let Some(file_path) = Self::file_path() else {
+            return Self::default();
+        };
+        let mut settings: Self = match std::fs::read_to_string(&file_path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                log::warn!("Ignoring unreadable settings file {}: {}", file_path.display(), e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        };
+        rebind_taken_keys(&mut settings.buckets);
+        settings
+    }
+
+    pub fn save(&self) {
+        let Some(file_path) = Self::file_path() else {
+            return;
+        };
+        if let Some(parent) = file_path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string_pretty(self)
+            && let Err(e) = std::fs::write(&file_path, json)
+        {
+            log::warn!("Could not save settings to {}: {}", file_path.display(), e);
+        }
+    }
+
+    /// Color of the flash after a keep or a discard.
+    pub fn flash_color(&self, keep: bool) -> egui::Color32 {
+        let [r, g, b] = if keep { self.keep_flash_color } else { self.discard_flash_color };
+        egui::Color32::from_rgb(r, g, b)
+    }
+
+    /// The window as it should open.
+    pub fn startup_viewport(&self) -> egui::ViewportBuilder {
+        let viewport = egui::ViewportBuilder::default();
+        match self.startup_window {
+            StartupWindow::Large => viewport.with_inner_size(LARGE_WINDOW_SIZE),
+            // Starts out large, so un-maximizing does not shrink it to a postage stamp
+            StartupWindow::Maximized => viewport.with_inner_size(LARGE_WINDOW_SIZE).with_maximized(true),
+            StartupWindow::Remember => viewport.with_inner_size(self.window_size).with_maximized(self.window_maximized),
+            StartupWindow::Custom => viewport.with_inner_size(self.window_size),
+        }
+    }
+
+    pub fn decode_timeout(&self) -> Option<std::time::Duration> {
+        (self.decode_timeout_secs > 0).then(|| std::time::Duration::from_secs(self.decode_timeout_secs.into()))
+    }
+
+    pub fn thumbnail_cache(&self) -> Option<ThumbnailCache> {
+        let dir = ThumbnailCache::default_dir().filter(|_| self.cache_thumbnails)?;
+        Some(ThumbnailCache::new(dir, u64::from(self.thumbnail_cache_mb) * 1024 * 1024))
+    }
+}
+
+/// Whether a slider was let go, or changed without dragging (by keyboard or by clicking the
+/// value), so expensive changes are applied once it settles rather than on every drag step.
+fn settled(response: &egui::Response) -> bool {
+    response.drag_stopped() || (response.changed() && !response.dragged())
+}
+
+/// The sections of the settings window. Each returns true when anything was edited; the
+/// window saves the settings and applies whatever else the change needs.
+impl Settings {
+    /// Returns whether anything was edited, and how many bytes were freed if the thumbnail
+    /// cache was cleared. Letting go of the thread slider at `running_threads`, the number
+    /// the decoders run with now, edits nothing.
+    pub fn decoding_ui(&mut self, ui: &mut egui::Ui, running_threads: Option<usize>) -> (bool, Option<u64>) {
+        let response = ui.add(egui::Slider::new(&mut self.decode_threads, 1..=max_decode_threads()).text("Decode threads"));
+        let mut changed = settled(&response) && running_threads != Some(self.decode_threads);
+        changed |= settled(
+            &ui.add(egui::Slider::new(&mut self.decode_timeout_secs, 0..=300).suffix(" s").text("Decode timeout"))
+                .on_hover_text("Images taking longer to decode are shown as broken, so they can be skipped or discarded. 0 waits forever."),
+        );
+        let mut cleared = None;
+        ui.horizontal(|ui| {
+            changed |= ui
+                .checkbox(&mut self.cache_thumbnails, "Keep thumbnails on disk, up to")
+                .on_hover_text("Folders opened before show their thumbnails right away")
+                .changed();
+            changed |= settled(&ui.add_enabled(
+                self.cache_thumbnails,
+                egui::Slider::new(&mut self.thumbnail_cache_mb, 16..=4096).logarithmic(true).suffix(" MB"),
+            ));
+            if ui.button("Clear").clicked()
+                && let Some(dir) = ThumbnailCache::default_dir()
+            {
+                cleared = Some(ThumbnailCache::new(dir, 0).clear());
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("JPEG decoder:");
+            changed |= ui.radio_value(&mut self.jpeg_backend, JpegBackend::Builtin, "Built-in").changed();
+            ui.add_enabled_ui(crate::decode::TURBOJPEG_AVAILABLE, |ui| {
+                changed |= ui
+                    .radio_value(&mut self.jpeg_backend, JpegBackend::TurboJpeg, "libjpeg-turbo")
+                    .on_hover_text("Much faster on large files")
+                    .on_disabled_hover_text("This build was made without the turbojpeg feature")
+                    .changed();
+            });
+        });
+        (changed, cleared)
+    }
+
+    /// `session_seed` is what the open session was shuffled with, offered as the fixed seed.
+    pub fn scanning_ui(&mut self, ui: &mut egui::Ui, session_seed: Option<u64>) -> bool {
+        let mut changed = ui
+            .checkbox(&mut self.skip_hidden_files, "Ignore hidden and system files")
+            .on_hover_text("Applies the next time a folder is scanned")
+            .changed();
+        changed |= ui
+            .checkbox(&mut self.fast_scan, "Fast scan")
+            .on_hover_text(
+                "Trust file names instead of reading every file's attributes, which is much quicker on \
+                 huge trees and network drives. Hidden files are then only recognized by name and broken \
+                 images only show up when displayed. Files without an extension are not sniffed, TIFFs are \
+                 queued without splitting them into pages, and folders are queued by name instead of by \
+                 date or size. Applies the next time a folder is scanned.",
+            )
+            .changed();
+        ui.horizontal(|ui| {
+            ui.label("Order within a folder:");
+            for order in ImageOrder::ALL {
+                changed |= ui.radio_value(&mut self.image_order, order, order.name()).changed();
+            }
+        })
+        .response
+        .on_hover_text("Applies the next time a folder is scanned. Sorting by date reads every image's EXIF while scanning.");
+        ui.horizontal(|ui| {
+            ui.label("Folders with only RAW files:");
+            for mode in RawOnlyFolders::ALL {
+                changed |= ui.radio_value(&mut self.raw_only_folders, mode, mode.name()).changed();
+            }
+        })
+        .response
+        .on_hover_text(
+            "RAW files next to JPEGs are copied along with them. Folders without JPEGs can be skipped, \
+             reviewed by the JPEG preview inside every RAW file, or skipped and listed after the scan. \
+             Applies the next time a folder is scanned.",
+        );
+        changed |= ui
+            .checkbox(&mut self.reverse_new_sessions, "Reverse the queue of new sessions")
+            .on_hover_text("Folders opened for the first time start at their last image, e.g. the newest shoot")
+            .changed();
+        changed |= ui
+            .checkbox(&mut self.shuffle_new_sessions, "Shuffle the queue of new sessions")
+            .on_hover_text("Folders opened for the first time are reviewed in random order, e.g. to judge a contest fairly")
+            .changed();
+        ui.horizontal(|ui| {
+            let mut fixed = self.shuffle_seed.is_some();
+            if ui
+                .checkbox(&mut fixed, "Shuffle with a fixed seed")
+                .on_hover_text("The same seed puts the same images in the same order again, e.g. for a second judge")
+                .changed()
+            {
+                self.shuffle_seed = fixed.then(|| session_seed.unwrap_or(1));
+                changed = true;
+            }
+            if let Some(seed) = &mut self.shuffle_seed {
+                changed |= ui.add(egui::DragValue::new(seed)).changed();
+            }
+        });
+        changed |= ui
+            .checkbox(&mut self.review_tiff_pages, "Review TIFF files, every page of a multi-page one on its own")
+            .on_hover_text(
+                "For scans and frames kept in multi-page TIFFs. Kept pages are exported as TIFF files of their own. \
+                 Applies the next time a folder is scanned.",
+            )
+            .changed();
+        changed |= ui
+            .checkbox(&mut self.read_xmp_sidecars, "Read ratings and labels from XMP sidecars")
+            .on_hover_text("Carry on from ratings and color labels given in Lightroom or Bridge. Applies the next time a folder is scanned.")
+            .changed();
+        changed |= ui
+            .checkbox(&mut self.sniff_extensionless, "Find JPEGs without a file extension")
+            .on_hover_text("Reads the first bytes of every file without an extension, which slows down scanning. Applies the next time a folder is scanned.")
+            .changed();
+        egui::CollapsingHeader::new("Folders never scanned").show(ui, |ui| {
+            ui.label("Paths below the working folder, such as earlier output. Bucket output folders are always left out. Applies the next time a folder is scanned.");
+            changed |= string_list_ui(&mut self.excluded_folders, "kept_images", ui);
+            if ui.button("Restore defaults").clicked() {
+                self.excluded_folders = default_excluded_folders();
+                changed = true;
+            }
+        });
+        changed
+    }
+
+    pub fn reviewing_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label("Image smoothing:");
+            changed |= ui.radio_value(&mut self.texture_filter, TextureFilter::Linear, "Linear").changed();
+            changed |= ui.radio_value(&mut self.texture_filter, TextureFilter::Nearest, "Nearest neighbor").changed();
+        });
+        changed |= ui
+            .checkbox(&mut self.review_discards, "Review every discard against the last kept image")
+            .on_hover_text("Before discarding, show the last image kept from the same folder so the only good shot does not go by mistake")
+            .changed();
+        changed |= ui
+            .checkbox(&mut self.confirm_protected_discards, "Confirm before discarding rated or labelled images")
+            .on_hover_text(format!("Applies to images with {} or more stars or any color label", crate::PROTECTED_RATING))
+            .changed();
+        changed |= ui.checkbox(&mut self.confirm_reset, "Confirm before resetting all decisions").changed();
+        changed |= ui
+            .checkbox(&mut self.confirm_output_conflicts, "Confirm before copying into folders that hold other files")
+            .on_hover_text("E.g. the results of sorting another set of images into the same output folder")
+            .changed();
+        changed
+    }
+
+    /// `current_size` is the size of the window now, if it is known and not maximized.
+    pub fn window_ui(&mut self, ui: &mut egui::Ui, current_size: Option<egui::Vec2>) -> bool {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label("Open the window:");
+            for startup_window in StartupWindow::ALL {
+                changed |= ui.radio_value(&mut self.startup_window, startup_window, startup_window.name()).changed();
+            }
+        })
+        .response
+        .on_hover_text("Applies the next time the app starts");
+        if self.startup_window == StartupWindow::Custom {
+            ui.horizontal(|ui| {
+                let [width, height] = &mut self.window_size;
+                changed |= ui.add(egui::DragValue::new(width).range(400.0..=8000.0).suffix(" pt")).changed();
+                ui.label("×");
+                changed |= ui.add(egui::DragValue::new(height).range(300.0..=8000.0).suffix(" pt")).changed();
+                if ui.button("Use the current size").clicked()
+                    && let Some(size) = current_size
+                {
+                    self.window_size = size.into();
+                    changed = true;
+                }
+            });
+        }
+        changed
+    }
+
+    pub fn appearance_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label("Theme:");
+            for theme in Theme::ALL {
+                changed |= ui.radio_value(&mut self.theme, theme, theme.name()).changed();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Text and control size:");
+            // Applied on release, as the slider would otherwise move under the pointer while dragged
+            changed |= settled(&ui.add(
+                egui::Slider::new(&mut self.ui_scale, crate::MIN_UI_SCALE..=crate::MAX_UI_SCALE)
+                    .step_by(0.05)
+                    .custom_formatter(|scale, _| format!("{:.0} %", scale * 100.0)),
+            ));
+            if ui.button("Reset").clicked() {
+                self.ui_scale = 1.0;
+                changed = true;
+            }
+        })
+        .response
+        .on_hover_text("Ctrl and + or − changes it for the current run only");
+        changed
+    }
+
+    /// Returns true once a new fallback font has been entered or picked.
+    pub fn fonts_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.label("Symbols on buttons come from egui's and a bundled font. To draw them in another style, pick a font that has them (e.g. Segoe UI Emoji or Noto Emoji).");
+        ui.horizontal(|ui| {
+            let mut changed = ui.add(egui::TextEdit::singleline(&mut self.fallback_font).hint_text("the bundled font")).lost_focus();
+            if ui.button("Browse…").clicked()
+                && let Some(path) = rfd::FileDialog::new().add_filter("Fonts", &["ttf", "otf", "ttc"]).pick_file()
+            {
+                self.fallback_font = path.to_string_lossy().to_string();
+                changed = true;
+            }
+            changed
+        })
+        .inner
+    }
+
+    pub fn feedback_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = ui
+            .checkbox(&mut self.flash_decisions, "Flash the window edge after each decision")
+            .changed();
+        ui.add_enabled_ui(self.flash_decisions, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Keep:");
+                changed |= ui.color_edit_button_srgb(&mut self.keep_flash_color).changed();
+                ui.label("Discard:");
+                changed |= ui.color_edit_button_srgb(&mut self.discard_flash_color).changed();
+            });
+            changed |= ui
+                .add(egui::Slider::new(&mut self.flash_millis, 50..=1000).suffix(" ms").text("for"))
+                .changed();
+        });
+        changed |= ui
+            .checkbox(&mut self.show_decision_trail, "Show the last few decisions above the image")
+            .on_hover_text("Thumbnails edged in the keep and discard colors above; click one to decide it again")
+            .changed();
+        changed
+    }
+
+    pub fn breaks_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = ui
+            .checkbox(&mut self.suggest_breaks, "Suggest a break after a long stretch of culling")
+            .on_hover_text("A pause of five minutes or more counts as a break")
+            .changed();
+        ui.add_enabled_ui(self.suggest_breaks, |ui| {
+            changed |= ui
+                .add(egui::Slider::new(&mut self.break_after_minutes, 5..=180).suffix(" min").text("after"))
+                .changed();
+            changed |= ui
+                .add(egui::Slider::new(&mut self.break_after_decisions, 50..=3000).text("or decisions"))
+                .changed();
+        });
+        changed
+    }
+
+    pub fn bursts_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = ui
+            .checkbox(&mut self.detect_bursts, "Group bursts and propose the sharpest frame")
+            .changed();
+        ui.add_enabled_ui(self.detect_bursts, |ui| {
+            changed |= ui
+                .add(egui::Slider::new(&mut self.burst_max_gap_ms, 100..=2000).suffix(" ms").text("Max gap between frames"))
+                .changed();
+        });
+        changed
+    }
+
+    pub fn export_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = self.export_split.ui(ui);
+        changed |= self.watermark.ui(ui);
+        changed |= ui
+            .add_enabled(
+                crate::orientation::LOSSLESS,
+                egui::Checkbox::new(&mut self.straighten_kept_jpegs, "Turn kept JPEGs upright after copying"),
+            )
+            .on_hover_text(
+                "For software that ignores the EXIF orientation. The copies are turned losslessly and marked as upright; \
+                 images that cannot be turned without cropping their edge are left as they are, and so are images moved \
+                 out in move-as-you-go mode, which are the originals.",
+            )
+            .on_disabled_hover_text("Needs a build with the turbojpeg feature, as turning them otherwise means re-encoding them")
+            .changed();
+        changed
+    }
+
+    pub fn auto_keep_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.label("Images whose path contains one of these (or matches it, with * and ?) are kept as soon as they are found.");
+        let mut changed = string_list_ui(&mut self.auto_keep_rules, "FINAL or selects/*", ui);
+        changed |= ui
+            .checkbox(&mut self.auto_keep_lone_images, "Keep images that are alone in their folder")
+            .on_hover_text("Folders with a single image have nothing to choose between; only folders with several are reviewed")
+            .changed();
+        changed
+    }
+
+    pub fn saving_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = ui
+            .add(egui::Slider::new(&mut self.autosave_idle_secs, 0..=60).suffix(" s").text("Save progress after a pause of"))
+            .on_hover_text("Every action goes to a journal right away, so a crash loses nothing. 0 rewrites the whole progress file after every action, which gets slow with huge folders.")
+            .changed();
+        changed |= ui
+            .checkbox(&mut self.backup_progress, "Keep a backup of the previous save")
+            .on_hover_text(format!("Loaded instead when {} turns out to be unreadable", crate::PROGRESS_FILE))
+            .changed();
+        changed
+    }
+
+    pub fn copying_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = ui
+            .checkbox(&mut self.mirror_discard_folders, "Keep the folder structure of discarded images")
+            .on_hover_text(
+                "Discards copied to a bucket's output folder keep their subfolders, so the whole tree can be moved \
+                 back over the originals. Off puts them all in one folder, with the folder names in the file names.",
+            )
+            .changed();
+        ui.horizontal(|ui| {
+            ui.label("Check copies against the originals:");
+            for verify in VerifyCopies::ALL {
+                changed |= ui.radio_value(&mut self.verify_copies, verify, verify.name()).changed();
+            }
+        })
+        .response
+        .on_hover_text(
+            "For unreliable drives and card readers, which can cut copies short without an error. Copies that \
+             do not match are made again; RAW files next to the images are checked too.",
+        );
+        changed |= ui
+            .checkbox(&mut self.preserve_timestamps, "Keep the originals' timestamps")
+            .on_hover_text("Copies and sidecars get the modified and created times of the original file")
+            .changed();
+        changed |= ui
+            .checkbox(&mut self.move_as_you_go, "⚠ Move decided images out right away")
+            .on_hover_text(
+                "Every decision moves the image, its RAW file and XMP sidecar into the output folder of its bucket, \
+                 freeing space on the card as you go. Nothing is copied at the end. Undoing a decision moves the \
+                 files back; images in buckets without an output folder stay where they are.",
+            )
+            .changed();
+        ui.horizontal(|ui| {
+            changed |= ui
+                .checkbox(&mut self.quarantine_decode_errors, "Copy images that could not be decoded to")
+                .on_hover_text(format!(
+                    "Instead of into their bucket, so they can be inspected later. {} in that folder says what went wrong.",
+                    crate::export::DECODE_ERROR_LOG
+                ))
+                .changed();
+            changed |= ui
+                .add_enabled(
+                    self.quarantine_decode_errors,
+                    egui::TextEdit::singleline(&mut self.quarantine_folder).desired_width(120.0),
+                )
+                .changed();
+        });
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_sort_folders_become_buckets() {
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+        let buckets = buckets_from_folders(&names(&["maybe", "2024", "Reject", "keep"]));
+        let summary: Vec<_> = buckets.iter().map(|bucket| (bucket.name.as_str(), bucket.output_folder.as_str(), bucket.keeps)).collect();
+        assert_eq!(summary, [("Keep", "keep", true), ("Maybe", "maybe", true), ("Reject", "Reject", false)]);
+        assert_eq!(buckets[0].key(), Some(egui::Key::ArrowRight));
+        assert_eq!(buckets[2].key(), Some(egui::Key::ArrowLeft));
+        assert!(buckets[1].key().is_some());
+        // Without a discarding folder, discards stay where they are
+        let buckets = buckets_from_folders(&names(&["selects", "maybe"]));
+        assert_eq!(buckets.last().map(|bucket| (bucket.keeps, bucket.output_folder.as_str())), Some((false, "")));
+        assert!(buckets_from_folders(&names(&["keep", "raw"])).is_empty());
+    }
+
+    #[test]
+    fn buckets_on_keys_taken_since_are_rebound() {
+        let mut buckets = default_buckets();
+        buckets.push(Bucket { name: "Maybe".to_string(), key: egui::Key::Z.name().to_string(), output_folder: "maybe".to_string(), keeps: true });
+        rebind_taken_keys(&mut buckets);
+        let keys: Vec<_> = buckets.iter().map(Bucket::key).collect();
+        assert_eq!(keys, [Some(egui::Key::ArrowRight), Some(egui::Key::ArrowLeft), Some(egui::Key::ArrowUp)]);
+    }
+
+    #[test]
+    fn the_window_opens_as_configured() {
+        let mut settings = Settings::default();
+        let large = settings.startup_viewport();
+        assert!(large.inner_size.is_some_and(|size| size.x > 800.0 && size.y > 600.0));
+        assert_eq!(large.maximized, None);
+        settings.startup_window = StartupWindow::Remember;
+        settings.window_size = [1000.0, 700.0];
+        settings.window_maximized = true;
+        let remembered = settings.startup_viewport();
+        assert_eq!(remembered.inner_size, Some(egui::vec2(1000.0, 700.0)));
+        assert_eq!(remembered.maximized, Some(true));
+    }
+}
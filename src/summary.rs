@@ -0,0 +1,146 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    folders::{self, FolderTally},
+    scan::FileSysNode,
+    Decision,
+};
+
+pub const SUMMARY_FILE: &str = "bildsak_summary.txt";
+
+/// Human-readable end-of-session report, written next to the progress file.
+pub struct SessionSummary {
+    pub total: usize,
+    pub kept: usize,
+    pub discarded: usize,
+    pub skipped: usize, // Times an image was deferred, not distinct images
+    pub shuffle_seed: Option<u64>, // Set when the images were reviewed in random order
+    pub kept_bytes: u64,
+    pub discarded_bytes: u64,
+    pub time_spent: Duration,
+    pub folders: Vec<(PathBuf, FolderTally)>, // Every folder with images, counts including subfolders
+    pub images: Vec<(PathBuf, Option<Decision>)>, // Every image in folder order, whatever order it was reviewed in
+}
+
+impl SessionSummary {
+    pub fn new(
+        tree: &FileSysNode,
+        base_path: &Path,
+        decisions: &HashMap<PathBuf, Decision>,
+        skipped: usize,
+        shuffle_seed: Option<u64>,
+        time_spent: Duration,
+    ) -> Self {
+        let size_of = |decision: Decision| {
+            decisions
+                .iter()
+                .filter(|(_, decided)| **decided == decision)
+                .map(|(path, _)| crate::pages::file_of(path)) // Pages of one file count its size once
+                .collect::<HashSet<_>>()
+                .iter()
+                .filter_map(|file| std::fs::metadata(file).ok())
+                .map(|metadata| metadata.len())
+                .sum()
+        };
+        let tally = folders::tally(tree, base_path, decisions);
+        let mut folder_list = Vec::new();
+        collect_folders(tree, &tally, base_path, &mut folder_list);
+        let images = tree
+            .get_images_depth_first_current_priority(base_path)
+            .into_iter()
+            .map(|image| {
+                let decision = decisions.get(&image).copied();
+                (image.strip_prefix(base_path).map_or_else(|_| image.clone(), Path::to_path_buf), decision)
+            })
+            .collect();
+        Self {
+            total: tally.total(),
+            kept: tally.kept,
+            discarded: tally.discarded,
+            skipped,
+            shuffle_seed,
+            kept_bytes: size_of(Decision::Keep),
+            discarded_bytes: size_of(Decision::Discard),
+            time_spent,
+            folders: folder_list,
+            images,
+        }
+    }
+
+    pub fn decisions_per_minute(&self) -> Option<f64> {
+        let minutes = self.time_spent.as_secs_f64() / 60.0;
+        (minutes > 0.0).then(|| (self.kept + self.discarded) as f64 / minutes)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "Image sifter session summary");
+        let _ = writeln!(text);
+        let _ = writeln!(text, "Total images:   {}", self.total);
+        let _ = writeln!(text, "Kept:           {} ({})", self.kept, format_bytes(self.kept_bytes));
+        let _ = writeln!(text, "Discarded:      {} ({})", self.discarded, format_bytes(self.discarded_bytes));
+        let _ = writeln!(text, "Undecided:      {}", self.total - self.kept - self.discarded);
+        let _ = writeln!(text, "Skips:          {}", self.skipped);
+        if let Some(seed) = self.shuffle_seed {
+            let _ = writeln!(text, "Review order:   shuffled (seed {}); folders and images below are in their own order", seed);
+        }
+        let _ = writeln!(text, "Time spent:     {}", format_duration(self.time_spent));
+        if let Some(rate) = self.decisions_per_minute() {
+            let _ = writeln!(text, "Decisions/min:  {:.1}", rate);
+        }
+        let _ = writeln!(text);
+        let _ = writeln!(text, "Per folder (kept / discarded / undecided):");
+        for (path, tally) in &self.folders {
+            let _ = writeln!(text, "  {}: {} / {} / {}", path.display(), tally.kept, tally.discarded, tally.remaining);
+        }
+        let _ = writeln!(text);
+        let _ = writeln!(text, "Per image, by position in the folders:");
+        for (position, (image, decision)) in self.images.iter().enumerate() {
+            let decision = match decision {
+                Some(Decision::Keep) => "kept",
+                Some(Decision::Discard) => "discarded",
+                None => "undecided",
+            };
+            let _ = writeln!(text, "  {:>5}  {:<9}  {}", position + 1, decision, image.display());
+        }
+        text
+    }
+}
+
+fn collect_folders(node: &FileSysNode, tally: &FolderTally, path: &Path, out: &mut Vec<(PathBuf, FolderTally)>) {
+    if tally.total() > 0 {
+        out.push((
+            path.to_path_buf(),
+            FolderTally {
+                kept: tally.kept,
+                discarded: tally.discarded,
+                remaining: tally.remaining,
+                children: Vec::new(),
+            },
+        ));
+    }
+    for (child, child_tally) in node.children.iter().zip(&tally.children) {
+        collect_folders(child, child_tally, &path.join(&child.name), out);
+    }
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}h {:02}m {:02}s", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
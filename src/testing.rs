@@ -0,0 +1,54 @@
+use std::path::{Path, PathBuf};
+
+/// The photo in `assets` that tests decode, copy and sort.
+pub const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/bild.jpg");
+
+/// A `width` by `height` JPEG, red on the left half and blue on the right, with an EXIF
+/// orientation of 6 (to be turned clockwise) if `sideways`.
+pub fn two_color_jpeg(width: u32, height: u32, sideways: bool) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    let pixels = image::RgbImage::from_fn(width, height, |x, _| if x < width / 2 { image::Rgb([250, 20, 20]) } else { image::Rgb([20, 20, 250]) });
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut encoded, 95).encode_image(&pixels).unwrap();
+    if !sideways {
+        return encoded;
+    }
+    // Big-endian EXIF with one IFD0 entry: orientation 6
+    let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01\0\x06\0\0\0\0\0\0".to_vec();
+    let mut app1 = vec![0xFF, 0xE1];
+    app1.extend_from_slice(&(exif.len() as u16 + 2).to_be_bytes());
+    app1.append(&mut exif);
+    encoded[..2].iter().chain(&app1).chain(&encoded[2..]).copied().collect()
+}
+
+/// A fresh folder of a test's own under the system's temp folder. It is removed again when
+/// dropped, so also when an assertion fails halfway.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("bildsak_{}_test_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path); // Left over from a run that was killed
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl std::ops::Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+    time::UNIX_EPOCH,
+};
+
+use eframe::egui;
+
+const EXTENSION: &str = "png";
+const EVICT_EVERY: u32 = 500; // Entries written between trims, so a long session cannot grow the cache without bound
+
+static NEXT_TEMP_FILE: AtomicU64 = AtomicU64::new(0);
+
+/// Thumbnails kept on disk between runs, so folders seen before fill the grid without decoding
+/// every image again. An entry is named after a hash of the image's path, size and modification
+/// time, so an edited image simply misses. Several decode threads, or several running copies
+/// of the program, can share it: entries are written under a temporary name and renamed into place.
+#[derive(Clone)]
+pub struct ThumbnailCache {
+    dir: PathBuf,
+    max_bytes: u64, // Least recently used entries are removed past this
+    puts_since_evict: Arc<AtomicU32>, // Shared by the clones handed to the decode threads
+}
+
+impl ThumbnailCache {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Self {
+        Self { dir, max_bytes, puts_since_evict: Arc::default() }
+    }
+
+    /// Under the OS cache folder, or None where there is none.
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("bildsak").join("thumbnails"))
+    }
+
+    /// Where the thumbnail of `image` is kept as it is on disk now, or None when it cannot be read.
+    fn entry_path(&self, image: &Path) -> Option<PathBuf> {
+        let metadata = std::fs::metadata(crate::pages::file_of(image)).ok()?; // Pages are told apart by their path
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        let absolute = std::path::absolute(image).ok()?;
+        let mut hash = Fnv1a::default();
+        hash.write(absolute.as_os_str().as_encoded_bytes());
+        hash.write(&metadata.len().to_le_bytes());
+        hash.write(&modified.as_nanos().to_le_bytes());
+        Some(self.dir.join(format!("{:016x}.{}", hash.0, EXTENSION)))
+    }
+
+    pub fn get(&self, image: &Path) -> Option<egui::ColorImage> {
+        let entry = self.entry_path(image)?;
+        let thumbnail = image::open(&entry).ok()?.to_rgba8();
+        // Marks it as recently used for eviction
+        let _ = filetime::set_file_mtime(&entry, filetime::FileTime::now());
+        let size = [thumbnail.width() as usize, thumbnail.height() as usize];
+        Some(egui::ColorImage::from_rgba_unmultiplied(size, thumbnail.as_raw()))
+    }
+
+    /// Stores the thumbnail of `image`, and trims the cache every `EVICT_EVERY` entries on
+    /// the calling thread, which is a decode thread rather than the UI.
+    pub fn put(&self, image: &Path, thumbnail: &egui::ColorImage) {
+        let Some(entry) = self.entry_path(image) else {
+            return;
+        };
+        if let Err(e) = self.write(&entry, thumbnail) {
+            log::warn!("Could not cache the thumbnail of {}: {}", image.display(), e);
+            return;
+        }
+        if self.puts_since_evict.fetch_add(1, Ordering::Relaxed) + 1 >= EVICT_EVERY {
+            self.puts_since_evict.store(0, Ordering::Relaxed);
+            self.evict();
+        }
+    }
+
+    fn write(&self, entry: &Path, thumbnail: &egui::ColorImage) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::create_dir_all(&self.dir)?;
+        let temp = entry.with_extension(format!(
+            "{}.{}.tmp",
+            std::process::id(),
+            NEXT_TEMP_FILE.fetch_add(1, Ordering::Relaxed)
+        ));
+        let [width, height] = thumbnail.size;
+        let result = image::save_buffer_with_format(
+            &temp,
+            thumbnail.as_raw(),
+            width as u32,
+            height as u32,
+            image::ExtendedColorType::Rgba8,
+            image::ImageFormat::Png,
+        )
+        .map_err(Box::from)
+        .and_then(|()| std::fs::rename(&temp, entry).map_err(Box::from));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp);
+        }
+        result
+    }
+
+    /// Removes the least recently used entries until the cache fits in its size limit again,
+    /// along with temporary files left behind by a crash. Returns how many bytes were freed.
+    pub fn evict(&self) -> u64 {
+        let Ok(read_dir) = std::fs::read_dir(&self.dir) else {
+            return 0;
+        };
+        let mut freed = 0;
+        let mut entries = Vec::new();
+        for file in read_dir.flatten() {
+            let Ok(metadata) = file.metadata() else {
+                continue;
+            };
+            let path = file.path();
+            if path.extension().is_some_and(|extension| extension == "tmp") {
+                let stale = metadata.modified().ok().and_then(|modified| modified.elapsed().ok());
+                if stale.is_some_and(|age| age.as_secs() > 3600) && std::fs::remove_file(&path).is_ok() {
+                    freed += metadata.len();
+                }
+            } else if path.extension().is_some_and(|extension| extension == EXTENSION) {
+                entries.push((metadata.modified().unwrap_or(UNIX_EPOCH), metadata.len(), path));
+            }
+        }
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        entries.sort_by_key(|(used, _, _)| *used);
+        for (_, size, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            if std::fs::remove_file(&path).is_ok() {
+                total -= size;
+                freed += size;
+            }
+        }
+        freed
+    }
+
+    /// Removes every entry; returns how many bytes were freed.
+    pub fn clear(&self) -> u64 {
+        Self::new(self.dir.clone(), 0).evict()
+    }
+}
+
+/// 64-bit FNV-1a, which unlike the standard library's hasher gives the same keys in every build.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn cached_thumbnails_are_missed_once_the_image_changes_and_evicted_oldest_first() {
+        let root = TempDir::new("thumbnail_cache");
+        let cache = ThumbnailCache::new(root.join("cache"), 1024 * 1024);
+        let (a, b) = (root.join("a.jpg"), root.join("b.jpg"));
+        std::fs::write(&a, b"first").unwrap();
+        std::fs::write(&b, b"other").unwrap();
+        let thumbnail = egui::ColorImage::new([4, 3], vec![egui::Color32::from_rgb(10, 200, 30); 12]);
+        cache.put(&a, &thumbnail);
+        let hit = cache.get(&a).map(|image| (image.size, image.pixels[0]));
+        std::fs::write(&a, b"edited").unwrap();
+        let after_edit = cache.get(&a).is_some();
+
+        cache.put(&b, &thumbnail);
+        for entry in std::fs::read_dir(root.join("cache")).unwrap().flatten() {
+            filetime::set_file_mtime(entry.path(), filetime::FileTime::from_unix_time(0, 0)).unwrap(); // Long unused
+        }
+        cache.put(&a, &thumbnail);
+        let entry_size = std::fs::metadata(root.join("cache").read_dir().unwrap().flatten().next().unwrap().path()).unwrap().len();
+        ThumbnailCache::new(root.join("cache"), entry_size).evict();
+        let entries = std::fs::read_dir(root.join("cache")).unwrap().count();
+        let (kept_a, kept_b) = (cache.get(&a).is_some(), cache.get(&b).is_some());
+        assert_eq!(hit, Some(([4, 3], egui::Color32::from_rgb(10, 200, 30))));
+        assert!(!after_edit);
+        assert_eq!(entries, 1);
+        assert_eq!((kept_a, kept_b), (true, false));
+    }
+
+    #[test]
+    fn long_sessions_trim_the_cache_as_they_go() {
+        let root = TempDir::new("thumbnail_cache_trim");
+        let cache = ThumbnailCache::new(root.join("cache"), 0);
+        let image = root.join("a.jpg");
+        std::fs::write(&image, b"a").unwrap();
+        let thumbnail = egui::ColorImage::new([2, 2], vec![egui::Color32::WHITE; 4]);
+        for _ in 1..EVICT_EVERY {
+            cache.put(&image, &thumbnail);
+        }
+        let kept_until_the_limit = cache.get(&image).is_some();
+        cache.clone().put(&image, &thumbnail); // Clones count together
+        assert!(kept_until_the_limit);
+        assert!(cache.get(&image).is_none());
+    }
+}
@@ -0,0 +1,174 @@
+use std::path::Path;
+
+use ab_glyph::{FontRef, PxScale};
+use eframe::egui;
+use image::{ImageDecoder, Rgb, RgbImage};
+use serde::{Serialize, Deserialize};
+
+const SHADOW_OFFSET: f32 = 0.06; // Of the text height; a dark copy behind the text keeps it readable on bright areas
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+impl WatermarkPosition {
+    const ALL: [Self; 5] = [Self::TopLeft, Self::TopRight, Self::BottomLeft, Self::BottomRight, Self::Center];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::TopLeft => "Top left",
+            Self::TopRight => "Top right",
+            Self::BottomLeft => "Bottom left",
+            Self::BottomRight => "Bottom right",
+            Self::Center => "Center",
+        }
+    }
+}
+
+/// Text drawn onto exported copies of kept JPEGs, for client proofs.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Watermark {
+    pub enabled: bool,
+    pub text: String, // `{date}` becomes the export date
+    pub position: WatermarkPosition,
+    pub opacity: f32, // 0 to 1
+    pub size_percent: f32, // Text height relative to the shorter side of the image
+    pub quality: u8, // JPEG quality of the re-encoded copy
+}
+
+impl Default for Watermark {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            text: "Proof {date}".to_string(),
+            position: WatermarkPosition::default(),
+            opacity: 0.6,
+            size_percent: 4.0,
+            quality: 92,
+        }
+    }
+}
+
+impl Watermark {
+    /// Returns true when anything was edited.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = ui
+            .checkbox(&mut self.enabled, "Watermark copies of kept JPEGs")
+            .on_hover_text("The copies are re-encoded with the text drawn on; originals and RAW files are not touched")
+            .changed();
+        ui.add_enabled_ui(self.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Text:");
+                changed |= ui.text_edit_singleline(&mut self.text).on_hover_text("{date} is replaced by the export date").changed();
+            });
+            egui::ComboBox::from_label("Position")
+                .selected_text(self.position.name())
+                .show_ui(ui, |ui| {
+                    for position in WatermarkPosition::ALL {
+                        changed |= ui.selectable_value(&mut self.position, position, position.name()).changed();
+                    }
+                });
+            changed |= ui.add(egui::Slider::new(&mut self.opacity, 0.1..=1.0).text("Opacity")).changed();
+            changed |= ui.add(egui::Slider::new(&mut self.size_percent, 1.0..=20.0).suffix(" %").text("Text size")).changed();
+            changed |= ui.add(egui::Slider::new(&mut self.quality, 50..=100).text("JPEG quality")).changed();
+        });
+        changed
+    }
+
+    fn expanded_text(&self) -> String {
+        self.text.replace("{date}", &today())
+    }
+
+    /// Writes a copy of the JPEG at `source` to `destination` with the watermark drawn on.
+    /// The copy is turned upright by the EXIF orientation first, as it does not keep the EXIF.
+    pub fn write_copy(&self, source: &Path, destination: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut decoder = image::ImageReader::open(source)?.with_guessed_format()?.into_decoder()?;
+        let orientation = decoder.orientation()?;
+        let mut image = image::DynamicImage::from_decoder(decoder)?;
+        image.apply_orientation(orientation);
+        let mut image = image.to_rgb8();
+        self.draw(&mut image);
+        let file = std::io::BufWriter::new(std::fs::File::create(destination)?);
+        image::codecs::jpeg::JpegEncoder::new_with_quality(file, self.quality).encode_image(&image)?;
+        Ok(())
+    }
+
+    fn draw(&self, image: &mut RgbImage) {
+        let text = self.expanded_text();
+        if text.trim().is_empty() {
+            return;
+        }
+        let font = FontRef::try_from_slice(epaint_default_fonts::UBUNTU_LIGHT).expect("the bundled font is valid");
+        let height = image.width().min(image.height()) as f32 * self.size_percent / 100.0;
+        let scale = PxScale::from(height.max(8.0));
+        let (text_width, text_height) = imageproc::drawing::text_size(scale, &font, &text);
+        let margin = (height * 0.5) as i64;
+        let (free_x, free_y) = (image.width() as i64 - text_width as i64, image.height() as i64 - text_height as i64);
+        let (x, y) = match self.position {
+            WatermarkPosition::TopLeft => (margin, margin),
+            WatermarkPosition::TopRight => (free_x - margin, margin),
+            WatermarkPosition::BottomLeft => (margin, free_y - margin),
+            WatermarkPosition::BottomRight => (free_x - margin, free_y - margin),
+            WatermarkPosition::Center => (free_x / 2, free_y / 2),
+        };
+
+        // Drawn fully opaque onto a copy, then blended back in, so the opacity applies evenly
+        let mut overlay = image.clone();
+        let shadow = (height * SHADOW_OFFSET).max(1.0) as i64;
+        imageproc::drawing::draw_text_mut(&mut overlay, Rgb([0, 0, 0]), (x + shadow) as i32, (y + shadow) as i32, scale, &font, &text);
+        imageproc::drawing::draw_text_mut(&mut overlay, Rgb([255, 255, 255]), x as i32, y as i32, scale, &font, &text);
+        let opacity = self.opacity.clamp(0.0, 1.0);
+        for (pixel, drawn) in image.pixels_mut().zip(overlay.pixels()) {
+            for (channel, drawn_channel) in pixel.0.iter_mut().zip(drawn.0) {
+                *channel = (*channel as f32 * (1.0 - opacity) + drawn_channel as f32 * opacity).round() as u8;
+            }
+        }
+    }
+}
+
+/// Today's date as YYYY-MM-DD in UTC.
+fn today() -> String {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs());
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Calendar date of a day counted from 1970-01-01 (Howard Hinnant's algorithm).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153; // March is 0
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{two_color_jpeg, TempDir};
+
+    #[test]
+    fn sideways_jpegs_are_watermarked_upright() {
+        let root = TempDir::new("watermark_orientation");
+        std::fs::write(root.join("sideways.jpg"), two_color_jpeg(32, 16, true)).unwrap();
+        let watermark = Watermark { enabled: true, ..Watermark::default() };
+        watermark.write_copy(&root.join("sideways.jpg"), &root.join("copy.jpg")).unwrap();
+        let copy = image::open(root.join("copy.jpg")).unwrap().to_rgb8();
+        assert_eq!(copy.dimensions(), (16, 32));
+        assert!(copy.get_pixel(8, 2).0[0] > 200, "red, the left half, turned to the top");
+    }
+}
@@ -0,0 +1,223 @@
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+use serde::{Serialize, Deserialize};
+
+const MAX_ZOOM: f32 = 16.0;
+const SCROLL_ZOOM_SPEED: f32 = 0.002; // Per point scrolled
+const MINIMAP_SIZE: f32 = 160.0; // Longer side
+const MINIMAP_MARGIN: f32 = 8.0;
+
+/// How the image is scaled to the viewer before any zooming.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum FitMode {
+    #[default]
+    Fit, // All of the image, as large as fits
+    Fill, // Covers the whole viewer, cropping what sticks out
+    Actual, // One image pixel to one screen pixel
+}
+
+impl FitMode {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Fit => Self::Fill,
+            Self::Fill => Self::Actual,
+            Self::Actual => Self::Fit,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Fit => "Fit",
+            Self::Fill => "Fill",
+            Self::Actual => "100 %",
+        }
+    }
+}
+
+/// How far the viewer is zoomed into the current image and where. Scrolling over the image
+/// zooms around the pointer, dragging pans and double-clicking goes back to the whole image.
+pub struct Zoom {
+    image: Option<PathBuf>, // The view is reset when another image comes up
+    pub mode: FitMode,
+    factor: f32, // On top of the fit mode's scale
+    center: egui::Pos2, // In texture coordinates, 0 to 1
+    view: egui::Vec2, // Share of the texture in view on each axis, as of the last layout
+}
+
+impl Default for Zoom {
+    fn default() -> Self {
+        Self {
+            image: None,
+            mode: FitMode::default(),
+            factor: 1.0,
+            center: egui::pos2(0.5, 0.5),
+            view: egui::Vec2::splat(1.0),
+        }
+    }
+}
+
+impl Zoom {
+    /// Starts over at the whole image when `image` is not the one zoomed into, in the fit
+    /// mode used so far if `keep_mode` and otherwise fitted.
+    pub fn follow(&mut self, image: &Path, keep_mode: bool) {
+        if self.image.as_deref() != Some(image) {
+            let mode = if keep_mode { self.mode } else { FitMode::Fit };
+            *self = Self { image: Some(image.to_path_buf()), mode, ..Self::default() };
+        }
+    }
+
+    /// Whether only part of the image is in view.
+    pub fn is_zoomed(&self) -> bool {
+        self.view.x < 1.0 || self.view.y < 1.0
+    }
+
+    pub fn reset(&mut self) {
+        self.factor = 1.0;
+        self.center = egui::pos2(0.5, 0.5);
+    }
+
+    /// The part of the texture in view.
+    pub fn uv_rect(&self) -> egui::Rect {
+        egui::Rect::from_center_size(self.center, self.view)
+    }
+
+    /// Size to draw a `texture_size` image at in `available` points, which also decides how
+    /// much of it is in view.
+    pub fn layout(&mut self, available: egui::Vec2, texture_size: [usize; 2], pixels_per_point: f32) -> egui::Vec2 {
+        let texture = egui::vec2(texture_size[0].max(1) as f32, texture_size[1].max(1) as f32);
+        let fit = available / texture;
+        let scale = match self.mode {
+            FitMode::Fit => fit.min_elem(),
+            FitMode::Fill => fit.max_elem(),
+            FitMode::Actual => 1.0 / pixels_per_point,
+        };
+        let full = texture * scale * self.factor;
+        let shown = full.min(available).max(egui::Vec2::splat(1.0));
+        self.view = (shown / full).min(egui::Vec2::splat(1.0));
+        self.center_on(self.center);
+        shown
+    }
+
+    /// Moves the view so that it is centered on `center` as far as the image edges allow.
+    pub fn center_on(&mut self, center: egui::Pos2) {
+        let half = self.view / 2.0;
+        self.center = egui::pos2(center.x.clamp(half.x, 1.0 - half.x), center.y.clamp(half.y, 1.0 - half.y));
+    }
+
+    /// Zooms by `change` keeping `anchor`, in texture coordinates, where it is on screen.
+    pub fn zoom_around(&mut self, change: f32, anchor: egui::Pos2) {
+        let factor = (self.factor * change).clamp(1.0, MAX_ZOOM);
+        let shrink = self.factor / factor;
+        let center = anchor - (anchor - self.center) * shrink;
+        self.view = (self.view * shrink).min(egui::Vec2::splat(1.0)); // Until the next layout works it out exactly
+        self.factor = factor;
+        self.center_on(center);
+    }
+
+    /// Zooms in until `region`, in texture coordinates, just fits the view and centers on it.
+    pub fn zoom_to(&mut self, region: egui::Rect) {
+        let unzoomed = self.view * self.factor; // Share in view at no zoom, as of the last layout
+        let fits = unzoomed / region.size().max(egui::Vec2::splat(f32::EPSILON));
+        self.factor = fits.min_elem().clamp(1.0, MAX_ZOOM);
+        self.view = (unzoomed / self.factor).min(egui::Vec2::splat(1.0)); // Until the next layout works it out exactly
+        self.center_on(region.center());
+    }
+
+    /// Applies scrolling, pinching and dragging on `response`, the image drawn at `uv_rect`.
+    pub fn handle_input(&mut self, ui: &egui::Ui, response: &egui::Response) {
+        let rect = response.rect;
+        if rect.width() <= 0.0 || rect.height() <= 0.0 {
+            return;
+        }
+        let uv = self.uv_rect();
+        if response.double_clicked() {
+            self.reset();
+            return;
+        }
+        if response.dragged() {
+            let delta = response.drag_delta() / rect.size() * uv.size();
+            self.center_on(self.center - delta);
+        }
+        if let Some(pointer) = response.hover_pos() {
+            let (scroll, pinch) = ui.input(|input| (input.smooth_scroll_delta.y, input.zoom_delta()));
+            let change = pinch * (scroll * SCROLL_ZOOM_SPEED).exp();
+            if change != 1.0 {
+                let anchor = uv.min + (pointer - rect.min) / rect.size() * uv.size();
+                self.zoom_around(change, anchor);
+            }
+        }
+    }
+
+    /// Draws the whole image small in the top right corner of `image_rect` with the part in
+    /// view outlined, while zoomed in. Clicking or dragging on it moves the view there.
+    pub fn paint_minimap(&mut self, ui: &egui::Ui, image_rect: egui::Rect, texture: &egui::TextureHandle) {
+        if !self.is_zoomed() {
+            return;
+        }
+        let [width, height] = texture.size();
+        let scale = MINIMAP_SIZE / width.max(height).max(1) as f32;
+        let size = egui::vec2(width as f32 * scale, height as f32 * scale);
+        let visible = image_rect.intersect(ui.clip_rect());
+        let map = egui::Rect::from_min_size(visible.right_top() + egui::vec2(-MINIMAP_MARGIN - size.x, MINIMAP_MARGIN), size);
+
+        let painter = ui.painter();
+        painter.rect_filled(map.expand(2.0), 3.0, egui::Color32::from_black_alpha(160));
+        painter.image(texture.id(), map, egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1.0, 1.0)), egui::Color32::WHITE);
+        let uv = self.uv_rect();
+        let viewport = egui::Rect::from_min_max(map.min + uv.min.to_vec2() * size, map.min + uv.max.to_vec2() * size);
+        painter.rect_stroke(viewport, 0.0, egui::Stroke::new(1.5, egui::Color32::YELLOW), egui::StrokeKind::Middle);
+
+        let response = ui.interact(map, ui.id().with("minimap"), egui::Sense::click_and_drag());
+        if let Some(pointer) = response.interact_pointer_pos() {
+            self.center_on(((pointer - map.min) / size).to_pos2());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zooming_stays_inside_the_image_and_resets_on_the_next_one() {
+        let mut zoom = Zoom::default();
+        zoom.follow(std::path::Path::new("a.jpg"), false);
+        assert!(!zoom.is_zoomed());
+        assert_eq!(zoom.uv_rect(), egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1.0, 1.0)));
+
+        zoom.zoom_around(4.0, egui::pos2(0.0, 0.0));
+        assert!(zoom.is_zoomed());
+        assert_eq!(zoom.uv_rect(), egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(0.25, 0.25)));
+        zoom.center_on(egui::pos2(1.0, 0.5));
+        assert_eq!(zoom.uv_rect(), egui::Rect::from_min_max(egui::pos2(0.75, 0.375), egui::pos2(1.0, 0.625)));
+
+        zoom.follow(std::path::Path::new("a.jpg"), false);
+        assert!(zoom.is_zoomed());
+        zoom.follow(std::path::Path::new("b.jpg"), false);
+        assert!(!zoom.is_zoomed());
+    }
+
+    #[test]
+    fn fill_crops_the_overflow_and_actual_pixels_ignores_the_viewer_size() {
+        let mut zoom = Zoom::default();
+        zoom.follow(std::path::Path::new("a.jpg"), false);
+        let viewer = egui::vec2(400.0, 400.0);
+        assert_eq!(zoom.layout(viewer, [800, 400], 1.0), egui::vec2(400.0, 200.0));
+        assert!(!zoom.is_zoomed());
+
+        zoom.mode = zoom.mode.next();
+        assert_eq!(zoom.mode, FitMode::Fill);
+        assert_eq!(zoom.layout(viewer, [800, 400], 1.0), viewer);
+        assert_eq!(zoom.uv_rect(), egui::Rect::from_min_max(egui::pos2(0.25, 0.0), egui::pos2(0.75, 1.0)));
+
+        zoom.mode = zoom.mode.next();
+        assert_eq!(zoom.layout(viewer, [800, 400], 2.0), egui::vec2(400.0, 200.0)); // 800 pixels at 2 per point
+        assert_eq!(zoom.layout(viewer, [200, 100], 1.0), egui::vec2(200.0, 100.0));
+
+        zoom.follow(std::path::Path::new("b.jpg"), true);
+        assert_eq!(zoom.mode, FitMode::Actual);
+        zoom.follow(std::path::Path::new("c.jpg"), false);
+        assert_eq!(zoom.mode, FitMode::Fit);
+    }
+}